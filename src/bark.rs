@@ -0,0 +1,139 @@
+use crate::{
+    error::Error,
+    library::Library,
+    model::{self, model_json},
+    random::Rng,
+};
+use articy::types::Model;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bark {
+    pub id: String,
+    pub speaker: String,
+    pub concept: String,
+    pub condition: Option<String>,
+    pub cooldown: Option<f64>,
+}
+
+// NOTE: Barks are DialogueFragments using a given template, played one at a time outside of any conversation
+#[derive(Debug, Default)]
+pub struct BarkPool {
+    barks: Vec<Bark>,
+    played: HashMap<String, f64>,
+    spoken: HashMap<String, f64>,
+    recent: HashMap<(String, String), Vec<String>>,
+    rng: Rng,
+}
+
+impl BarkPool {
+    // NOTE: `template` matches like it does for quests, `concept_property` names the property sorting barks into
+    //       concepts such as "Greeting" or "Hurt". The first input pin holds the bark's condition.
+    pub fn scan(library: &Library, template: &str, concept_property: &str) -> Result<Self, Error> {
+        let mut pool = BarkPool::default();
+
+        for model in library.all_models()? {
+            let speaker = match model {
+                Model::DialogueFragment { speaker, .. } => speaker.to_inner(),
+                _ => continue,
+            };
+
+            let json = model_json(model);
+            let is_bark = json["Type"] == template
+                || json["Template"]
+                    .as_object()
+                    .map_or(false, |features| features.contains_key(template));
+
+            if !is_bark {
+                continue;
+            }
+
+            pool.barks.push(Bark {
+                id: model.id().to_inner(),
+                speaker,
+                concept: model::string_property(&json, concept_property).unwrap_or_default(),
+                condition: json["Properties"]["InputPins"].as_array().and_then(|pins| {
+                    pins.iter()
+                        .filter_map(|pin| pin["Text"].as_str())
+                        .find(|text| !text.trim().is_empty())
+                        .map(str::to_owned)
+                }),
+                cooldown: model::number_property(&json, "Cooldown"),
+            });
+        }
+
+        Ok(pool)
+    }
+
+    pub fn barks(&self) -> &[Bark] {
+        &self.barks
+    }
+
+    // NOTE: Picks a bark that isn't cooling down and whose condition `passes`, preferring the ones that weren't among
+    //       the last `history` barks of the speaker for this concept. `now` and the cooldowns are in seconds.
+    pub fn pick(
+        &mut self,
+        speaker: &str,
+        concept: &str,
+        now: f64,
+        speaker_cooldown: f64,
+        history: usize,
+        mut passes: impl FnMut(&str) -> bool,
+    ) -> Option<String> {
+        if let Some(spoken) = self.spoken.get(speaker) {
+            if now - spoken < speaker_cooldown {
+                return None;
+            }
+        }
+
+        let candidates = self
+            .barks
+            .iter()
+            .filter(|bark| bark.speaker == speaker && bark.concept == concept)
+            .filter(|bark| match (bark.cooldown, self.played.get(&bark.id)) {
+                (Some(cooldown), Some(played)) => now - played >= cooldown,
+                _ => true,
+            })
+            .filter(|bark| bark.condition.as_deref().map_or(true, &mut passes))
+            .map(|bark| bark.id.to_owned())
+            .collect::<Vec<String>>();
+
+        let key = (speaker.to_owned(), concept.to_owned());
+        let recent = self.recent.entry(key).or_default();
+
+        let fresh = candidates
+            .iter()
+            .filter(|id| !recent.contains(id))
+            .collect::<Vec<&String>>();
+
+        // NOTE: With everything heard recently, the one that was heard the longest ago goes again
+        let id = if fresh.is_empty() {
+            candidates
+                .iter()
+                .min_by(|a, b| {
+                    let played = |id: &String| self.played.get(id).copied().unwrap_or_default();
+                    played(a).total_cmp(&played(b))
+                })?
+                .to_owned()
+        } else {
+            fresh[self.rng.below(fresh.len())].to_owned()
+        };
+
+        recent.retain(|other| *other != id);
+        recent.push(id.to_owned());
+        if recent.len() > history {
+            recent.drain(..recent.len() - history);
+        }
+
+        self.played.insert(id.to_owned(), now);
+        self.spoken.insert(speaker.to_owned(), now);
+
+        Some(id)
+    }
+
+    pub fn reset(&mut self) {
+        self.played.clear();
+        self.spoken.clear();
+        self.recent.clear();
+    }
+}
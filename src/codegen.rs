@@ -0,0 +1,172 @@
+use crate::{error::Error, library::Library, model::model_json};
+use std::collections::HashSet;
+
+// NOTE: A class of constants per kind of model, named after technical names and holding ids (or, for variables,
+//       the "Namespace.variable" name scripts use)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstantGroup {
+    pub name: &'static str,
+    pub constants: Vec<(Vec<String>, String)>,
+}
+
+pub fn id_constants(library: &Library) -> Result<Vec<ConstantGroup>, Error> {
+    let mut groups = vec![];
+
+    for (name, kind) in [
+        ("Dialogues", "Dialogue"),
+        ("Entities", "Entity"),
+        ("Folders", "UserFolder"),
+    ] {
+        let constants = library
+            .models_of_type(kind)?
+            .into_iter()
+            .map(|model| {
+                let id = model.id().to_inner();
+                let properties = &model_json(model)["Properties"];
+                let name = ["TechnicalName", "DisplayName"]
+                    .into_iter()
+                    .find_map(|property| {
+                        properties[property]
+                            .as_str()
+                            .filter(|name| !name.is_empty())
+                    })
+                    .unwrap_or(&id);
+
+                (words(name), id.to_owned())
+            })
+            .collect();
+
+        groups.push(ConstantGroup { name, constants });
+    }
+
+    let constants = library
+        .global_variables()?
+        .iter()
+        .flat_map(|namespace| {
+            namespace.variables.iter().map(|variable| {
+                let name = format!("{}.{}", namespace.name, variable.name);
+                (words(&name), name)
+            })
+        })
+        .collect();
+
+    groups.push(ConstantGroup {
+        name: "Variables",
+        constants,
+    });
+
+    Ok(groups)
+}
+
+pub fn to_gdscript(groups: &[ConstantGroup]) -> String {
+    let mut script =
+        "# Generated from the Articy export, changes will be overwritten\nclass_name ArticyIds\n"
+            .to_owned();
+
+    for group in groups {
+        script.push_str(&format!("\n\nclass {}:\n", group.name));
+        if group.constants.is_empty() {
+            script.push_str("\tpass\n");
+        }
+
+        for (name, value) in unique(&group.constants, |words| {
+            words
+                .iter()
+                .map(|word| word.to_uppercase())
+                .collect::<Vec<String>>()
+                .join("_")
+        }) {
+            script.push_str(&format!("\tconst {name} = {value:?}\n"));
+        }
+    }
+
+    script
+}
+
+pub fn to_csharp(groups: &[ConstantGroup]) -> String {
+    let mut script = "// Generated from the Articy export, changes will be overwritten\npublic static class ArticyIds\n{\n".to_owned();
+
+    for (position, group) in groups.iter().enumerate() {
+        if position > 0 {
+            script.push('\n');
+        }
+
+        script.push_str(&format!("    public static class {}\n    {{\n", group.name));
+        for (name, value) in unique(&group.constants, |words| {
+            words
+                .iter()
+                .map(|word| {
+                    let mut characters = word.chars();
+                    characters.next().map_or_else(String::new, |first| {
+                        first.to_uppercase().chain(characters).collect()
+                    })
+                })
+                .collect()
+        }) {
+            script.push_str(&format!(
+                "        public const string {name} = {value:?};\n"
+            ));
+        }
+        script.push_str("    }\n");
+    }
+
+    script.push_str("}\n");
+    script
+}
+
+// NOTE: Splits on anything that isn't a letter or digit and on camel case humps, e.g. "Chr_ManfredV2" gives
+//       ["chr", "manfred", "v2"]
+pub fn words(name: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut current = String::new();
+    let mut previous_lowercase = false;
+
+    for character in name.chars() {
+        let boundary =
+            !character.is_alphanumeric() || (character.is_uppercase() && previous_lowercase);
+        if boundary && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+
+        if !character.is_alphanumeric() {
+            previous_lowercase = false;
+            continue;
+        }
+
+        previous_lowercase = character.is_lowercase() || character.is_ascii_digit();
+        current.extend(character.to_lowercase());
+    }
+
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
+
+// NOTE: Names are made valid identifiers, clashes get numbered in the order they came in
+fn unique(
+    constants: &[(Vec<String>, String)],
+    style: impl Fn(&[String]) -> String,
+) -> Vec<(String, String)> {
+    let mut taken = HashSet::new();
+
+    constants
+        .iter()
+        .map(|(words, value)| {
+            let mut name = style(words);
+            if name.is_empty() || name.starts_with(|character: char| character.is_ascii_digit()) {
+                name.insert(0, '_');
+            }
+
+            let mut candidate = name.to_owned();
+            let mut counter = 2;
+            while !taken.insert(candidate.to_owned()) {
+                candidate = format!("{name}_{counter}");
+                counter += 1;
+            }
+
+            (candidate, value.to_owned())
+        })
+        .collect()
+}
@@ -0,0 +1,63 @@
+use crate::model::{model_json, ModelIndex};
+use articy::types::File as ArticyFile;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+
+// NOTE: Everything a session passed through, kept across dialogues for QA to check every branch got played
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    visited: HashSet<String>,
+    choices: BTreeMap<String, u64>,
+}
+
+impl Coverage {
+    pub fn visit(&mut self, id: &str) {
+        if !self.visited.contains(id) {
+            self.visited.insert(id.to_owned());
+        }
+    }
+
+    pub fn choose(&mut self, id: &str) {
+        *self.choices.entry(id.to_owned()).or_default() += 1;
+    }
+
+    // NOTE: Totals count every node inside a dialogue, nested fragments included
+    pub fn report(&self, file: &ArticyFile, index: &ModelIndex) -> Value {
+        let mut dialogues = Map::new();
+
+        for dialogue in file.get_models_of_type("Dialogue") {
+            let id = dialogue.id().to_inner();
+
+            let mut nodes = vec![];
+            let mut pending = index.children(&id).to_vec();
+            while let Some(node) = pending.pop() {
+                pending.extend(index.children(&node).iter().cloned());
+                nodes.push(node);
+            }
+            nodes.sort();
+
+            let (visited, unvisited): (Vec<String>, Vec<String>) = nodes
+                .into_iter()
+                .partition(|node| self.visited.contains(node));
+
+            dialogues.insert(
+                id,
+                json!({
+                    "name": model_json(dialogue)["Properties"]["DisplayName"],
+                    "visited": visited.len(),
+                    "total": visited.len() + unvisited.len(),
+                    "unvisited": unvisited,
+                }),
+            );
+        }
+
+        let mut visited = self.visited.iter().cloned().collect::<Vec<String>>();
+        visited.sort();
+
+        json!({
+            "dialogues": dialogues,
+            "choices": self.choices,
+            "visited": visited,
+        })
+    }
+}
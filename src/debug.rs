@@ -0,0 +1,99 @@
+use crate::error::Error;
+use serde_json::Value;
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+// NOTE: Line delimited JSON over TCP, never blocks so it can be polled every frame
+#[derive(Debug)]
+pub struct DebugServer {
+    listener: TcpListener,
+    clients: Vec<Client>,
+}
+
+#[derive(Debug)]
+struct Client {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    closed: bool,
+}
+
+impl DebugServer {
+    pub fn bind(address: &str) -> Result<Self, Error> {
+        let listener = TcpListener::bind(address).map_err(|error| {
+            Error::DebugServerError(format!("can't listen on {address}: {error}"))
+        })?;
+
+        listener
+            .set_nonblocking(true)
+            .map_err(|error| Error::DebugServerError(error.to_string()))?;
+
+        Ok(DebugServer {
+            listener,
+            clients: vec![],
+        })
+    }
+
+    // NOTE: Yields every complete request with the client it came from. Clients that left are only dropped on the next
+    //       poll, so the positions stay valid for `respond` until then
+    pub fn poll(&mut self) -> Vec<(usize, Result<Value, Error>)> {
+        self.clients.retain(|client| !client.closed);
+
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                self.clients.push(Client {
+                    stream,
+                    buffer: vec![],
+                    closed: false,
+                });
+            }
+        }
+
+        let mut requests = vec![];
+
+        for (position, client) in self.clients.iter_mut().enumerate() {
+            let mut chunk = [0; 4096];
+
+            loop {
+                match client.stream.read(&mut chunk) {
+                    Ok(0) => {
+                        client.closed = true;
+                        break;
+                    }
+                    Ok(read) => client.buffer.extend_from_slice(&chunk[..read]),
+                    Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                    Err(_) => {
+                        client.closed = true;
+                        break;
+                    }
+                }
+            }
+
+            while let Some(end) = client.buffer.iter().position(|byte| *byte == b'\n') {
+                let line = client.buffer.drain(..=end).collect::<Vec<u8>>();
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+
+                let request = serde_json::from_slice::<Value>(&line)
+                    .map_err(|error| Error::DebugServerError(format!("invalid request: {error}")));
+                requests.push((position, request));
+            }
+        }
+
+        requests
+    }
+
+    pub fn respond(&mut self, client: usize, message: &Value) {
+        if let Some(client) = self.clients.get_mut(client) {
+            writeln!(client.stream, "{message}").ok();
+        }
+    }
+
+    pub fn broadcast(&mut self, message: &Value) {
+        for client in &mut self.clients {
+            writeln!(client.stream, "{message}").ok();
+        }
+    }
+}
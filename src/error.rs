@@ -0,0 +1,154 @@
+use crate::export;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    DatabaseNotSetup,
+    InterpreterNotSetup,
+    FailedToSetState(String),
+    FailedToGetState(String),
+    ModelNotFound(String),
+    NotAUserFolder(String),
+    HierarchyNotFound(String),
+    NodeNotFound(String),
+    NotADatabase(String),
+    InvalidResource(String),
+    UnsupportedType(String),
+    PackageNotFound(String),
+    ChoiceNotAvailable(i64),
+    ScriptError(String),
+    FunctionNotRegistered(String),
+    LocaleNotFound(String),
+    NotAnAsset(String),
+    InvalidOption(String),
+    ConversationNotFound(i64),
+    DialogueStackEmpty,
+    FlowLoop(Vec<String>),
+    PropertyNotFound(String),
+    NoFallbackChoice,
+    DebugServerError(String),
+    NotADialogue(String),
+    NotALocation(String),
+    // NOTE: Line and column of where parsing the export failed
+    InvalidExport(usize, usize, String),
+    UnsupportedExportVersion(String),
+    IncompatibleSave(String),
+    TemplateNotFound(String),
+    Panicked(String),
+    ArticyError(articy::types::Error),
+}
+
+impl Error {
+    pub fn code(&self) -> i64 {
+        match self {
+            Error::DatabaseNotSetup => 1,
+            Error::InterpreterNotSetup => 2,
+            Error::FailedToSetState(..) => 3,
+            Error::FailedToGetState(..) => 4,
+            Error::ModelNotFound(..) => 5,
+            Error::NotAUserFolder(..) => 6,
+            Error::HierarchyNotFound(..) => 7,
+            Error::NodeNotFound(..) => 8,
+            Error::NotADatabase(..) => 9,
+            Error::InvalidResource(..) => 10,
+            Error::UnsupportedType(..) => 11,
+            Error::ArticyError(..) => 12,
+            Error::PackageNotFound(..) => 13,
+            Error::ChoiceNotAvailable(..) => 14,
+            Error::ScriptError(..) => 15,
+            Error::FunctionNotRegistered(..) => 16,
+            Error::LocaleNotFound(..) => 17,
+            Error::NotAnAsset(..) => 18,
+            Error::InvalidOption(..) => 19,
+            Error::ConversationNotFound(..) => 20,
+            Error::DialogueStackEmpty => 21,
+            Error::FlowLoop(..) => 22,
+            Error::PropertyNotFound(..) => 23,
+            Error::NoFallbackChoice => 24,
+            Error::DebugServerError(..) => 25,
+            Error::NotADialogue(..) => 26,
+            Error::InvalidExport(..) => 27,
+            Error::UnsupportedExportVersion(..) => 28,
+            Error::Panicked(..) => 29,
+            Error::IncompatibleSave(..) => 30,
+            Error::TemplateNotFound(..) => 31,
+            Error::NotALocation(..) => 32,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::DatabaseNotSetup => write!(formatter, "the Articy Database has no file loaded"),
+            Error::InterpreterNotSetup => write!(
+                formatter,
+                "the Articy Interpreter has no database set, call `set_database` first"
+            ),
+            Error::FailedToSetState(key) => write!(formatter, "failed to set state {key:?}"),
+            Error::FailedToGetState(key) => write!(formatter, "failed to get state {key:?}"),
+            Error::ModelNotFound(id) => write!(formatter, "no model found with id {id:?}"),
+            Error::NotAUserFolder(id) => write!(
+                formatter,
+                "{id:?} isn't a UserFolder, therefor can't get entities"
+            ),
+            Error::HierarchyNotFound(id) => {
+                write!(formatter, "no hierarchy found for model {id:?}")
+            }
+            Error::NodeNotFound(path) => write!(formatter, "no node found at path {path:?}"),
+            Error::NotADatabase(path) => write!(
+                formatter,
+                "node at path {path:?} isn't a Database from the Articy integration"
+            ),
+            Error::InvalidResource(reason) => write!(formatter, "invalid resource: {reason}"),
+            Error::UnsupportedType(kind) => {
+                write!(
+                    formatter,
+                    "type {kind} not supported for serialisation in Articy"
+                )
+            }
+            Error::PackageNotFound(name) => write!(formatter, "no package named {name:?}"),
+            Error::ChoiceNotAvailable(index) => {
+                write!(formatter, "no choice available at index {index}")
+            }
+            Error::ScriptError(reason) => write!(formatter, "script error: {reason}"),
+            Error::FunctionNotRegistered(name) => write!(
+                formatter,
+                "no function registered as {name:?}, call `register_function` first"
+            ),
+            Error::LocaleNotFound(locale) => {
+                write!(formatter, "no localization loaded for locale {locale:?}")
+            }
+            Error::NotAnAsset(id) => write!(formatter, "{id:?} isn't an Asset"),
+            Error::InvalidOption(option) => write!(formatter, "invalid option {option:?}"),
+            Error::ConversationNotFound(handle) => {
+                write!(formatter, "no active conversation with handle {handle}")
+            }
+            Error::DialogueStackEmpty => write!(formatter, "no pushed dialogue to return from"),
+            Error::FlowLoop(ids) => write!(
+                formatter,
+                "flow kept looping through {ids:?}, stopped advancing"
+            ),
+            Error::PropertyNotFound(path) => write!(formatter, "no property found at {path:?}"),
+            Error::NoFallbackChoice => {
+                write!(formatter, "none of the available choices is a fallback")
+            }
+            Error::DebugServerError(reason) => write!(formatter, "debug server error: {reason}"),
+            Error::NotADialogue(id) => write!(formatter, "{id:?} isn't a Dialogue"),
+            Error::NotALocation(id) => write!(formatter, "{id:?} isn't a Location"),
+            Error::InvalidExport(line, column, reason) => write!(
+                formatter,
+                "export can't be parsed at line {line}, column {column}: {reason}"
+            ),
+            Error::UnsupportedExportVersion(version) => write!(
+                formatter,
+                "export version {version:?} isn't supported, supported are {:?}",
+                export::SUPPORTED_EXPORT_VERSIONS
+            ),
+            Error::IncompatibleSave(reason) => write!(formatter, "incompatible save: {reason}"),
+            Error::TemplateNotFound(name) => write!(formatter, "no template named {name:?}"),
+            Error::Panicked(message) => write!(formatter, "panicked: {message}"),
+            Error::ArticyError(error) => write!(formatter, "articy-rs returned {error:?}"),
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use crate::error::Error;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+// NOTE: The parts of an export articy-rs doesn't keep around, read separately from the same buffer
+#[derive(Debug, Clone, Default)]
+pub struct ExportInfo {
+    pub global_variables: Vec<VariableNamespace>,
+    enums: HashMap<String, EnumDefinition>,
+    // NOTE: Keyed by (feature, property), holding the name of the property's type
+    property_types: HashMap<(String, String), String>,
+    features: HashMap<String, FeatureDefinition>,
+    templates: BTreeMap<String, TemplateDefinition>,
+    pub metadata: ExportMetadata,
+    // NOTE: Of the whole export as it was loaded, so saves can tell whether they were made against the same one
+    pub hash: String,
+}
+
+// NOTE: Not every version of Articy writes all of these, missing ones are left out
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportMetadata {
+    pub export_version: Option<String>,
+    pub articy_version: Option<String>,
+    pub export_date: Option<String>,
+    pub project_name: Option<String>,
+    pub project_technical_name: Option<String>,
+    pub project_guid: Option<String>,
+}
+
+// NOTE: Export format versions this integration is written against, others might load but can break in subtle ways
+pub const SUPPORTED_EXPORT_VERSIONS: [&str; 1] = ["1.0"];
+
+#[derive(Debug, Clone, Default)]
+pub struct EnumDefinition {
+    pub values: HashMap<i64, String>,
+    pub display_names: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemplateDefinition {
+    pub name: String,
+    pub display_name: String,
+    // NOTE: What the template is built on, e.g. "Entity" or "DialogueFragment"
+    pub class: String,
+    pub features: Vec<FeatureDefinition>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeatureDefinition {
+    pub name: String,
+    pub display_name: String,
+    pub properties: Vec<PropertyDefinition>,
+}
+
+// NOTE: `constraints` holds whatever else Articy exported for the property, e.g. value ranges or an enum's values
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PropertyDefinition {
+    pub name: String,
+    pub kind: String,
+    pub display_name: String,
+    pub default: Value,
+    pub constraints: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VariableNamespace {
+    pub name: String,
+    pub description: String,
+    pub variables: Vec<VariableDefinition>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VariableDefinition {
+    pub name: String,
+    pub kind: String,
+    pub default: Value,
+    pub description: String,
+}
+
+impl ExportInfo {
+    pub fn from_buffer(buffer: &[u8]) -> Self {
+        let json = match serde_json::from_slice::<Value>(buffer) {
+            Ok(json) => json,
+            Err(_) => return Default::default(),
+        };
+
+        let mut info = ExportInfo {
+            global_variables: json["GlobalVariables"]
+                .as_array()
+                .map(|namespaces| {
+                    namespaces
+                        .iter()
+                        .map(VariableNamespace::from_json)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            metadata: ExportMetadata::from_json(&json),
+            ..Default::default()
+        };
+
+        for definition in json["ObjectDefinitions"].as_array().into_iter().flatten() {
+            let kind = string_of(&definition["Type"]);
+
+            match definition["Class"].as_str() {
+                Some("Enum") => {
+                    let values = definition["Values"]
+                        .as_object()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|(name, value)| Some((value.as_i64()?, name.to_owned())))
+                        .collect();
+                    let display_names = definition["DisplayNames"]
+                        .as_object()
+                        .into_iter()
+                        .flatten()
+                        .map(|(name, display_name)| (name.to_owned(), string_of(display_name)))
+                        .collect();
+
+                    info.enums.insert(
+                        kind,
+                        EnumDefinition {
+                            values,
+                            display_names,
+                        },
+                    );
+                }
+                Some("Feature") => {
+                    for property in definition["Properties"].as_array().into_iter().flatten() {
+                        info.property_types.insert(
+                            (kind.to_owned(), string_of(&property["Property"])),
+                            string_of(&property["Type"]),
+                        );
+                    }
+
+                    info.features
+                        .insert(kind.to_owned(), FeatureDefinition::from_json(definition));
+                }
+                _ => {}
+            }
+        }
+
+        // NOTE: After the features, as templates without their properties listed fall back on the feature's own
+        for definition in json["ObjectDefinitions"].as_array().into_iter().flatten() {
+            let template = &definition["Template"];
+            if !template.is_object() {
+                continue;
+            }
+
+            let name = match string_of(&template["TechnicalName"]) {
+                name if name.is_empty() => string_of(&definition["Type"]),
+                name => name,
+            };
+            let features = template["Features"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|feature| {
+                    let name = string_of(&feature["TechnicalName"]);
+                    match (feature["Properties"].is_array(), info.features.get(&name)) {
+                        (false, Some(definition)) => definition.to_owned(),
+                        _ => FeatureDefinition::from_json(feature),
+                    }
+                })
+                .collect();
+
+            info.templates.insert(
+                name.to_owned(),
+                TemplateDefinition {
+                    name,
+                    display_name: string_of(&template["DisplayName"]),
+                    class: string_of(&definition["Class"]),
+                    features,
+                },
+            );
+        }
+
+        for template in info.templates.values_mut() {
+            for property in template
+                .features
+                .iter_mut()
+                .flat_map(|feature| feature.properties.iter_mut())
+            {
+                if let Some(definition) = info.enums.get(&property.kind) {
+                    let mut values = definition.values.iter().collect::<Vec<(&i64, &String)>>();
+                    values.sort();
+
+                    property.constraints.insert(
+                        "values".to_owned(),
+                        values
+                            .into_iter()
+                            .map(|(value, name)| serde_json::json!({"value": value, "name": name}))
+                            .collect(),
+                    );
+                }
+            }
+        }
+
+        info
+    }
+
+    pub fn template_names(&self) -> Vec<String> {
+        self.templates.keys().cloned().collect()
+    }
+
+    pub fn template(&self, name: &str) -> Option<&TemplateDefinition> {
+        self.templates.get(name)
+    }
+
+    pub fn enum_of(&self, feature: &str, property: &str) -> Option<&EnumDefinition> {
+        self.property_types
+            .get(&(feature.to_owned(), property.to_owned()))
+            .and_then(|kind| self.enums.get(kind))
+    }
+
+    pub fn is_color(&self, feature: &str, property: &str) -> bool {
+        self.property_types
+            .get(&(feature.to_owned(), property.to_owned()))
+            .map_or(false, |kind| kind.eq_ignore_ascii_case("color"))
+    }
+
+    // NOTE: Swaps enum values in a template (`{Feature: {Property: value}}`) for `{value, name, display_name}`
+    pub fn resolve_enums(&self, template: &mut Value) {
+        if self.enums.is_empty() {
+            return;
+        }
+
+        for (feature, properties) in template.as_object_mut().into_iter().flatten() {
+            for (property, value) in properties.as_object_mut().into_iter().flatten() {
+                let (definition, number) = match (self.enum_of(feature, property), value.as_i64()) {
+                    (Some(definition), Some(number)) => (definition, number),
+                    _ => continue,
+                };
+
+                let name = definition.values.get(&number).cloned().unwrap_or_default();
+                let display_name = definition
+                    .display_names
+                    .get(&name)
+                    .cloned()
+                    .unwrap_or_else(|| name.to_owned());
+
+                *value = serde_json::json!({
+                    "value": number,
+                    "name": name,
+                    "display_name": display_name,
+                });
+            }
+        }
+    }
+}
+
+impl FeatureDefinition {
+    // NOTE: Takes both a feature's own definition and one listed in a template
+    fn from_json(json: &Value) -> Self {
+        let name = match string_of(&json["TechnicalName"]) {
+            name if name.is_empty() => string_of(&json["Type"]),
+            name => name,
+        };
+
+        FeatureDefinition {
+            name,
+            display_name: string_of(&json["DisplayName"]),
+            properties: json["Properties"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(PropertyDefinition::from_json)
+                .collect(),
+        }
+    }
+}
+
+impl PropertyDefinition {
+    fn from_json(json: &Value) -> Self {
+        let mut constraints = json.as_object().cloned().unwrap_or_default();
+        let mut take = |key: &str| constraints.remove(key).unwrap_or_default();
+
+        PropertyDefinition {
+            name: string_of(&take("Property")),
+            kind: string_of(&take("Type")),
+            display_name: string_of(&take("DisplayName")),
+            default: take("DefaultValue"),
+            constraints,
+        }
+    }
+}
+
+impl VariableNamespace {
+    fn from_json(json: &Value) -> Self {
+        VariableNamespace {
+            name: string_of(&json["Namespace"]),
+            description: string_of(&json["Description"]),
+            variables: json["Variables"]
+                .as_array()
+                .map(|variables| {
+                    variables
+                        .iter()
+                        .map(VariableDefinition::from_json)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl VariableDefinition {
+    fn from_json(json: &Value) -> Self {
+        let kind = string_of(&json["Type"]);
+
+        // NOTE: Articy exports defaults as strings, e.g. "False" or "12"
+        let default = match (kind.as_str(), &json["Value"]) {
+            ("Boolean", Value::String(value)) => Value::Bool(value.eq_ignore_ascii_case("true")),
+            ("Integer", Value::String(value)) => {
+                value.trim().parse::<i64>().map_or(Value::Null, Value::from)
+            }
+            (_, value) => value.to_owned(),
+        };
+
+        VariableDefinition {
+            name: string_of(&json["Variable"]),
+            kind,
+            default,
+            description: string_of(&json["Description"]),
+        }
+    }
+}
+
+// NOTE: FNV-1a, which unlike the std hasher stays the same between builds so it can be stored
+pub fn hash(buffer: &[u8]) -> String {
+    let hash = buffer.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    format!("{hash:016x}")
+}
+
+// NOTE: Packages are added, replacing ones with the same name. Variables and definitions the export already has win,
+//       and the hierarchy of `additional` is hung under the root of `base`. Gives back the names of the added packages.
+pub fn merge(base: &mut Value, mut additional: Value) -> Result<Vec<String>, Error> {
+    let mut added = vec![];
+
+    for package in take_array(&mut additional, "Packages") {
+        let name = string_of(&package["Name"]);
+        let packages = array_of(base, "Packages")?;
+
+        packages.retain(|existing| existing["Name"] != name.as_str());
+        packages.push(package);
+        added.push(name);
+    }
+
+    merge_by_key(base, &mut additional, "GlobalVariables", "Namespace")?;
+    merge_by_key(base, &mut additional, "ObjectDefinitions", "Type")?;
+
+    let children = take_array(&mut additional["Hierarchy"], "Children");
+    if !children.is_empty() {
+        array_of(&mut base["Hierarchy"], "Children")?.extend(children);
+    }
+
+    Ok(added)
+}
+
+fn merge_by_key(
+    base: &mut Value,
+    additional: &mut Value,
+    field: &str,
+    key: &str,
+) -> Result<(), Error> {
+    for entry in take_array(additional, field) {
+        let existing = array_of(base, field)?;
+
+        if !existing.iter().any(|other| other[key] == entry[key]) {
+            existing.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+fn take_array(value: &mut Value, field: &str) -> Vec<Value> {
+    match value.get_mut(field).map(Value::take) {
+        Some(Value::Array(values)) => values,
+        _ => vec![],
+    }
+}
+
+fn array_of<'a>(value: &'a mut Value, field: &str) -> Result<&'a mut Vec<Value>, Error> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| Error::InvalidResource(format!("export can't hold {field:?}")))?;
+
+    object
+        .entry(field)
+        .or_insert_with(|| Value::Array(vec![]))
+        .as_array_mut()
+        .ok_or_else(|| Error::InvalidResource(format!("export's {field:?} isn't a list")))
+}
+
+impl ExportMetadata {
+    fn from_json(json: &Value) -> Self {
+        let settings = &json["Settings"];
+        let project = &json["Project"];
+        let text = |value: &Value| value.as_str().map(str::to_owned);
+
+        ExportMetadata {
+            export_version: text(&settings["ExportVersion"]),
+            articy_version: text(&settings["ArticyVersion"]),
+            export_date: text(&settings["ExportDate"]),
+            project_name: text(&project["Name"]),
+            project_technical_name: text(&project["TechnicalName"]),
+            project_guid: text(&project["Guid"]),
+        }
+    }
+
+    // NOTE: An export without a version is given the benefit of the doubt
+    pub fn is_supported(&self) -> bool {
+        self.export_version
+            .as_deref()
+            .map_or(true, |version| SUPPORTED_EXPORT_VERSIONS.contains(&version))
+    }
+}
+
+fn string_of(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_owned()
+}
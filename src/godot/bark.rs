@@ -0,0 +1,183 @@
+use super::{
+    convert::line_to_dictionary,
+    database::{connect_unloaded, is_database, with_database},
+    interpreter::{get_database, Interpreter},
+    report,
+};
+use crate::{
+    bark::BarkPool,
+    error::Error,
+    script,
+    session::{Line, Session},
+};
+use gdnative::{api::OS, prelude::*};
+
+// NOTE: Plays single template-tagged fragments on request, e.g. guards greeting the player when passing by
+#[derive(NativeClass)]
+#[inherit(Node)]
+#[register_with(Self::register_signals)]
+pub struct BarkManager {
+    #[property]
+    database_path: Option<NodePath>,
+    // NOTE: The Interpreter whose variables the bark conditions are checked against, without one they get their own
+    #[property]
+    interpreter_path: Option<NodePath>,
+    // NOTE: The exported type of the bark fragments, or a feature of their template
+    #[property]
+    template: String,
+    #[property]
+    concept_property: String,
+    // NOTE: Seconds a speaker stays quiet after barking
+    #[property]
+    cooldown: f64,
+    // NOTE: How many of a speaker's last barks per concept are avoided when there's something else to say
+    #[property]
+    history_size: i64,
+    pool: BarkPool,
+    session: Option<Session>,
+}
+
+#[methods]
+impl BarkManager {
+    fn new(_base: &Node) -> Self {
+        BarkManager {
+            database_path: None,
+            interpreter_path: None,
+            template: "Bark".to_owned(),
+            concept_property: "Concept".to_owned(),
+            cooldown: 0.0,
+            history_size: 3,
+            pool: BarkPool::default(),
+            session: None,
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder
+            .signal("bark")
+            .with_param("line", VariantType::Dictionary)
+            .done();
+
+        builder
+            .signal("error")
+            .with_param("code", VariantType::I64)
+            .with_param("message", VariantType::GodotString)
+            .done();
+    }
+
+    #[method]
+    fn _ready(&mut self, #[base] owner: &Node) {
+        if self.database_path.is_some() {
+            self.scan(owner);
+        }
+    }
+
+    // NOTE: Scanning again forgets the cooldowns and history
+    #[method]
+    fn scan(&mut self, #[base] owner: &Node) -> bool {
+        let scanned = match &self.database_path {
+            Some(path) => with_database(owner, path, |library| {
+                BarkPool::scan(library, &self.template, &self.concept_property)
+            })
+            .and_then(|pool| Ok((pool, Session::new(get_database(owner, path)?)))),
+            None => Err(Error::DatabaseNotSetup),
+        };
+
+        match report(owner, scanned) {
+            Some((pool, session)) => {
+                self.pool = pool;
+                self.session = Some(session);
+
+                if let Some(path) = &self.database_path {
+                    report(
+                        owner,
+                        connect_unloaded(owner, path, "_on_database_unloaded"),
+                    );
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    // NOTE: Returns whether `entity_id` had anything to say about `concept`
+    #[method]
+    fn request_bark(&mut self, #[base] owner: &Node, entity_id: String, concept: String) -> bool {
+        let now = OS::godot_singleton().get_ticks_msec() as f64 / 1000.0;
+
+        let line = match self.interpreter_path.clone() {
+            Some(path) => interpreter(owner, &path).and_then(|interpreter| {
+                interpreter
+                    .map_mut(|interpreter, _| {
+                        let session = interpreter.session()?;
+                        Ok(self.pick(session, &entity_id, &concept, now))
+                    })
+                    .map_err(|error| Error::InvalidOption(format!("{error:?}")))?
+            }),
+            None => {
+                let mut session = self.session.take();
+                let line = session
+                    .as_mut()
+                    .ok_or(Error::DatabaseNotSetup)
+                    .map(|session| self.pick(session, &entity_id, &concept, now));
+
+                self.session = session;
+                line
+            }
+        };
+
+        match report(owner, line).flatten() {
+            Some(line) => {
+                owner.emit_signal("bark", &[line_to_dictionary(&line).owned_to_variant()]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[method]
+    fn reset(&mut self) {
+        self.pool.reset();
+    }
+
+    #[method]
+    fn _on_database_unloaded(&mut self, #[base] owner: &Node, database: Ref<Node>) {
+        if is_database(owner, &self.database_path, database) {
+            self.pool = BarkPool::default();
+            self.session = None;
+        }
+    }
+
+    fn pick(
+        &mut self,
+        session: &mut Session,
+        speaker: &str,
+        concept: &str,
+        now: f64,
+    ) -> Option<Line> {
+        let id = self.pool.pick(
+            speaker,
+            concept,
+            now,
+            self.cooldown,
+            self.history_size.max(0) as usize,
+            |condition| {
+                session
+                    .evaluate(condition)
+                    .map_or(false, |value| script::is_truthy(&value))
+            },
+        )?;
+
+        session.line(&id)
+    }
+}
+
+fn interpreter<'a>(owner: &'a Node, path: &NodePath) -> Result<TInstance<'a, Interpreter>, Error> {
+    let node = owner
+        .get_node(path.to_godot_string())
+        .ok_or_else(|| Error::NodeNotFound(path.to_string()))?;
+
+    unsafe { node.assume_safe() }
+        .cast_instance::<Interpreter>()
+        .ok_or_else(|| Error::InvalidOption(format!("{path:?} isn't an Interpreter")))
+}
@@ -0,0 +1,557 @@
+use super::serializer;
+use crate::{
+    codegen,
+    error::Error,
+    export::{ExportMetadata, TemplateDefinition},
+    graph::FlowGraph,
+    library::{HierarchyNode, Reference, SearchMatch},
+    location::Location,
+    model::{self, Connection},
+    quest::Quest,
+    session::{Choice, HistoryEntry, Line},
+    validation::Issue,
+};
+use articy::types::Model;
+use gdnative::api::AudioStream;
+use gdnative::prelude::*;
+use serde_json::{Map, Number, Value};
+
+// NOTE: The flag adds a "layout" dictionary with the `position`, `size` and `color` of the node in Articy's flow editor
+pub struct ArticyModel<'a>(pub &'a Model, pub bool);
+
+impl ToVariant for ArticyModel<'_> {
+    fn to_variant(&self) -> Variant {
+        let variant = match self.0 {
+            Model::Custom(..) => json_to_variant(&model::model_value(self.0)),
+            model => serializer::to_variant(model).expect("articy-rs models to be serializable"),
+        };
+
+        match (self.1, variant.to::<Dictionary>()) {
+            (true, Some(dictionary)) => {
+                let dictionary = dictionary.duplicate();
+                dictionary.insert("layout", layout_to_dictionary(self.0));
+                dictionary.owned_to_variant()
+            }
+            _ => variant,
+        }
+    }
+}
+
+fn layout_to_dictionary(model: &Model) -> Dictionary<Unique> {
+    let json = model::model_json(model);
+    let properties = &json["Properties"];
+    let dictionary = Dictionary::new();
+
+    if let (Some(x), Some(y)) = (
+        properties["Position"]["x"].as_f64(),
+        properties["Position"]["y"].as_f64(),
+    ) {
+        dictionary.insert("position", Vector2::new(x as f32, y as f32));
+    }
+
+    if let (Some(width), Some(height)) = (
+        properties["Size"]["w"].as_f64(),
+        properties["Size"]["h"].as_f64(),
+    ) {
+        dictionary.insert("size", Vector2::new(width as f32, height as f32));
+    }
+
+    if let Some([r, g, b, a]) = model::json_color(&properties["Color"]) {
+        dictionary.insert("color", Color::from_rgba(r, g, b, a));
+    }
+
+    dictionary
+}
+
+// NOTE: Only a model's own "Color" becomes a `Color`, other objects that happen to have r/g/b keys stay
+//       dictionaries. Template properties defined as colors go through `property_to_variant`.
+pub fn json_to_variant(value: &Value) -> Variant {
+    match value {
+        Value::Null => Variant::nil(),
+        Value::Bool(bool) => bool.to_variant(),
+        Value::Number(number) => match number.as_i64() {
+            Some(int) => int.to_variant(),
+            None => number.as_f64().unwrap_or_default().to_variant(),
+        },
+        Value::String(string) => string.to_variant(),
+        Value::Array(values) => {
+            VariantArray::from_iter(values.iter().map(json_to_variant)).owned_to_variant()
+        }
+        Value::Object(map) => {
+            let dictionary = Dictionary::new();
+            for (key, value) in map {
+                dictionary.insert(key.to_owned(), property_to_variant(value, key == "Color"));
+            }
+
+            dictionary.owned_to_variant()
+        }
+    }
+}
+
+pub fn property_to_variant(value: &Value, is_color: bool) -> Variant {
+    match model::json_color(value) {
+        Some([r, g, b, a]) if is_color => Color::from_rgba(r, g, b, a).to_variant(),
+        _ => json_to_variant(value),
+    }
+}
+
+// NOTE: `colors` holds the "Feature.Property" of the template properties the export defines as colors
+fn template_to_variant(template: &Value, colors: &[String]) -> Variant {
+    let features = match template.as_object() {
+        Some(features) => features,
+        None => return json_to_variant(template),
+    };
+
+    let dictionary = Dictionary::new();
+    for (feature, properties) in features {
+        let variant = match properties.as_object() {
+            Some(properties) => {
+                let inner = Dictionary::new();
+                for (property, value) in properties {
+                    let is_color = colors.contains(&format!("{feature}.{property}"));
+                    inner.insert(property.to_owned(), property_to_variant(value, is_color));
+                }
+
+                inner.owned_to_variant()
+            }
+            None => json_to_variant(properties),
+        };
+
+        dictionary.insert(feature.to_owned(), variant);
+    }
+
+    dictionary.owned_to_variant()
+}
+
+// NOTE: Colors go back to Articy's `{"r", "g", "b", "a"}`, so `property_to_variant` turns them into a `Color` again
+pub fn variant_to_json(value: &Variant) -> Result<Value, Error> {
+    Ok(match value.dispatch() {
+        VariantDispatch::Nil => Value::Null,
+        VariantDispatch::Bool(bool) => Value::Bool(bool),
+        VariantDispatch::I64(integer) => Value::from(integer),
+        VariantDispatch::F64(float) => Number::from_f64(float)
+            .map(Value::Number)
+            .ok_or_else(|| Error::UnsupportedType(format!("Float ({float})")))?,
+        VariantDispatch::GodotString(string) => Value::String(string.to_string()),
+        VariantDispatch::NodePath(path) => Value::String(path.to_string()),
+        VariantDispatch::VariantArray(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| variant_to_json(&value))
+                .collect::<Result<Vec<Value>, Error>>()?,
+        ),
+        // NOTE: Only string keys, JSON has no other kind
+        VariantDispatch::Dictionary(dictionary) => Value::Object(
+            dictionary
+                .iter()
+                .map(|(key, value)| match key.dispatch() {
+                    VariantDispatch::GodotString(key) => {
+                        Ok((key.to_string(), variant_to_json(&value)?))
+                    }
+                    _ => Err(Error::UnsupportedType(format!(
+                        "{:?} as dictionary key",
+                        key.get_type()
+                    ))),
+                })
+                .collect::<Result<Map<String, Value>, Error>>()?,
+        ),
+        VariantDispatch::Color(color) => color_to_json(color),
+
+        VariantDispatch::Vector2(..)
+        | VariantDispatch::Vector3(..)
+        | VariantDispatch::Quat(..)
+        | VariantDispatch::Transform2D(..)
+        | VariantDispatch::Plane(..)
+        | VariantDispatch::Aabb(..)
+        | VariantDispatch::Basis(..)
+        | VariantDispatch::Transform(..)
+        | VariantDispatch::Rid(..)
+        | VariantDispatch::Object(..)
+        | VariantDispatch::ByteArray(..)
+        | VariantDispatch::Int32Array(..)
+        | VariantDispatch::Float32Array(..)
+        | VariantDispatch::StringArray(..)
+        | VariantDispatch::Vector2Array(..)
+        | VariantDispatch::Vector3Array(..)
+        | VariantDispatch::ColorArray(..)
+        | VariantDispatch::Rect2(..) => {
+            return Err(Error::UnsupportedType(format!("{:?}", value.get_type())))
+        }
+    })
+}
+
+fn color_to_json(color: Color) -> Value {
+    serde_json::json!({
+        "r": color.r,
+        "g": color.g,
+        "b": color.b,
+        "a": color.a,
+    })
+}
+
+pub fn line_to_dictionary(line: &Line) -> Dictionary<Unique> {
+    let dictionary = Dictionary::new();
+
+    dictionary.insert("id", line.id.to_owned());
+    dictionary.insert("line", line.text.to_owned());
+    dictionary.insert("speaker", line.speaker.to_owned());
+    dictionary.insert("technical_name", line.technical_name.to_owned());
+
+    if let Some(speaker) = &line.speaker_details {
+        if let Some(name) = &speaker.name {
+            dictionary.insert("speaker_name", name.to_owned());
+        }
+
+        if let Some([r, g, b, a]) = speaker.color {
+            dictionary.insert("speaker_color", Color::from_rgba(r, g, b, a));
+        }
+
+        dictionary.insert("speaker_external_id", speaker.external_id.to_owned());
+
+        if let Some(portrait) = &speaker.portrait {
+            dictionary.insert("speaker_portrait", portrait.to_owned());
+        }
+    }
+
+    dictionary.insert("stage_directions", line.stage_directions.to_owned());
+
+    if let Some(key) = &line.key {
+        dictionary.insert("line_key", key.to_owned());
+    }
+
+    if let Some(voice_over) = &line.voice_over {
+        dictionary.insert("voice_over", voice_over.to_owned());
+    }
+
+    // NOTE: Only resolved when asked for, so loading the stream here doesn't happen by surprise
+    if let Some(path) = &line.voice_over_path {
+        dictionary.insert("voice_over_path", path.to_owned());
+        dictionary.insert("voice_over_stream", load::<AudioStream>(path.as_str()));
+    }
+
+    if let Some([r, g, b, a]) = line.color {
+        dictionary.insert("color", Color::from_rgba(r, g, b, a));
+    }
+
+    if let Some(template) = &line.template {
+        dictionary.insert("template", template_to_variant(template, &line.colors));
+    }
+
+    insert_fields(&dictionary, &line.fields, &line.colors);
+
+    dictionary
+}
+
+pub fn choice_to_dictionary(choice: &Choice) -> Dictionary<Unique> {
+    let dictionary = Dictionary::new();
+
+    dictionary.insert("label", choice.label.to_owned());
+    dictionary.insert("id", choice.id.to_owned());
+    dictionary.insert("available", choice.available);
+    dictionary.insert("condition", choice.condition.to_owned());
+    dictionary.insert("seen", choice.seen);
+    dictionary.insert("fallback", choice.fallback);
+
+    if let Some(time_limit) = choice.time_limit {
+        dictionary.insert("time_limit", time_limit);
+    }
+
+    if let Some(target_id) = &choice.target_id {
+        dictionary.insert("target_id", target_id.to_owned());
+        dictionary.insert(
+            "target_type",
+            choice.target_type.to_owned().unwrap_or_default(),
+        );
+    }
+
+    if let Some(template) = &choice.template {
+        dictionary.insert("template", template_to_variant(template, &choice.colors));
+    }
+
+    insert_fields(&dictionary, &choice.fields, &choice.colors);
+
+    dictionary
+}
+
+// NOTE: Flattened template properties never replace the keys the integration puts in itself
+fn insert_fields(dictionary: &Dictionary<Unique>, fields: &[(String, Value)], colors: &[String]) {
+    for (key, value) in fields {
+        let is_color = colors.iter().any(|path| {
+            path.rsplit('.')
+                .next()
+                .map_or(false, |property| codegen::words(property).join("_") == *key)
+        });
+
+        if !dictionary.contains(key.as_str()) {
+            dictionary.insert(key.as_str(), property_to_variant(value, is_color));
+        }
+    }
+}
+
+pub fn history_entry_to_dictionary(entry: &HistoryEntry) -> Dictionary<Unique> {
+    let dictionary = Dictionary::new();
+
+    dictionary.insert("id", entry.id.to_owned());
+    dictionary.insert("speaker", entry.speaker.to_owned());
+    dictionary.insert("text", entry.text.to_owned());
+    dictionary.insert("timestamp", entry.timestamp);
+    dictionary.insert("chosen", entry.chosen);
+
+    dictionary
+}
+
+pub fn quest_to_dictionary(quest: &Quest) -> Dictionary<Unique> {
+    let dictionary = Dictionary::new();
+
+    dictionary.insert("id", quest.id.to_owned());
+    dictionary.insert("technical_name", quest.technical_name.to_owned());
+    dictionary.insert("display_name", quest.display_name.to_owned());
+    dictionary.insert("state", quest.state.as_str());
+    dictionary.insert("stage", quest.stage.to_owned());
+
+    dictionary
+}
+
+pub fn search_match_to_dictionary(found: &SearchMatch) -> Dictionary<Unique> {
+    let dictionary = Dictionary::new();
+
+    dictionary.insert("id", found.id.to_owned());
+    dictionary.insert("type", found.kind.to_owned());
+    dictionary.insert("property", found.property.to_owned());
+    dictionary.insert("text", found.text.to_owned());
+
+    if let Some((id, name)) = &found.dialogue {
+        dictionary.insert("dialogue_id", id.to_owned());
+        dictionary.insert("dialogue_name", name.to_owned());
+    }
+
+    dictionary
+}
+
+pub fn reference_to_dictionary(reference: &Reference) -> Dictionary<Unique> {
+    let dictionary = Dictionary::new();
+
+    dictionary.insert("id", reference.id.to_owned());
+    dictionary.insert("kind", reference.kind.to_owned());
+    dictionary.insert("path", reference.path.to_owned());
+
+    if let Some(expression) = &reference.expression {
+        dictionary.insert("expression", expression.to_owned());
+    }
+
+    dictionary
+}
+
+pub fn template_to_dictionary(template: &TemplateDefinition) -> Dictionary<Unique> {
+    let features = template.features.iter().map(|feature| {
+        let properties = feature.properties.iter().map(|property| {
+            let dictionary = Dictionary::new();
+            dictionary.insert("name", property.name.to_owned());
+            dictionary.insert("type", property.kind.to_owned());
+            dictionary.insert("display_name", property.display_name.to_owned());
+            dictionary.insert("default", json_to_variant(&property.default));
+            dictionary.insert(
+                "constraints",
+                json_to_variant(&Value::Object(property.constraints.to_owned())),
+            );
+
+            dictionary.into_shared()
+        });
+
+        let dictionary = Dictionary::new();
+        dictionary.insert("name", feature.name.to_owned());
+        dictionary.insert("display_name", feature.display_name.to_owned());
+        dictionary.insert(
+            "properties",
+            VariantArray::from_iter(properties).into_shared(),
+        );
+
+        dictionary.into_shared()
+    });
+
+    let dictionary = Dictionary::new();
+    dictionary.insert("name", template.name.to_owned());
+    dictionary.insert("display_name", template.display_name.to_owned());
+    dictionary.insert("class", template.class.to_owned());
+    dictionary.insert("features", VariantArray::from_iter(features).into_shared());
+
+    dictionary
+}
+
+pub fn location_to_dictionary(location: &Location) -> Dictionary<Unique> {
+    let vector = |[x, y]: [f64; 2]| Vector2::new(x as f32, y as f32);
+
+    let zones = location.zones.iter().map(|zone| {
+        let dictionary = Dictionary::new();
+        dictionary.insert("id", zone.id.to_owned());
+        dictionary.insert("display_name", zone.display_name.to_owned());
+        dictionary.insert(
+            "points",
+            PoolArray::from_vec(zone.points.iter().copied().map(vector).collect()),
+        );
+
+        dictionary.into_shared()
+    });
+
+    let spots = location.spots.iter().map(|spot| {
+        let dictionary = Dictionary::new();
+        dictionary.insert("id", spot.id.to_owned());
+        dictionary.insert("display_name", spot.display_name.to_owned());
+        dictionary.insert("position", vector(spot.position));
+
+        dictionary.into_shared()
+    });
+
+    let links = location.links.iter().map(|link| {
+        let dictionary = Dictionary::new();
+        dictionary.insert("id", link.id.to_owned());
+        dictionary.insert("source", link.source.to_owned());
+        dictionary.insert("target", link.target.to_owned());
+
+        dictionary.into_shared()
+    });
+
+    let dictionary = Dictionary::new();
+    dictionary.insert("id", location.id.to_owned());
+    dictionary.insert("display_name", location.display_name.to_owned());
+    dictionary.insert("zones", VariantArray::from_iter(zones).into_shared());
+    dictionary.insert("spots", VariantArray::from_iter(spots).into_shared());
+    dictionary.insert("links", VariantArray::from_iter(links).into_shared());
+
+    dictionary
+}
+
+pub fn export_metadata_to_dictionary(metadata: &ExportMetadata) -> Dictionary<Unique> {
+    let dictionary = Dictionary::new();
+
+    for (key, value) in [
+        ("export_version", &metadata.export_version),
+        ("articy_version", &metadata.articy_version),
+        ("export_date", &metadata.export_date),
+        ("project_name", &metadata.project_name),
+        ("project_technical_name", &metadata.project_technical_name),
+        ("project_guid", &metadata.project_guid),
+    ] {
+        if let Some(value) = value {
+            dictionary.insert(key, value.to_owned());
+        }
+    }
+
+    dictionary.insert("supported", metadata.is_supported());
+
+    dictionary
+}
+
+pub fn issue_to_dictionary(issue: &Issue) -> Dictionary<Unique> {
+    let dictionary = Dictionary::new();
+
+    dictionary.insert("severity", issue.severity.as_str());
+    dictionary.insert("id", issue.id.to_owned());
+    dictionary.insert("message", issue.message.to_owned());
+
+    dictionary
+}
+
+pub fn flow_graph_to_dictionary(graph: &FlowGraph) -> Dictionary<Unique> {
+    let nodes = graph.nodes.iter().map(|node| {
+        let dictionary = Dictionary::new();
+
+        dictionary.insert("id", node.id.to_owned());
+        dictionary.insert("type", node.kind.to_owned());
+        dictionary.insert("label", node.label.to_owned());
+        dictionary.insert("offset", Vector2::new(node.position[0], node.position[1]));
+        dictionary.insert("inputs", node.inputs as i64);
+        dictionary.insert("outputs", node.outputs as i64);
+
+        if let Some([width, height]) = node.size {
+            dictionary.insert("size", Vector2::new(width, height));
+        }
+
+        dictionary.into_shared().to_variant()
+    });
+
+    let edges = graph.edges.iter().map(|edge| {
+        let dictionary = Dictionary::new();
+
+        dictionary.insert("from", edge.from.to_owned());
+        dictionary.insert("from_port", edge.from_port as i64);
+        dictionary.insert("to", edge.to.to_owned());
+        dictionary.insert("to_port", edge.to_port as i64);
+        dictionary.insert("label", edge.label.to_owned());
+        dictionary.insert("condition", edge.condition.to_owned());
+        dictionary.insert("instruction", edge.instruction.to_owned());
+
+        dictionary.into_shared().to_variant()
+    });
+
+    let dictionary = Dictionary::new();
+    dictionary.insert("nodes", VariantArray::from_iter(nodes).into_shared());
+    dictionary.insert("edges", VariantArray::from_iter(edges).into_shared());
+
+    dictionary
+}
+
+pub fn hierarchy_to_dictionary(node: &HierarchyNode) -> Dictionary<Unique> {
+    let dictionary = Dictionary::new();
+
+    dictionary.insert("id", node.id.to_owned());
+    dictionary.insert("type", node.kind.to_owned());
+    dictionary.insert("display_name", node.display_name.to_owned());
+    dictionary.insert("technical_name", node.technical_name.to_owned());
+    dictionary.insert(
+        "children",
+        VariantArray::from_iter(
+            node.children
+                .iter()
+                .map(|child| hierarchy_to_dictionary(child).owned_to_variant()),
+        )
+        .into_shared(),
+    );
+
+    dictionary
+}
+
+pub fn connection_to_dictionary(connection: &Connection) -> Dictionary<Unique> {
+    let dictionary = Dictionary::new();
+
+    dictionary.insert("source", connection.source.to_owned());
+    dictionary.insert("source_pin", connection.source_pin.to_owned());
+    dictionary.insert("target", connection.target.to_owned());
+    dictionary.insert("target_pin", connection.target_pin.to_owned());
+    dictionary.insert("label", connection.label.to_owned());
+
+    dictionary
+}
+
+pub fn states_to_dictionary(states: &[(String, Value)]) -> Dictionary<Unique> {
+    let dictionary = Dictionary::new();
+
+    for (key, value) in states {
+        dictionary.insert(key.to_owned(), json_to_variant(value));
+    }
+
+    dictionary
+}
+
+pub fn dictionary_to_states(dictionary: &Dictionary) -> Result<Vec<(String, Value)>, Error> {
+    dictionary
+        .iter()
+        .map(|(key, value)| Ok((key.to_string(), variant_to_json(&value)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // NOTE: Variants need a running engine, so this covers the JSON `save_state_to_file` writes for a color override
+    #[test]
+    fn color_override_survives_save_and_load() {
+        let color = Color::from_rgba(0.25, 0.5, 0.75, 1.0);
+
+        let saved = serde_json::to_string(&color_to_json(color)).unwrap();
+        let loaded = serde_json::from_str::<Value>(&saved).unwrap();
+
+        assert_eq!(model::json_color(&loaded), Some([0.25, 0.5, 0.75, 1.0]));
+    }
+}
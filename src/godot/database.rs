@@ -0,0 +1,1156 @@
+use super::{
+    convert::{
+        connection_to_dictionary, export_metadata_to_dictionary, flow_graph_to_dictionary,
+        hierarchy_to_dictionary, issue_to_dictionary, json_to_variant, location_to_dictionary,
+        property_to_variant, reference_to_dictionary, search_match_to_dictionary,
+        template_to_dictionary, variant_to_json, ArticyModel,
+    },
+    dialogue::ArticyDialogue,
+    entity::ArticyEntity,
+    report, report_error,
+};
+use crate::{
+    codegen::{self, ConstantGroup},
+    error::Error,
+    graph,
+    library::{self, Library, LoadMessage},
+    localization,
+    location::Location,
+    model::{Connection, Lookup},
+    query::Query,
+    statistics, validation,
+};
+use articy::types::Model;
+use gdnative::api::{PackedDataContainer, Resource, ResourceLoader};
+use gdnative::prelude::*;
+use serde_json::Value;
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+#[derive(NativeClass, Debug, Default)]
+#[inherit(Node)]
+#[register_with(Self::register_signals)]
+pub struct Database {
+    #[property]
+    articy_resource: Option<Ref<PackedDataContainer>>,
+    // NOTE: Exports of an unsupported version are only warned about, unless this is on
+    #[property(set = "Self::set_refuse_unsupported_versions")]
+    refuse_unsupported_versions: bool,
+    // NOTE: Only parses the default package when loading, the others get parsed once they're made active, loaded
+    //       with `load_package` or looked up in. Saves time and memory on big exports, doesn't apply to precompiled ones
+    #[property(set = "Self::set_lazy_packages")]
+    lazy_packages: bool,
+    // NOTE: Adds the editor position, size and color to models handed out, e.g. to draw a map of the flow
+    #[property]
+    include_layout: bool,
+    // NOTE: Prints how long parsing and indexing took after every load
+    #[property]
+    print_load_timings: bool,
+    // NOTE: Multiplies the positions of zones and spots handed out by `get_location`, Articy's units to Godot's
+    #[property]
+    location_scale: f64,
+    pub library: Library,
+    loading: Option<Receiver<LoadMessage>>,
+    watching: Option<Watch>,
+}
+
+#[derive(Debug)]
+struct Watch {
+    path: String,
+    modified: u64,
+    elapsed: f64,
+}
+
+// NOTE: Seconds between checking a watched export for changes
+const WATCH_INTERVAL: f64 = 1.0;
+
+#[derive(ToVariant, Debug)]
+pub struct Dialogue {
+    id: String,
+    name: String,
+    technical_name: String,
+}
+
+impl Dialogue {
+    fn from_model(library: &Library, model: &Model) -> Self {
+        let id = model.id().to_inner();
+        let properties = library
+            .find_summary(&id)
+            .map_or(&Value::Null, |json| &json["Properties"]);
+        let text = |property: &str| properties[property].as_str().unwrap_or_default().to_owned();
+
+        Dialogue {
+            id,
+            name: text("DisplayName"),
+            technical_name: text("TechnicalName"),
+        }
+    }
+}
+
+#[methods]
+impl Database {
+    fn new(_base: &Node) -> Self {
+        Database {
+            location_scale: 1.0,
+            ..Default::default()
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.signal("loaded").done();
+
+        builder.signal("unloaded").done();
+
+        builder
+            .signal("reloaded")
+            .with_param("path", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("package_loaded")
+            .with_param("name", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("load_progress")
+            .with_param("percent", VariantType::F64)
+            .done();
+
+        builder
+            .signal("locale_changed")
+            .with_param("locale", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("error")
+            .with_param("code", VariantType::I64)
+            .with_param("message", VariantType::GodotString)
+            .done();
+    }
+
+    fn set_refuse_unsupported_versions(&mut self, _owner: TRef<Node>, refuse: bool) {
+        self.library.set_refuse_unsupported(refuse);
+        self.refuse_unsupported_versions = refuse;
+    }
+
+    fn set_lazy_packages(&mut self, _owner: TRef<Node>, lazy: bool) {
+        self.library.set_lazy_packages(lazy);
+        self.lazy_packages = lazy;
+    }
+
+    fn check_version(&self) {
+        let metadata = match self.library.export_metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => return,
+        };
+
+        if !metadata.is_supported() {
+            godot_warn!(
+                "Articy export version {:?} isn't supported, it might not load correctly",
+                metadata.export_version.as_deref().unwrap_or_default()
+            );
+        }
+    }
+
+    fn print_timings(&self) {
+        if !self.print_load_timings {
+            return;
+        }
+
+        let timings = self.library.load_timings();
+        godot_print!(
+            "Articy export parsed in {:.1}ms, indexed in {:.1}ms",
+            timings.parse.as_secs_f64() * 1000.0,
+            timings.index.as_secs_f64() * 1000.0
+        );
+    }
+
+    #[method]
+    fn _ready(&mut self, #[base] owner: &Node) {
+        // NOTE: Only needs to process while a `load_async` is in flight or an export is watched
+        owner.set_process(false);
+
+        if let Some(resource) = &self.articy_resource {
+            self.load(owner, resource.clone());
+        } else if let Some(node) = owner.get_parent() {
+            let name = unsafe {
+                match node.assume_safe().get("name").dispatch() {
+                    VariantDispatch::GodotString(godot_string) => godot_string.to_string(),
+                    _ => "".to_owned(),
+                }
+            };
+
+            if name == "root" {
+                godot_print!("Detected as AutoLoad, attempting to read project setting \"articy/autoload_database_path\" to load resource from");
+                let settings = gdnative::api::ProjectSettings::godot_singleton();
+
+                if settings.has_setting("articy/autoload_database_path") {
+                    let path = settings
+                        .get_setting("articy/autoload_database_path")
+                        .to_string();
+
+                    match load::<PackedDataContainer>(path.clone()) {
+                        Some(resource) => {
+                            self.load(owner, resource);
+                        }
+                        None => report_error(
+                            owner,
+                            Error::InvalidResource(format!("the resource loaded from \"articy/autoload_database_path\" ({path:?}) is not of type `PackedDataContainer` (as imported by the plugin)")),
+                        ),
+                    }
+                } else {
+                    godot_error!(
+                        "Your project does not have \"articy/autoload_database_path\" set."
+                    )
+                }
+            }
+        }
+    }
+
+    #[method]
+    fn _get_configuration_warning(&self) -> String {
+        let settings = gdnative::api::ProjectSettings::godot_singleton();
+
+        if self.articy_resource.is_none() && !settings.has_setting("articy/autoload_database_path")
+        {
+            return "No `articy_resource` is set and the project has no \"articy/autoload_database_path\" setting, nothing gets loaded unless `load` is called from a script.".to_owned();
+        }
+
+        String::new()
+    }
+
+    // NOTE: A failed load leaves the file that was loaded before in place
+    #[method]
+    fn load(&mut self, #[base] owner: &Node, resource: Ref<PackedDataContainer, Shared>) -> bool {
+        let loaded = resource_bytes(resource).and_then(|bytes| self.library.load(&bytes));
+
+        if report(owner, loaded).is_none() {
+            return false;
+        }
+
+        self.check_version();
+        self.print_timings();
+        owner.emit_signal("loaded", &[]);
+        true
+    }
+
+    // NOTE: Merges another export (e.g. DLC) into the loaded one, Interpreters pick it up on their next `set_database`
+    #[method]
+    fn load_additional(
+        &mut self,
+        #[base] owner: &Node,
+        resource: Ref<PackedDataContainer, Shared>,
+    ) -> bool {
+        let added = resource_bytes(resource).and_then(|bytes| self.library.load_additional(&bytes));
+
+        match report(owner, added) {
+            Some(added) => {
+                self.check_version();
+                self.print_timings();
+                for name in added {
+                    owner.emit_signal("package_loaded", &[name.to_variant()]);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[method]
+    fn load_async(
+        &mut self,
+        #[base] owner: &Node,
+        resource: Ref<PackedDataContainer, Shared>,
+    ) -> bool {
+        match report(owner, resource_bytes(resource)) {
+            Some(bytes) => {
+                self.loading = Some(library::load_in_background(bytes, self.lazy_packages));
+                owner.set_process(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // NOTE: For development, reloads the JSON export at `path` whenever it changes on disk. Interpreters keep
+    //       running on the old one until they're given the database again, e.g. `set_database(path, true)` on `reloaded`
+    #[method]
+    fn watch(&mut self, #[base] owner: &Node, path: String) -> bool {
+        let file = gdnative::api::File::new();
+        if !file.file_exists(path.as_str()) {
+            report_error(
+                owner,
+                Error::InvalidResource(format!("can't watch {path:?}, it doesn't exist")),
+            );
+            return false;
+        }
+
+        self.watching = Some(Watch {
+            modified: file.get_modified_time(path.as_str()),
+            path,
+            elapsed: 0.0,
+        });
+        owner.set_process(true);
+
+        true
+    }
+
+    #[method]
+    fn unwatch(&mut self) {
+        self.watching = None;
+    }
+
+    // NOTE: Interpreters and managers using this Database stop their conversations and give up their sessions,
+    //       calls made to them afterwards fail until they're given a loaded database again
+    #[method]
+    fn unload(&mut self, #[base] owner: &Node) {
+        self.loading = None;
+        self.watching = None;
+        owner.set_process(false);
+
+        self.library.unload();
+        owner.emit_signal("unloaded", &[]);
+    }
+
+    #[method]
+    fn _process(&mut self, #[base] owner: &Node, delta: f64) {
+        self.poll_watch(owner, delta);
+        self.poll_loading(owner);
+
+        if self.loading.is_none() && self.watching.is_none() {
+            owner.set_process(false);
+        }
+    }
+
+    fn poll_watch(&mut self, owner: &Node, delta: f64) {
+        let watch = match &mut self.watching {
+            Some(watch) => watch,
+            None => return,
+        };
+
+        watch.elapsed += delta;
+        if watch.elapsed < WATCH_INTERVAL {
+            return;
+        }
+        watch.elapsed = 0.0;
+
+        let modified = gdnative::api::File::new().get_modified_time(watch.path.as_str());
+        if modified == watch.modified {
+            return;
+        }
+        watch.modified = modified;
+
+        // NOTE: Articy might still be writing the export, a broken one is reported and picked up on the next change
+        let path = watch.path.to_owned();
+        let loaded = file_bytes(&path).and_then(|bytes| self.library.load(&bytes));
+
+        if report(owner, loaded).is_some() {
+            self.check_version();
+            self.print_timings();
+            owner.emit_signal("reloaded", &[path.to_variant()]);
+        }
+    }
+
+    fn poll_loading(&mut self, owner: &Node) {
+        let receiver = match &self.loading {
+            Some(receiver) => receiver,
+            None => return,
+        };
+
+        let mut finished = false;
+        while !finished {
+            match receiver.try_recv() {
+                Ok(LoadMessage::Progress(percent)) => {
+                    owner.emit_signal("load_progress", &[percent.to_variant()]);
+                }
+                Ok(LoadMessage::Loaded(parsed)) => {
+                    if report(owner, self.library.install(*parsed)).is_some() {
+                        self.check_version();
+                        self.print_timings();
+                        owner.emit_signal("loaded", &[]);
+                    }
+                    finished = true;
+                }
+                Ok(LoadMessage::Failed(error)) => {
+                    report_error(owner, error);
+                    finished = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                // NOTE: Only when the loading thread panicked, errors are sent back as `Failed`
+                Err(TryRecvError::Disconnected) => {
+                    report_error(
+                        owner,
+                        Error::InvalidResource(
+                            "parsing the Articy export on the loading thread failed".to_owned(),
+                        ),
+                    );
+                    finished = true;
+                }
+            }
+        }
+
+        if finished {
+            self.loading = None;
+        }
+    }
+
+    // NOTE: Takes either a path to the exported localization JSON or a resource imported like the export itself
+    #[method]
+    fn load_localization(&mut self, #[base] owner: &Node, locale: String, source: Variant) -> bool {
+        let bytes = match source.dispatch() {
+            VariantDispatch::GodotString(path) => file_bytes(&path.to_string()),
+            VariantDispatch::Object(object) => object
+                .to_object::<PackedDataContainer>()
+                .ok_or_else(|| {
+                    Error::InvalidResource(
+                        "localization resource is not of type `PackedDataContainer`".to_owned(),
+                    )
+                })
+                .and_then(resource_bytes),
+            _ => Err(Error::InvalidResource(format!(
+                "can't load localization from a {:?}",
+                source.get_type()
+            ))),
+        };
+
+        let result = bytes.and_then(|bytes| self.library.load_localization(&locale, &bytes));
+
+        report(owner, result).is_some()
+    }
+
+    // NOTE: Writes constants for the ids of dialogues, entities, folders and the names of variables, as GDScript or C#
+    //       depending on whether `path` ends in ".gd" or ".cs"
+    #[method]
+    fn export_ids(&self, #[base] owner: &Node, path: String) -> bool {
+        let result =
+            codegen::id_constants(&self.library).and_then(|groups| write_ids(&path, &groups));
+
+        report(owner, result).is_some()
+    }
+
+    // NOTE: Writes the loaded export in the precompiled format, which `load` picks up by itself
+    #[method]
+    fn save_precompiled(&self, #[base] owner: &Node, path: String) -> bool {
+        let result = self.library.precompile().and_then(|bytes| {
+            let file = gdnative::api::File::new();
+            file.open(path.as_str(), gdnative::api::File::WRITE)
+                .map_err(|error| {
+                    Error::InvalidResource(format!("can't write to {path:?}: {error:?}"))
+                })?;
+
+            file.store_buffer(PoolArray::from_vec(bytes));
+            file.close();
+
+            Ok(())
+        });
+
+        report(owner, result).is_some()
+    }
+
+    // NOTE: `format` is either "csv" or "pot", the CSV gets a single column for `locale` (defaults to "en")
+    #[method]
+    fn export_translations(
+        &self,
+        #[base] owner: &Node,
+        path: String,
+        format: String,
+        #[opt] locale: Option<String>,
+    ) -> bool {
+        let result = self.library.translation_entries().and_then(|entries| {
+            let contents = match format.as_str() {
+                "csv" => localization::to_csv(&entries, locale.as_deref().unwrap_or("en")),
+                "pot" => localization::to_pot(&entries),
+                _ => return Err(Error::InvalidOption(format!("translation format {format}"))),
+            };
+
+            let file = gdnative::api::File::new();
+            file.open(path.as_str(), gdnative::api::File::WRITE)
+                .map_err(|error| {
+                    Error::InvalidResource(format!("can't write to {path:?}: {error:?}"))
+                })?;
+
+            file.store_string(contents);
+            file.close();
+
+            Ok(())
+        });
+
+        report(owner, result).is_some()
+    }
+
+    #[method]
+    fn set_locale(&mut self, #[base] owner: &Node, locale: String) -> bool {
+        if report(owner, self.library.set_locale(&locale)).is_some() {
+            owner.emit_signal("locale_changed", &[locale.to_variant()]);
+            true
+        } else {
+            false
+        }
+    }
+
+    #[method]
+    fn get_export_info(&self, #[base] owner: &Node) -> Option<Dictionary> {
+        report(owner, self.library.export_metadata())
+            .map(|metadata| export_metadata_to_dictionary(metadata).into_shared())
+    }
+
+    #[method]
+    fn get_locale(&self) -> String {
+        self.library.locale().unwrap_or_default()
+    }
+
+    #[method]
+    fn get_model(
+        &mut self,
+        #[base] owner: &Node,
+        id: String,
+        #[opt] package: Option<String>,
+    ) -> Option<ArticyModel<'_>> {
+        self.require(owner, &id);
+
+        report(
+            owner,
+            self.library.lookup(Lookup::Id, &id, package.as_deref()),
+        )
+        .flatten()
+        .map(|model| ArticyModel(model, self.include_layout))
+    }
+
+    // NOTE: Filters on "type", "template", "folder", "speaker" and "properties", e.g.
+    //       `{"type": "Entity", "folder": id, "properties": {"Template.Stats.Health": {"min": 10}}}`
+    #[method]
+    fn query(&self, #[base] owner: &Node, filter: Dictionary) -> Vec<ArticyModel<'_>> {
+        let found = variant_to_json(&filter.owned_to_variant())
+            .and_then(|filter| Query::from_json(&filter))
+            .and_then(|query| query.run(&self.library));
+
+        report(owner, found)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|model| ArticyModel(model, self.include_layout))
+            .collect::<Vec<ArticyModel<'_>>>()
+    }
+
+    // NOTE: `kind` is a type or an Array of them. Pages through the models with `offset` and `limit`, a `limit` of
+    //       0 or less gives every model from `offset` on
+    #[method]
+    fn get_models_of_type(
+        &self,
+        #[base] owner: &Node,
+        kind: Variant,
+        #[opt] offset: i64,
+        #[opt] limit: i64,
+    ) -> Vec<ArticyModel<'_>> {
+        let kinds = match kind.dispatch() {
+            VariantDispatch::VariantArray(kinds) => {
+                kinds.iter().map(|kind| kind.to_string()).collect()
+            }
+            _ => vec![kind.to_string()],
+        };
+        let limit = match usize::try_from(limit) {
+            Ok(0) | Err(_) => usize::MAX,
+            Ok(limit) => limit,
+        };
+
+        let offset = usize::try_from(offset).unwrap_or_default();
+
+        report(owner, self.library.models_of_types(&kinds, offset, limit))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|model| ArticyModel(model, self.include_layout))
+            .collect::<Vec<ArticyModel<'_>>>()
+    }
+
+    #[method]
+    fn get_model_by_external_id(
+        &mut self,
+        #[base] owner: &Node,
+        external_id: String,
+        #[opt] package: Option<String>,
+    ) -> Option<ArticyModel<'_>> {
+        self.require(owner, &external_id);
+
+        report(
+            owner,
+            self.library
+                .lookup(Lookup::ExternalId, &external_id, package.as_deref()),
+        )
+        .flatten()
+        .map(|model| ArticyModel(model, self.include_layout))
+    }
+
+    #[method]
+    fn get_model_by_technical_name(
+        &mut self,
+        #[base] owner: &Node,
+        technical_name: String,
+        #[opt] package: Option<String>,
+    ) -> Option<ArticyModel<'_>> {
+        self.require(owner, &technical_name);
+
+        report(
+            owner,
+            self.library
+                .lookup(Lookup::TechnicalName, &technical_name, package.as_deref()),
+        )
+        .flatten()
+        .map(|model| ArticyModel(model, self.include_layout))
+    }
+
+    #[method]
+    fn get_property(&mut self, #[base] owner: &Node, id: String, path: String) -> Variant {
+        self.require(owner, &id);
+
+        report(owner, self.library.property(&id, &path))
+            .map(|value| property_to_variant(&value, self.library.is_color(&path)))
+            .unwrap_or_default()
+    }
+
+    // NOTE: The models a slot or reference strip property points at, instead of their ids
+    #[method]
+    fn resolve_references(
+        &self,
+        #[base] owner: &Node,
+        id: String,
+        property_path: String,
+    ) -> Vec<ArticyModel<'_>> {
+        report(owner, self.library.resolve_references(&id, &property_path))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|model| ArticyModel(model, self.include_layout))
+            .collect::<Vec<ArticyModel<'_>>>()
+    }
+
+    #[method]
+    fn get_models_by_display_name(
+        &mut self,
+        #[base] owner: &Node,
+        display_name: String,
+        #[opt] package: Option<String>,
+    ) -> Vec<ArticyModel<'_>> {
+        self.require(owner, &display_name);
+
+        report(
+            owner,
+            self.library
+                .models_by_display_name(&display_name, package.as_deref()),
+        )
+        .unwrap_or_default()
+        .into_iter()
+        .map(|model| ArticyModel(model, self.include_layout))
+        .collect::<Vec<ArticyModel<'_>>>()
+    }
+
+    #[method]
+    fn get_lines_by_speaker(
+        &self,
+        #[base] owner: &Node,
+        entity_id: String,
+    ) -> Vec<ArticyModel<'_>> {
+        report(owner, self.library.lines_by_speaker(&entity_id))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|model| ArticyModel(model, self.include_layout))
+            .collect::<Vec<ArticyModel<'_>>>()
+    }
+
+    #[method]
+    fn get_packages(&self, #[base] owner: &Node) -> VariantArray {
+        let array = VariantArray::new();
+
+        for package in report(owner, self.library.packages()).unwrap_or_default() {
+            let dictionary = Dictionary::new();
+            dictionary.insert("name", package.name);
+            dictionary.insert("model_count", package.model_count as i64);
+            dictionary.insert("is_default", package.is_default);
+            dictionary.insert("is_active", package.is_active);
+            dictionary.insert("is_loaded", package.is_loaded);
+
+            array.push(dictionary);
+        }
+
+        array.into_shared()
+    }
+
+    // NOTE: Zones as polygons (`points`), spots as a `position` and links as a `source` and `target` id
+    #[method]
+    fn get_location(&self, #[base] owner: &Node, id: String) -> Option<Dictionary> {
+        report(
+            owner,
+            Location::read(&self.library, &id, self.location_scale),
+        )
+        .map(|location| location_to_dictionary(&location).into_shared())
+    }
+
+    #[method]
+    fn get_template_names(&self, #[base] owner: &Node) -> Vec<String> {
+        report(owner, self.library.template_names()).unwrap_or_default()
+    }
+
+    // NOTE: Every feature of the template with the name, type, display name, default and constraints of its properties
+    #[method]
+    fn get_template_schema(&self, #[base] owner: &Node, name: String) -> Option<Dictionary> {
+        report(owner, self.library.template(&name))
+            .map(|template| template_to_dictionary(template).into_shared())
+    }
+
+    #[method]
+    fn get_global_variables(&self, #[base] owner: &Node) -> Dictionary {
+        let namespaces = Dictionary::new();
+
+        for namespace in report(owner, self.library.global_variables()).unwrap_or_default() {
+            let variables = Dictionary::new();
+
+            for variable in &namespace.variables {
+                let definition = Dictionary::new();
+                definition.insert("type", variable.kind.clone());
+                definition.insert("default", json_to_variant(&variable.default));
+                definition.insert("description", variable.description.clone());
+
+                variables.insert(variable.name.clone(), definition);
+            }
+
+            let dictionary = Dictionary::new();
+            dictionary.insert("description", namespace.description.clone());
+            dictionary.insert("variables", variables);
+
+            namespaces.insert(namespace.name.clone(), dictionary);
+        }
+
+        namespaces.into_shared()
+    }
+
+    #[method]
+    fn set_active_package(&mut self, #[base] owner: &Node, name: String) -> bool {
+        self.load_package(owner, name.to_owned())
+            && report(owner, self.library.set_active_package(&name)).is_some()
+    }
+
+    // NOTE: With `lazy_packages` on, looking something up first parses the packages that mention it
+    fn require(&mut self, owner: &Node, key: &str) {
+        for name in report(owner, self.library.require(key)).unwrap_or_default() {
+            owner.emit_signal("package_loaded", &[name.to_variant()]);
+        }
+    }
+
+    // NOTE: Only does something after a load with `lazy_packages` on, other packages are loaded already
+    #[method]
+    fn load_package(&mut self, #[base] owner: &Node, name: String) -> bool {
+        let pending = self
+            .library
+            .packages()
+            .unwrap_or_default()
+            .iter()
+            .any(|package| package.name == name && !package.is_loaded);
+
+        match report(owner, self.library.load_package(&name)) {
+            Some(()) => {
+                if pending {
+                    owner.emit_signal("package_loaded", &[name.to_variant()]);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[method]
+    fn get_all_models(&self, #[base] owner: &Node) -> Vec<ArticyModel<'_>> {
+        report(owner, self.library.all_models())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|model| ArticyModel(model, self.include_layout))
+            .collect::<Vec<ArticyModel<'_>>>()
+    }
+
+    #[method]
+    fn get_entity_ids_from_folder(
+        &self,
+        #[base] owner: &Node,
+        folder_id: String,
+        #[opt] recursive: bool,
+    ) -> Vec<String> {
+        report(
+            owner,
+            self.library.entity_ids_from_folder(&folder_id, recursive),
+        )
+        .unwrap_or_default()
+    }
+
+    #[method]
+    fn get_entities_from_folder(
+        &self,
+        #[base] owner: &Node,
+        folder_id: String,
+        #[opt] recursive: bool,
+    ) -> Vec<ArticyModel<'_>> {
+        report(
+            owner,
+            self.library.entity_ids_from_folder(&folder_id, recursive),
+        )
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|id| {
+            report(owner, self.library.find_model(&id))
+                .map(|model| ArticyModel(model, self.include_layout))
+        })
+        .collect::<Vec<ArticyModel<'_>>>()
+    }
+
+    // NOTE: Colors are stored the way Articy exports them, setting null takes the override away
+    #[method]
+    fn set_entity_override(
+        &mut self,
+        #[base] owner: &Node,
+        id: String,
+        property: String,
+        value: Variant,
+    ) -> bool {
+        let result = variant_to_json(&value)
+            .and_then(|value| self.library.set_entity_override(&id, &property, value));
+        report(owner, result).is_some()
+    }
+
+    #[method]
+    fn get_entity_override(&self, id: String, property: String) -> Variant {
+        self.library
+            .entity_overrides()
+            .get(&id, &property)
+            .map(|value| property_to_variant(value, property == "Color"))
+            .unwrap_or_default()
+    }
+
+    #[method]
+    fn get_entity(&self, #[base] owner: &Node, id: String) -> Option<Instance<ArticyEntity>> {
+        report(owner, ArticyEntity::from_library(&self.library, &id))
+            .map(|entity| entity.emplace().into_shared())
+    }
+
+    // NOTE: Every dialogue as `id`, `name` and `technical_name`, e.g. for a debug menu to jump into any conversation
+    #[method]
+    fn get_dialogues(&self, #[base] owner: &Node) -> Vec<Dialogue> {
+        report(owner, self.library.dialogues(None))
+            .map(|models| {
+                models
+                    .into_iter()
+                    .map(|model| Dialogue::from_model(&self.library, model))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[method]
+    fn get_dialogues_in_folder(&self, #[base] owner: &Node, folder_id: String) -> Vec<Dialogue> {
+        report(owner, self.library.dialogues(Some(&folder_id)))
+            .map(|models| {
+                models
+                    .into_iter()
+                    .map(|model| Dialogue::from_model(&self.library, model))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // NOTE: Ids of the entities speaking in the dialogue, nested fragments included, in hierarchy order
+    #[method]
+    fn get_dialogue_participants(&self, #[base] owner: &Node, dialogue_id: String) -> Vec<String> {
+        report(owner, self.library.dialogue_overview(&dialogue_id))
+            .map(|overview| overview.participants)
+            .unwrap_or_default()
+    }
+
+    #[method]
+    fn get_dialogue(&self, #[base] owner: &Node, id: String) -> Option<Instance<ArticyDialogue>> {
+        report(owner, ArticyDialogue::from_library(&self.library, &id))
+            .map(|dialogue| dialogue.emplace().into_shared())
+    }
+
+    #[method]
+    fn get_children(&self, #[base] owner: &Node, id: String) -> Vec<String> {
+        report(owner, self.library.children(&id))
+            .map(<[String]>::to_vec)
+            .unwrap_or_default()
+    }
+
+    // NOTE: Nested dictionaries of `id`, `type`, `display_name`, `technical_name` and `children`, e.g. to fill a Tree
+    #[method]
+    fn get_hierarchy_tree(
+        &self,
+        #[base] owner: &Node,
+        #[opt] root_id: Option<String>,
+    ) -> Option<Dictionary> {
+        report(owner, self.library.hierarchy(root_id.as_deref()))
+            .map(|node| hierarchy_to_dictionary(&node).into_shared())
+    }
+
+    #[method]
+    fn get_parent(&self, #[base] owner: &Node, id: String) -> Option<String> {
+        report(owner, self.library.parent(&id))
+            .flatten()
+            .map(str::to_owned)
+    }
+
+    #[method]
+    fn get_outgoing_connections(&self, #[base] owner: &Node, id: String) -> VariantArray {
+        connections_to_array(
+            report(owner, self.library.outgoing_connections(&id)).unwrap_or_default(),
+        )
+    }
+
+    #[method]
+    fn get_incoming_connections(&self, #[base] owner: &Node, id: String) -> VariantArray {
+        connections_to_array(
+            report(owner, self.library.incoming_connections(&id)).unwrap_or_default(),
+        )
+    }
+
+    #[method]
+    fn map_asset(&mut self, key: String, path: String) {
+        self.library.map_asset(&key, &path);
+    }
+
+    #[method]
+    fn get_asset_path(&self, #[base] owner: &Node, asset_id: String) -> Option<String> {
+        report(owner, self.library.asset_path(&asset_id, &asset_root()))
+    }
+
+    #[method]
+    fn resolve_asset(&self, #[base] owner: &Node, asset_id: String) -> Option<Ref<Resource>> {
+        let path = self.get_asset_path(owner, asset_id)?;
+        let resource = ResourceLoader::godot_singleton().load(path.as_str(), "", false);
+
+        if resource.is_none() {
+            report_error(
+                owner,
+                Error::InvalidResource(format!("no resource could be loaded from {path:?}")),
+            );
+        }
+
+        resource
+    }
+
+    #[method]
+    fn search_text(&self, #[base] owner: &Node, query: String) -> VariantArray {
+        let matches = report(owner, self.library.search_text(&query)).unwrap_or_default();
+
+        VariantArray::from_iter(
+            matches
+                .iter()
+                .map(|found| search_match_to_dictionary(found).owned_to_variant()),
+        )
+        .into_shared()
+    }
+
+    // NOTE: Leaving out `scope_id` counts the whole export, otherwise everything under that folder, flow or dialogue
+    #[method]
+    fn get_statistics(
+        &self,
+        #[base] owner: &Node,
+        #[opt] scope_id: Option<String>,
+    ) -> Option<Dictionary> {
+        report(
+            owner,
+            statistics::statistics(&self.library, scope_id.as_deref()),
+        )
+        .and_then(|statistics| json_to_variant(&statistics).to::<Dictionary>())
+    }
+
+    // NOTE: Node ids double as GraphNode names, `offset` and ports map onto GraphEdit as they are
+    #[method]
+    fn get_flow_graph(&self, #[base] owner: &Node, dialogue_id: String) -> Option<Dictionary> {
+        report(owner, graph::flow_graph(&self.library, &dialogue_id))
+            .map(|graph| flow_graph_to_dictionary(&graph).into_shared())
+    }
+
+    // NOTE: Memory figures are estimates in bytes, `source` being the export kept around for reloading
+    #[method]
+    fn get_diagnostics(&self, #[base] owner: &Node) -> Option<Dictionary> {
+        report(owner, self.library.diagnostics())
+            .and_then(|diagnostics| json_to_variant(&diagnostics).to::<Dictionary>())
+    }
+
+    // NOTE: Lists `unreachable` nodes, `dead_ends` and `single_option_hubs` inside the dialogue
+    #[method]
+    fn analyze_dialogue(&self, #[base] owner: &Node, id: String) -> Option<Dictionary> {
+        report(owner, graph::analyze_dialogue(&self.library, &id))
+            .and_then(|analysis| json_to_variant(&analysis).to::<Dictionary>())
+    }
+
+    // NOTE: A `max_depth` of 0 or less searches without a limit, an empty result means `to_id` is out of reach
+    #[method]
+    fn find_path(
+        &self,
+        #[base] owner: &Node,
+        from_id: String,
+        to_id: String,
+        #[opt] max_depth: i64,
+    ) -> Vec<String> {
+        let max_depth = usize::try_from(max_depth)
+            .ok()
+            .filter(|max_depth| *max_depth > 0);
+
+        report(
+            owner,
+            graph::find_path(&self.library, &from_id, &to_id, max_depth),
+        )
+        .unwrap_or_default()
+    }
+
+    #[method]
+    fn find_references(&self, #[base] owner: &Node, id: String) -> VariantArray {
+        let references = report(owner, self.library.find_references(&id)).unwrap_or_default();
+
+        VariantArray::from_iter(
+            references
+                .iter()
+                .map(|reference| reference_to_dictionary(reference).owned_to_variant()),
+        )
+        .into_shared()
+    }
+
+    // NOTE: Every issue has a "severity" of "error", "warning" or "info", so CI can fail on errors only
+    #[method]
+    fn validate(&self, #[base] owner: &Node) -> VariantArray {
+        let issues = report(owner, validation::validate(&self.library)).unwrap_or_default();
+
+        VariantArray::from_iter(
+            issues
+                .iter()
+                .map(|issue| issue_to_dictionary(issue).owned_to_variant()),
+        )
+        .into_shared()
+    }
+
+    #[method]
+    fn get_first_dialogue_fragment_of_dialogue(
+        &self,
+        #[base] owner: &Node,
+        id: String,
+    ) -> Option<ArticyModel<'_>> {
+        report(owner, self.library.first_dialogue_fragment_of_dialogue(&id))
+            .map(|model| ArticyModel(model, self.include_layout))
+    }
+}
+
+pub(super) fn asset_root() -> String {
+    let settings = gdnative::api::ProjectSettings::godot_singleton();
+
+    if settings.has_setting("articy/asset_root") {
+        settings.get_setting("articy/asset_root").to_string()
+    } else {
+        "res://".to_owned()
+    }
+}
+
+fn connections_to_array(connections: &[Connection]) -> VariantArray {
+    VariantArray::from_iter(
+        connections
+            .iter()
+            .map(|connection| connection_to_dictionary(connection).owned_to_variant()),
+    )
+    .into_shared()
+}
+
+pub(super) fn write_ids(path: &str, groups: &[ConstantGroup]) -> Result<(), Error> {
+    let contents = if path.ends_with(".cs") {
+        codegen::to_csharp(groups)
+    } else if path.ends_with(".gd") {
+        codegen::to_gdscript(groups)
+    } else {
+        return Err(Error::InvalidOption(format!(
+            "ids file {path:?} should end in .gd or .cs"
+        )));
+    };
+
+    let file = gdnative::api::File::new();
+    file.open(path, gdnative::api::File::WRITE)
+        .map_err(|error| Error::InvalidResource(format!("can't write to {path:?}: {error:?}")))?;
+
+    file.store_string(contents);
+    file.close();
+
+    Ok(())
+}
+
+pub(super) fn resource_bytes(resource: Ref<PackedDataContainer, Shared>) -> Result<Vec<u8>, Error> {
+    let resource = unsafe { resource.assume_safe() };
+    let data = resource.get("__data__");
+
+    if data.is_nil() {
+        return Err(Error::InvalidResource(
+            "resource has no __data__, it wasn't imported by the plugin".to_owned(),
+        ));
+    }
+
+    let bytes = data.to::<PoolArray<u8>>().ok_or_else(|| {
+        Error::InvalidResource("__data__ is not of type PoolArray<u8> (PoolByteArray)".to_owned())
+    })?;
+
+    if bytes.len() == 0 {
+        return Err(Error::InvalidResource("__data__ is empty".to_owned()));
+    }
+
+    Ok(bytes.to_vec())
+}
+
+fn file_bytes(path: &str) -> Result<Vec<u8>, Error> {
+    let file = gdnative::api::File::new();
+
+    file.open(path, gdnative::api::File::READ)
+        .map_err(|error| Error::InvalidResource(format!("can't open {path:?}: {error:?}")))?;
+
+    let bytes = file.get_buffer(file.get_len());
+    file.close();
+
+    Ok(bytes.to_vec())
+}
+
+// NOTE: Lets `owner` know through `method` when the Database at `path` gets unloaded. The Database is passed
+//       along, so signals from one that `owner` has stopped using can be told apart
+pub(super) fn connect_unloaded(owner: &Node, path: &NodePath, method: &str) -> Result<(), Error> {
+    let node = owner
+        .get_node(path.to_godot_string())
+        .ok_or_else(|| Error::NodeNotFound(path.to_string()))?;
+    let node = unsafe { node.assume_safe() };
+    let target = unsafe { owner.assume_shared() };
+
+    if node.is_connected("unloaded", target, method) {
+        return Ok(());
+    }
+
+    let binds = VariantArray::new();
+    binds.push(node.claim());
+
+    node.connect("unloaded", target, method, binds.into_shared(), 0)
+        .map_err(|error| Error::InvalidOption(format!("can't connect to the Database: {error:?}")))
+}
+
+pub(super) fn is_database(owner: &Node, path: &Option<NodePath>, database: Ref<Node>) -> bool {
+    let current = path
+        .as_ref()
+        .and_then(|path| owner.get_node(path.to_godot_string()));
+
+    match current {
+        Some(current) => unsafe {
+            current.assume_safe().get_instance_id() == database.assume_safe().get_instance_id()
+        },
+        None => false,
+    }
+}
+
+pub(super) fn with_database<T>(
+    owner: &Node,
+    path: &NodePath,
+    function: impl FnOnce(&Library) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let node = owner
+        .get_node(path.to_godot_string())
+        .ok_or_else(|| Error::NodeNotFound(path.to_string()))?;
+
+    unsafe {
+        node.assume_safe()
+            .cast_instance::<Database>()
+            .ok_or_else(|| Error::NotADatabase(path.to_string()))?
+            .map(|data, _base| function(&data.library))
+            .map_err(|_| Error::NotADatabase(path.to_string()))?
+    }
+}
@@ -0,0 +1,201 @@
+use super::{interpreter::Interpreter, report};
+use crate::{debug::DebugServer, error::Error, session::Session};
+use gdnative::prelude::*;
+use serde_json::{json, Value};
+
+// NOTE: Lets an external inspector attach to the Interpreter at `interpreter_path`. Requests are JSON objects on a
+//       line of their own, e.g. `{"command": "state", "sequence": 1}`, answered with the same `sequence` and either a
+//       `result` or an `error`. Breakpoint hits and entered nodes are pushed to every client as `event`s.
+#[derive(NativeClass)]
+#[inherit(Node)]
+#[register_with(Self::register_signals)]
+pub struct NarrativeDebugger {
+    #[property]
+    interpreter_path: Option<NodePath>,
+    #[property]
+    address: String,
+    server: Option<DebugServer>,
+}
+
+#[methods]
+impl NarrativeDebugger {
+    fn new(_base: &Node) -> Self {
+        NarrativeDebugger {
+            interpreter_path: None,
+            address: "127.0.0.1:6008".to_owned(),
+            server: None,
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder
+            .signal("error")
+            .with_param("code", VariantType::I64)
+            .with_param("message", VariantType::GodotString)
+            .done();
+    }
+
+    #[method]
+    fn _ready(&mut self, #[base] owner: &Node) {
+        owner.set_process(false);
+
+        if self.interpreter_path.is_some() {
+            self.listen(owner);
+        }
+    }
+
+    #[method]
+    fn listen(&mut self, #[base] owner: &Node) -> bool {
+        let server = self.interpreter(owner).and_then(|interpreter| {
+            let server = DebugServer::bind(&self.address)?;
+
+            for (signal, method) in [
+                ("breakpoint_hit", "_on_breakpoint_hit"),
+                ("node_entered", "_on_node_entered"),
+            ] {
+                interpreter
+                    .base()
+                    .connect(
+                        signal,
+                        unsafe { owner.assume_shared() },
+                        method,
+                        VariantArray::new_shared(),
+                        0,
+                    )
+                    .map_err(|error| {
+                        Error::DebugServerError(format!("can't watch {signal}: {error:?}"))
+                    })?;
+            }
+
+            Ok(server)
+        });
+
+        self.server = report(owner, server);
+        owner.set_process(self.server.is_some());
+
+        self.server.is_some()
+    }
+
+    #[method]
+    fn close(&mut self, #[base] owner: &Node) {
+        self.server = None;
+        owner.set_process(false);
+    }
+
+    #[method]
+    fn _process(&mut self, #[base] owner: &Node, _delta: f64) {
+        let requests = match &mut self.server {
+            Some(server) => server.poll(),
+            None => return,
+        };
+
+        for (client, request) in requests {
+            let sequence = request
+                .as_ref()
+                .map(|request| request["sequence"].clone())
+                .unwrap_or_default();
+
+            let response = match request.and_then(|request| self.handle(owner, &request)) {
+                Ok(result) => json!({"sequence": sequence, "result": result}),
+                Err(error) => json!({
+                    "sequence": sequence,
+                    "error": {"code": error.code(), "message": error.to_string()},
+                }),
+            };
+
+            if let Some(server) = &mut self.server {
+                server.respond(client, &response);
+            }
+        }
+    }
+
+    #[method]
+    fn _on_breakpoint_hit(&mut self, id: String) {
+        self.broadcast(json!({"event": "breakpoint_hit", "id": id}));
+    }
+
+    #[method]
+    fn _on_node_entered(&mut self, id: String, kind: String) {
+        self.broadcast(json!({"event": "node_entered", "id": id, "type": kind}));
+    }
+
+    fn broadcast(&mut self, message: Value) {
+        if let Some(server) = &mut self.server {
+            server.broadcast(&message);
+        }
+    }
+
+    fn interpreter(&self, owner: &Node) -> Result<TInstance<'_, Interpreter>, Error> {
+        let path = self
+            .interpreter_path
+            .as_ref()
+            .ok_or_else(|| Error::InvalidOption("interpreter_path isn't set".to_owned()))?;
+
+        let node = owner
+            .get_node(path.to_godot_string())
+            .ok_or_else(|| Error::NodeNotFound(path.to_string()))?;
+
+        unsafe { node.assume_safe() }
+            .cast_instance::<Interpreter>()
+            .ok_or_else(|| Error::InvalidOption(format!("{path:?} isn't an Interpreter")))
+    }
+
+    fn handle(&self, owner: &Node, request: &Value) -> Result<Value, Error> {
+        let interpreter = self.interpreter(owner)?;
+        let command = request["command"].as_str().unwrap_or_default();
+        let id = request["id"].as_str().unwrap_or_default().to_owned();
+
+        interpreter
+            .map_mut(|interpreter, base| match command {
+                "current" => Ok(match interpreter.session_ref() {
+                    Some(session) => json!({
+                        "running": session.is_running(),
+                        "paused": interpreter.is_paused(),
+                        "id": session.current_id(),
+                        "type": session.current_kind(),
+                    }),
+                    None => json!({"running": false, "paused": false}),
+                }),
+                "state" => {
+                    let session = interpreter
+                        .session_ref()
+                        .ok_or(Error::InterpreterNotSetup)?;
+
+                    Ok(Value::Object(session.states()?.into_iter().collect()))
+                }
+                "history" => Ok(Value::Array(
+                    interpreter
+                        .session_ref()
+                        .map(Session::history)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|entry| {
+                            json!({
+                                "id": entry.id,
+                                "speaker": entry.speaker,
+                                "text": entry.text,
+                                "timestamp": entry.timestamp,
+                                "chosen": entry.chosen,
+                            })
+                        })
+                        .collect(),
+                )),
+                "breakpoints" => Ok(json!(interpreter.breakpoints())),
+                "set_breakpoint" => {
+                    interpreter.set_breakpoint(id);
+                    Ok(Value::Bool(true))
+                }
+                "clear_breakpoint" => {
+                    interpreter.clear_breakpoint(id);
+                    Ok(Value::Bool(true))
+                }
+                "resume" => Ok(Value::Bool(interpreter.resume(&base))),
+                _ => Err(Error::InvalidOption(format!(
+                    "debugger command {command:?}"
+                ))),
+            })
+            .map_err(|error| {
+                Error::DebugServerError(format!("can't reach the Interpreter: {error:?}"))
+            })?
+    }
+}
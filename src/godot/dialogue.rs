@@ -0,0 +1,50 @@
+use crate::{
+    error::Error,
+    library::{DialogueOverview, Library},
+};
+use gdnative::prelude::*;
+
+// NOTE: A snapshot like ArticyEntity, ids are handed out so models can be fetched from the Database when needed
+#[derive(NativeClass)]
+#[inherit(Reference)]
+#[no_constructor]
+pub struct ArticyDialogue {
+    overview: DialogueOverview,
+}
+
+impl ArticyDialogue {
+    pub fn from_library(library: &Library, id: &str) -> Result<Self, Error> {
+        Ok(ArticyDialogue {
+            overview: library.dialogue_overview(id)?,
+        })
+    }
+}
+
+#[methods]
+impl ArticyDialogue {
+    #[method]
+    fn get_id(&self) -> String {
+        self.overview.id.to_owned()
+    }
+
+    #[method]
+    fn get_start_nodes(&self) -> Vec<String> {
+        self.overview.start_nodes.to_owned()
+    }
+
+    #[method]
+    fn get_fragments(&self) -> Vec<String> {
+        self.overview.fragments.to_owned()
+    }
+
+    // NOTE: Entity ids of every speaker, in the order they first speak
+    #[method]
+    fn get_participants(&self) -> Vec<String> {
+        self.overview.participants.to_owned()
+    }
+
+    #[method]
+    fn get_word_count(&self) -> i64 {
+        self.overview.word_count as i64
+    }
+}
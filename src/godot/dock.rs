@@ -0,0 +1,200 @@
+use super::database::resource_bytes;
+use crate::{error::Error, library::Library, model::model_json};
+use gdnative::api::{Button, LineEdit, PackedDataContainer, ProjectSettings, Tree, TreeItem, OS};
+use gdnative::prelude::*;
+
+// NOTE: Editor dock listing the export at "articy/autoload_database_path" the way it's nested in Articy. Activating
+//       an item copies its id, dragging one drops the id into text fields such as exported `String` properties.
+#[derive(NativeClass, Default)]
+#[inherit(VBoxContainer)]
+pub struct ArticyDock {
+    library: Library,
+    search: Option<Ref<LineEdit>>,
+    tree: Option<Ref<Tree>>,
+}
+
+#[methods]
+impl ArticyDock {
+    fn new(_base: &VBoxContainer) -> Self {
+        Default::default()
+    }
+
+    #[method]
+    fn _ready(&mut self, #[base] owner: TRef<VBoxContainer>) {
+        owner.set_name("Articy");
+
+        let toolbar = HBoxContainer::new();
+
+        let search = LineEdit::new();
+        search.set_placeholder("Search");
+        search.set_h_size_flags(Control::SIZE_EXPAND_FILL);
+        search
+            .connect(
+                "text_changed",
+                owner,
+                "_on_search_changed",
+                VariantArray::new_shared(),
+                0,
+            )
+            .ok();
+
+        let reload = Button::new();
+        reload.set_text("Reload");
+        reload
+            .connect("pressed", owner, "reload", VariantArray::new_shared(), 0)
+            .ok();
+
+        let tree = Tree::new();
+        tree.set_v_size_flags(Control::SIZE_EXPAND_FILL);
+        tree.set_hide_root(true);
+        tree.set_drag_forwarding(owner);
+        tree.connect(
+            "item_activated",
+            owner,
+            "_on_item_activated",
+            VariantArray::new_shared(),
+            0,
+        )
+        .ok();
+
+        let search = search.into_shared();
+        let tree = tree.into_shared();
+
+        toolbar.add_child(search.clone(), false);
+        toolbar.add_child(reload, false);
+        owner.add_child(toolbar, false);
+        owner.add_child(tree.clone(), false);
+
+        self.search = Some(search);
+        self.tree = Some(tree);
+
+        self.reload();
+    }
+
+    #[method]
+    fn reload(&mut self) {
+        if let Err(error) = self.load() {
+            godot_warn!("Articy dock couldn't load the export: {error}");
+        }
+
+        self.fill("");
+    }
+
+    #[method]
+    fn _on_search_changed(&mut self, query: String) {
+        self.fill(&query);
+    }
+
+    #[method]
+    fn _on_item_activated(&self) {
+        if let Some(id) = self.selected_id() {
+            OS::godot_singleton().set_clipboard(id);
+        }
+    }
+
+    #[method]
+    fn get_drag_data_fw(&self, _position: Vector2, _from: Ref<Control>) -> Variant {
+        match self.selected_id() {
+            Some(id) => id.to_variant(),
+            None => Variant::nil(),
+        }
+    }
+
+    fn load(&mut self) -> Result<(), Error> {
+        let settings = ProjectSettings::godot_singleton();
+        if !settings.has_setting("articy/autoload_database_path") {
+            return Err(Error::InvalidOption(
+                "\"articy/autoload_database_path\" isn't set".to_owned(),
+            ));
+        }
+
+        let path = settings
+            .get_setting("articy/autoload_database_path")
+            .to_string();
+        let resource = load::<PackedDataContainer>(path.as_str()).ok_or_else(|| {
+            Error::InvalidResource(format!("{path:?} isn't an imported Articy export"))
+        })?;
+
+        self.library.load(&resource_bytes(resource)?)
+    }
+
+    fn selected_id(&self) -> Option<String> {
+        let tree = unsafe { self.tree.as_ref()?.assume_safe() };
+        let item = unsafe { tree.get_selected()?.assume_safe() };
+
+        Some(item.get_metadata(0).to_string())
+    }
+
+    fn fill(&self, query: &str) {
+        let tree = match &self.tree {
+            Some(tree) => unsafe { tree.assume_safe() },
+            None => return,
+        };
+
+        tree.clear();
+        let root = match tree.create_item(Null::<Object>::null(), -1) {
+            Some(root) => root,
+            None => return,
+        };
+
+        let query = query.to_lowercase();
+
+        for id in self.library.root_ids().unwrap_or_default() {
+            self.add_item(tree, &root, &id, &query);
+        }
+    }
+
+    // NOTE: While searching, only matches and the items leading to them are shown
+    fn add_item(&self, tree: TRef<Tree>, parent: &Ref<TreeItem>, id: &str, query: &str) {
+        if !self.matches(id, query) {
+            return;
+        }
+
+        let item = match tree.create_item(parent, -1) {
+            Some(item) => item,
+            None => return,
+        };
+
+        for child in self.library.children(id).unwrap_or_default() {
+            self.add_item(tree, &item, child, query);
+        }
+
+        let item = unsafe { item.assume_safe() };
+        item.set_text(0, self.label(id));
+        item.set_tooltip(0, id);
+        item.set_metadata(0, id.to_variant());
+        item.set_collapsed(query.is_empty());
+    }
+
+    fn matches(&self, id: &str, query: &str) -> bool {
+        query.is_empty()
+            || id.to_lowercase().contains(query)
+            || self.label(id).to_lowercase().contains(query)
+            || self
+                .library
+                .children(id)
+                .unwrap_or_default()
+                .iter()
+                .any(|child| self.matches(child, query))
+    }
+
+    fn label(&self, id: &str) -> String {
+        let json = self
+            .library
+            .find_model(id)
+            .map(model_json)
+            .unwrap_or_default();
+        let properties = &json["Properties"];
+
+        let name = ["DisplayName", "TechnicalName"]
+            .into_iter()
+            .find_map(|property| {
+                properties[property]
+                    .as_str()
+                    .filter(|name| !name.is_empty())
+            })
+            .unwrap_or(id);
+
+        format!("{name} ({})", json["Type"].as_str().unwrap_or_default())
+    }
+}
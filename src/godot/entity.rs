@@ -0,0 +1,107 @@
+use super::{convert::property_to_variant, database::asset_root};
+use crate::{
+    error::Error,
+    library::Library,
+    model::{self, model_json},
+    session::{speaker_details, Speaker},
+};
+use gdnative::api::{Resource, ResourceLoader};
+use gdnative::prelude::*;
+use serde_json::Value;
+
+// NOTE: A snapshot of an entity taken from the Database, it doesn't follow the Database reloading
+#[derive(NativeClass)]
+#[inherit(Reference)]
+#[no_constructor]
+pub struct ArticyEntity {
+    id: String,
+    json: Value,
+    // NOTE: "Feature.Property" of the template properties the export defines as colors
+    colors: Vec<String>,
+    speaker: Speaker,
+    portrait_path: Option<String>,
+}
+
+impl ArticyEntity {
+    pub fn from_library(library: &Library, id: &str) -> Result<Self, Error> {
+        let model = library.find_model(id)?;
+        let mut speaker = speaker_details(model);
+        library.entity_overrides().apply(id, &mut speaker);
+
+        // NOTE: A portrait that isn't an asset is left out rather than failing the whole entity
+        let portrait_path = speaker
+            .portrait
+            .as_ref()
+            .and_then(|asset| library.asset_path(asset, &asset_root()).ok());
+
+        let json = model_json(model);
+        let colors = json["Template"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .flat_map(|(feature, properties)| {
+                properties
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(move |(property, _)| format!("{feature}.{property}"))
+            })
+            .filter(|path| library.is_color(path))
+            .collect();
+
+        Ok(ArticyEntity {
+            id: id.to_owned(),
+            json,
+            colors,
+            speaker,
+            portrait_path,
+        })
+    }
+}
+
+#[methods]
+impl ArticyEntity {
+    #[method]
+    fn get_id(&self) -> String {
+        self.id.to_owned()
+    }
+
+    #[method]
+    fn get_technical_name(&self) -> String {
+        self.json["Properties"]["TechnicalName"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned()
+    }
+
+    #[method]
+    fn get_display_name(&self) -> String {
+        self.speaker.name.to_owned().unwrap_or_default()
+    }
+
+    #[method]
+    fn get_color(&self) -> Option<Color> {
+        self.speaker
+            .color
+            .map(|[r, g, b, a]| Color::from_rgba(r, g, b, a))
+    }
+
+    // NOTE: Paths start inside the template, e.g. "Character.Age"
+    #[method]
+    fn get_template_property(&self, path: String) -> Variant {
+        model::property_at(self.json.to_owned(), &format!("Template.{path}"))
+            .map(|value| property_to_variant(&value, self.colors.contains(&path)))
+            .unwrap_or_default()
+    }
+
+    #[method]
+    fn get_portrait_asset_id(&self) -> Option<String> {
+        self.speaker.portrait.to_owned()
+    }
+
+    #[method]
+    fn get_portrait(&self) -> Option<Ref<Resource>> {
+        let path = self.portrait_path.as_ref()?;
+        ResourceLoader::godot_singleton().load(path.as_str(), "", false)
+    }
+}
@@ -0,0 +1,232 @@
+use super::{database::write_ids, dock::ArticyDock};
+use crate::{
+    codegen,
+    error::Error,
+    library::{self, Library},
+};
+use gdnative::api::{
+    EditorImportPlugin, EditorPlugin, File, GlobalConstants, PackedDataContainer, ResourceSaver,
+};
+use gdnative::prelude::*;
+use serde_json::Value;
+
+#[derive(NativeClass, Default)]
+#[inherit(EditorImportPlugin)]
+pub struct ArticyImportPlugin;
+
+#[methods]
+impl ArticyImportPlugin {
+    fn new(_base: &EditorImportPlugin) -> Self {
+        Default::default()
+    }
+
+    #[method]
+    fn get_importer_name(&self) -> String {
+        "articy.export".to_owned()
+    }
+
+    #[method]
+    fn get_visible_name(&self) -> String {
+        "Articy Export".to_owned()
+    }
+
+    #[method]
+    fn get_recognized_extensions(&self) -> Vec<String> {
+        vec!["json".to_owned()]
+    }
+
+    #[method]
+    fn get_save_extension(&self) -> String {
+        "res".to_owned()
+    }
+
+    #[method]
+    fn get_resource_type(&self) -> String {
+        "PackedDataContainer".to_owned()
+    }
+
+    #[method]
+    fn get_preset_count(&self) -> i64 {
+        1
+    }
+
+    #[method]
+    fn get_preset_name(&self, _preset: i64) -> String {
+        "Default".to_owned()
+    }
+
+    #[method]
+    fn get_import_options(&self, _preset: i64) -> VariantArray {
+        let packages = Dictionary::new();
+        packages.insert("name", "packages");
+        packages.insert("default_value", "");
+        packages.insert(
+            "hint_string",
+            "Comma separated package names, empty keeps all",
+        );
+
+        let compress = Dictionary::new();
+        compress.insert("name", "compress");
+        compress.insert("default_value", true);
+
+        let ids_path = Dictionary::new();
+        ids_path.insert("name", "ids_path");
+        ids_path.insert("default_value", "");
+        ids_path.insert("property_hint", GlobalConstants::PROPERTY_HINT_SAVE_FILE);
+        ids_path.insert("hint_string", "*.gd,*.cs");
+
+        let precompile = Dictionary::new();
+        precompile.insert("name", "precompile");
+        precompile.insert("default_value", false);
+
+        VariantArray::from_iter([
+            packages.owned_to_variant(),
+            compress.owned_to_variant(),
+            precompile.owned_to_variant(),
+            ids_path.owned_to_variant(),
+        ])
+        .into_shared()
+    }
+
+    #[method]
+    fn get_option_visibility(&self, _option: String, _options: Dictionary) -> bool {
+        true
+    }
+
+    #[method]
+    fn import(
+        &self,
+        source_file: String,
+        save_path: String,
+        options: Dictionary,
+        _platform_variants: VariantArray,
+        _gen_files: VariantArray,
+    ) -> i64 {
+        match import_export(&source_file, &save_path, &options) {
+            Ok(()) => 0,
+            Err(error) => {
+                godot_error!("Failed to import {source_file:?} as Articy export: {error}");
+                GodotError::Failed as i64
+            }
+        }
+    }
+}
+
+#[derive(NativeClass, Default)]
+#[inherit(EditorPlugin)]
+pub struct ArticyEditorPlugin {
+    import_plugin: Option<Instance<ArticyImportPlugin, Shared>>,
+    dock: Option<Instance<ArticyDock, Shared>>,
+}
+
+#[methods]
+impl ArticyEditorPlugin {
+    fn new(_base: &EditorPlugin) -> Self {
+        Default::default()
+    }
+
+    #[method]
+    fn _enter_tree(&mut self, #[base] owner: &EditorPlugin) {
+        let import_plugin = ArticyImportPlugin::new_instance().into_shared();
+        owner.add_import_plugin(import_plugin.base());
+
+        self.import_plugin = Some(import_plugin);
+
+        let dock = ArticyDock::new_instance().into_shared();
+        owner.add_control_to_dock(EditorPlugin::DOCK_SLOT_LEFT_UR, dock.base());
+
+        self.dock = Some(dock);
+    }
+
+    #[method]
+    fn _exit_tree(&mut self, #[base] owner: &EditorPlugin) {
+        if let Some(import_plugin) = self.import_plugin.take() {
+            owner.remove_import_plugin(import_plugin.base());
+        }
+
+        if let Some(dock) = self.dock.take() {
+            owner.remove_control_from_docks(dock.base());
+            unsafe { dock.base().assume_safe() }.queue_free();
+        }
+    }
+}
+
+fn import_export(source_file: &str, save_path: &str, options: &Dictionary) -> Result<(), Error> {
+    let file = File::new();
+    file.open(source_file, File::READ)
+        .map_err(|error| Error::InvalidResource(format!("can't open file: {error:?}")))?;
+
+    let bytes = file.get_buffer(file.get_len()).to_vec();
+    file.close();
+
+    let packages = options
+        .get("packages")
+        .map(|packages| packages.to_string())
+        .unwrap_or_default();
+
+    // NOTE: Only reserialize when filtering, so unfiltered imports stay byte for byte what Articy exported
+    let bytes = if packages.trim().is_empty() {
+        bytes
+    } else {
+        filter_packages(&bytes, &packages)?
+    };
+
+    let ids_path = options
+        .get("ids_path")
+        .map(|path| path.to_string())
+        .unwrap_or_default();
+
+    // NOTE: Generated from what gets imported, so filtered out packages don't end up in there either
+    if !ids_path.is_empty() {
+        let mut library = Library::default();
+        library.load(&bytes)?;
+
+        write_ids(&ids_path, &codegen::id_constants(&library)?)?;
+    }
+
+    let bytes = match options
+        .get("precompile")
+        .and_then(|precompile| precompile.to::<bool>())
+    {
+        Some(true) => library::precompile(&bytes)?,
+        _ => bytes,
+    };
+
+    let container = PackedDataContainer::new();
+    container.set("__data__", PoolArray::<u8>::from_vec(bytes));
+
+    let flags = match options
+        .get("compress")
+        .and_then(|compress| compress.to::<bool>())
+    {
+        Some(false) => 0,
+        _ => ResourceSaver::FLAG_COMPRESS,
+    };
+
+    ResourceSaver::godot_singleton()
+        .save(format!("{save_path}.res"), container, flags)
+        .map_err(|error| Error::InvalidResource(format!("can't save resource: {error:?}")))
+}
+
+fn filter_packages(bytes: &[u8], packages: &str) -> Result<Vec<u8>, Error> {
+    let names = packages
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<&str>>();
+
+    let mut json = serde_json::from_slice::<Value>(bytes)
+        .map_err(|error| Error::InvalidResource(format!("export isn't valid JSON: {error}")))?;
+
+    json.get_mut("Packages")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| Error::InvalidResource("export has no \"Packages\"".to_owned()))?
+        .retain(|package| {
+            package["Name"]
+                .as_str()
+                .map_or(false, |name| names.contains(&name))
+        });
+
+    serde_json::to_vec(&json)
+        .map_err(|error| Error::InvalidResource(format!("can't serialize export: {error}")))
+}
@@ -0,0 +1,1839 @@
+use super::{
+    caught,
+    convert::{
+        choice_to_dictionary, dictionary_to_states, history_entry_to_dictionary, json_to_variant,
+        line_to_dictionary, property_to_variant, states_to_dictionary, variant_to_json,
+        ArticyModel,
+    },
+    database::{asset_root, connect_unloaded, is_database, with_database, Database},
+    report, report_error,
+};
+use crate::{
+    error::Error,
+    library::{Library, SharedLibrary},
+    model::model_json,
+    script,
+    session::{
+        ChoiceSortMode, Event, Function, HubStrategy, SavedState, Session,
+        DEFAULT_FALLBACK_PROPERTY, DEFAULT_MAX_ITERATIONS, DEFAULT_TIME_LIMIT_PROPERTY,
+        DEFAULT_VOICE_OVER_PROPERTY,
+    },
+    soak,
+};
+use gdnative::api::{Engine, File, GlobalConstants};
+use gdnative::prelude::*;
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
+
+// NOTE: Bumped whenever the layout of `save_state` changes in a way older versions can't read
+const SAVE_VERSION: u64 = 1;
+
+#[derive(NativeClass, Default)]
+#[inherit(Node)]
+#[register_with(Self::register_signals)]
+pub struct Interpreter {
+    #[property]
+    database_path: Option<NodePath>,
+    // NOTE: One of "ask", "first", "priority" or "random"
+    #[property(set = "Self::set_hub_strategy")]
+    hub_strategy: String,
+    // NOTE: One of "connection" (the default), "priority", "position" or "none" to keep the order articy-rs gives
+    #[property(set = "Self::set_choice_sort_mode")]
+    choice_sort_mode: String,
+    #[property(set = "Self::set_emit_bbcode")]
+    emit_bbcode: bool,
+    // NOTE: Puts template feature properties straight into "line" and "choices", e.g. `line.emotion`, next to `template`
+    #[property(set = "Self::set_flatten_template")]
+    flatten_template: bool,
+    // NOTE: Fills in placeholders like `{Player.Name}`, `{Inventory.Gold}` or tokens from `set_token`, `{{` and `}}`
+    //       being literal braces
+    #[property(set = "Self::set_interpolate_text")]
+    interpolate_text: bool,
+    // NOTE: Lines following each other from the same speaker go out together through "lines" instead of "line"
+    #[property(set = "Self::set_group_lines")]
+    group_lines: bool,
+    // NOTE: Adds choices whose condition failed to the "choices" signal, flagged with `available: false`
+    #[property(set = "Self::set_include_unavailable_choices")]
+    include_unavailable_choices: bool,
+    // NOTE: Leaves out choices taken before, otherwise only the ones with a true "OnceOnly" property are left out
+    #[property(set = "Self::set_hide_seen_choices")]
+    hide_seen_choices: bool,
+    // NOTE: Model types to advance through silently, e.g. "FlowFragment", "Hub" or "Jump"
+    #[property(set = "Self::set_skip_types")]
+    skip_types: PoolArray<GodotString>,
+    // NOTE: Steps without reaching a line, choice or the end before giving up, 0 keeps the default of 1000
+    #[property(set = "Self::set_max_iterations")]
+    max_iterations: i64,
+    // NOTE: Records every node and choice passed for `get_coverage_report`, turning it off clears the record
+    #[property(set = "Self::set_coverage")]
+    coverage: bool,
+    // NOTE: Empty keeps the default of "VoiceOver"
+    #[property(set = "Self::set_voice_over_property")]
+    voice_over_property: String,
+    // NOTE: Empty keeps the defaults of "IsFallback" and "TimeLimit", both show up in the "choices" payload
+    #[property(set = "Self::set_fallback_property")]
+    fallback_property: String,
+    #[property(set = "Self::set_time_limit_property")]
+    time_limit_property: String,
+    // NOTE: Adds `voice_over_path` and a loaded `voice_over_stream` to lines, through the Database's asset mapping
+    #[property]
+    resolve_voice_over: bool,
+    // NOTE: Runs lines through `tr()`, keyed by "id" or "technical_name", keeping the Articy text when untranslated
+    #[property]
+    use_translation_keys: bool,
+    #[property]
+    translation_key: String,
+    // NOTE: Starts `start_node` once the database is set
+    #[property]
+    autostart: bool,
+    // NOTE: Listed through `_get_property_list`, so the inspector can offer the Database's dialogues to pick from
+    start_node: String,
+    session: Option<Session>,
+    functions: HashMap<String, (Ref<Object>, String)>,
+    middleware: Vec<(Ref<Object>, String)>,
+    model_handlers: HashMap<String, (Ref<Object>, String)>,
+    tokens: HashMap<String, String>,
+    watches: Vec<(String, String)>,
+    rng_seed: Option<u64>,
+    breakpoints: HashSet<String>,
+    // NOTE: Events held back since a breakpoint was hit, the first one being the breakpoint's `NodeEntered`
+    paused: Option<Vec<Event>>,
+    recording: Option<Recording>,
+}
+
+// NOTE: Calls made on the Interpreter since recording started, as `{"time": ..., "action": ..., "arguments": [...]}`
+//       with the time in seconds
+struct Recording {
+    started: Instant,
+    actions: Vec<Value>,
+}
+
+#[methods]
+impl Interpreter {
+    fn new(_base: &Node) -> Self {
+        Default::default()
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder
+            .signal("database_changed")
+            .with_param("path", VariantType::NodePath)
+            .done();
+
+        builder
+            .signal("started")
+            .with_param("id", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("breakpoint_hit")
+            .with_param("id", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("node_entered")
+            .with_param("id", VariantType::GodotString)
+            .with_param("type", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("line")
+            .with_param("line", VariantType::Dictionary)
+            .done();
+
+        builder
+            .signal("lines")
+            .with_param("lines", VariantType::VariantArray)
+            .done();
+
+        builder
+            .signal("choices")
+            .with_param("choices", VariantType::VariantArray)
+            .done();
+
+        builder
+            .signal("model")
+            .with_param("model", VariantType::Dictionary)
+            .done();
+
+        builder
+            .signal("condition_evaluated")
+            .with_param("id", VariantType::GodotString)
+            .with_param("expression", VariantType::GodotString)
+            .with_param("result", VariantType::Bool)
+            .done();
+
+        builder
+            .signal("state_changed")
+            .with_param("key", VariantType::GodotString)
+            .with_param("old_value", VariantType::Nil)
+            .with_param("new_value", VariantType::Nil)
+            .done();
+
+        builder
+            .signal("hub_entered")
+            .with_param("id", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("dialogue_pushed")
+            .with_param("id", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("dialogue_popped")
+            .with_param("cursor", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("jump_followed")
+            .with_param("from_id", VariantType::GodotString)
+            .with_param("target_id", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("instruction_executed")
+            .with_param("id", VariantType::GodotString)
+            .with_param("expression", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("pin_condition_evaluated")
+            .with_param("id", VariantType::GodotString)
+            .with_param("pin_id", VariantType::GodotString)
+            .with_param("expression", VariantType::GodotString)
+            .with_param("result", VariantType::Bool)
+            .done();
+
+        builder
+            .signal("pin_instruction_executed")
+            .with_param("id", VariantType::GodotString)
+            .with_param("pin_id", VariantType::GodotString)
+            .with_param("expression", VariantType::GodotString)
+            .done();
+
+        // NOTE: `reason` is either "ended", "stopped" or "error"
+        builder
+            .signal("stopped")
+            .with_param("id", VariantType::GodotString)
+            .with_param("reason", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("watch_triggered")
+            .with_param("name", VariantType::GodotString)
+            .with_param("value", VariantType::Nil)
+            .done();
+
+        builder
+            .signal("speaker_changed")
+            .with_param("previous_id", VariantType::GodotString)
+            .with_param("new_id", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("save_entry_unmapped")
+            .with_param("id", VariantType::GodotString)
+            .with_param("kind", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("error")
+            .with_param("code", VariantType::I64)
+            .with_param("message", VariantType::GodotString)
+            .done();
+    }
+
+    pub(super) fn session_ref(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    pub(super) fn breakpoints(&self) -> &HashSet<String> {
+        &self.breakpoints
+    }
+
+    pub(super) fn session(&mut self) -> Result<&mut Session, Error> {
+        self.session.as_mut().ok_or(Error::InterpreterNotSetup)
+    }
+
+    fn hooks(&self) -> Hooks<'_> {
+        Hooks {
+            middleware: &self.middleware,
+            model_handlers: &self.model_handlers,
+            group_lines: self.group_lines,
+        }
+    }
+
+    fn record(&mut self, action: &str, arguments: Vec<Value>) {
+        if let Some(recording) = &mut self.recording {
+            recording.actions.push(json!({
+                "time": recording.started.elapsed().as_secs_f64(),
+                "action": action,
+                "arguments": arguments,
+            }));
+        }
+    }
+
+    fn step(
+        &mut self,
+        owner: &Node,
+        step: impl FnOnce(&mut Session) -> Result<Vec<Event>, Error>,
+    ) -> bool {
+        let events = self.session().and_then(|session| caught(|| step(session)));
+
+        self.emit(owner, events)
+    }
+
+    fn emit(&mut self, owner: &Node, mut events: Result<Vec<Event>, Error>) -> bool {
+        // NOTE: Translated from the key, the session then marks up and interpolates the translation like its own text
+        if let (true, Ok(events), Some(session)) =
+            (self.use_translation_keys, &mut events, &mut self.session)
+        {
+            for event in events {
+                if let Event::Line(line) = event {
+                    let key = match self.translation_key.as_str() {
+                        "technical_name" => line.technical_name.to_owned(),
+                        _ => line.id.to_owned(),
+                    };
+
+                    let translated = owner.tr(key.as_str()).to_string();
+                    if translated != key {
+                        session.retranslate(line, &translated);
+                    }
+
+                    line.key = Some(key);
+                }
+            }
+        }
+
+        if let (true, Some(path), Ok(events)) =
+            (self.resolve_voice_over, &self.database_path, &mut events)
+        {
+            for event in events {
+                if let Event::Line(line) = event {
+                    line.voice_over_path = line.voice_over.as_ref().and_then(|voice_over| {
+                        let root = asset_root();
+                        let path = with_database(owner, path, |library| {
+                            library.asset_path(voice_over, &root)
+                        });
+
+                        report(owner, path)
+                    });
+                }
+            }
+        }
+
+        let failed = matches!(events, Err(Error::ArticyError(..) | Error::Panicked(..)));
+        let emitted = match events {
+            Ok(events) => {
+                self.release(owner, events);
+                true
+            }
+            Err(error) => emit_events(owner, Err(error), &[]),
+        };
+
+        if let (true, Some(session)) = (failed, &mut self.session) {
+            if session.is_running() {
+                let events = session.abort();
+                self.release(owner, events);
+            }
+        }
+
+        emitted
+    }
+
+    // NOTE: The session has already moved on by the time a breakpoint is hit, only the signals are held back. Events
+    //       of steps taken while paused queue up behind them.
+    fn release(&mut self, owner: &Node, mut events: Vec<Event>) {
+        if let Some(paused) = &mut self.paused {
+            paused.extend(events);
+            return;
+        }
+
+        let hit = events
+            .iter()
+            .enumerate()
+            .find_map(|(position, event)| match event {
+                Event::NodeEntered { id, .. } if self.breakpoints.contains(id) => {
+                    Some((position, id.to_owned()))
+                }
+                _ => None,
+            });
+
+        match hit {
+            Some((position, id)) => {
+                self.paused = Some(events.split_off(position));
+                emit_filtered(owner, Ok(events), &[], &self.hooks());
+
+                owner.emit_signal("breakpoint_hit", &[id.to_variant()]);
+            }
+            None => {
+                emit_filtered(owner, Ok(events), &[], &self.hooks());
+            }
+        }
+    }
+
+    fn set_emit_bbcode(&mut self, _owner: TRef<Node>, emit_bbcode: bool) {
+        if let Some(session) = &mut self.session {
+            session.set_emit_bbcode(emit_bbcode);
+        }
+
+        self.emit_bbcode = emit_bbcode;
+    }
+
+    fn set_flatten_template(&mut self, _owner: TRef<Node>, flatten_template: bool) {
+        if let Some(session) = &mut self.session {
+            session.set_flatten_template(flatten_template);
+        }
+
+        self.flatten_template = flatten_template;
+    }
+
+    fn set_group_lines(&mut self, _owner: TRef<Node>, group_lines: bool) {
+        if let Some(session) = &mut self.session {
+            session.set_group_lines(group_lines);
+        }
+
+        self.group_lines = group_lines;
+    }
+
+    fn set_interpolate_text(&mut self, _owner: TRef<Node>, interpolate_text: bool) {
+        if let Some(session) = &mut self.session {
+            session.set_interpolate(interpolate_text);
+        }
+
+        self.interpolate_text = interpolate_text;
+    }
+
+    fn set_include_unavailable_choices(&mut self, _owner: TRef<Node>, include: bool) {
+        if let Some(session) = &mut self.session {
+            session.set_include_unavailable(include);
+        }
+
+        self.include_unavailable_choices = include;
+    }
+
+    fn set_hide_seen_choices(&mut self, _owner: TRef<Node>, hide: bool) {
+        if let Some(session) = &mut self.session {
+            session.set_hide_seen_choices(hide);
+        }
+
+        self.hide_seen_choices = hide;
+    }
+
+    fn set_skip_types(&mut self, _owner: TRef<Node>, skip_types: PoolArray<GodotString>) {
+        if let Some(session) = &mut self.session {
+            session.set_skip_types(string_vec(&skip_types));
+        }
+
+        self.skip_types = skip_types;
+    }
+
+    fn set_voice_over_property(&mut self, _owner: TRef<Node>, property: String) {
+        if let Some(session) = &mut self.session {
+            session.set_voice_over_property(property_name(&property, DEFAULT_VOICE_OVER_PROPERTY));
+        }
+
+        self.voice_over_property = property;
+    }
+
+    fn set_fallback_property(&mut self, _owner: TRef<Node>, property: String) {
+        if let Some(session) = &mut self.session {
+            session.set_fallback_property(property_name(&property, DEFAULT_FALLBACK_PROPERTY));
+        }
+
+        self.fallback_property = property;
+    }
+
+    fn set_time_limit_property(&mut self, _owner: TRef<Node>, property: String) {
+        if let Some(session) = &mut self.session {
+            session.set_time_limit_property(property_name(&property, DEFAULT_TIME_LIMIT_PROPERTY));
+        }
+
+        self.time_limit_property = property;
+    }
+
+    fn set_coverage(&mut self, _owner: TRef<Node>, coverage: bool) {
+        if let Some(session) = &mut self.session {
+            session.set_coverage(coverage);
+        }
+
+        self.coverage = coverage;
+    }
+
+    fn set_max_iterations(&mut self, _owner: TRef<Node>, max_iterations: i64) {
+        if let Some(session) = &mut self.session {
+            session.set_max_iterations(iterations(max_iterations));
+        }
+
+        self.max_iterations = max_iterations;
+    }
+
+    fn set_hub_strategy(&mut self, owner: TRef<Node>, strategy: String) {
+        if let Some(parsed) = report(&owner, strategy.parse::<HubStrategy>()) {
+            if let Some(session) = &mut self.session {
+                session.set_hub_strategy(parsed);
+            }
+
+            self.hub_strategy = strategy;
+        }
+    }
+
+    fn set_choice_sort_mode(&mut self, owner: TRef<Node>, mode: String) {
+        if let Some(parsed) = report(&owner, mode.parse::<ChoiceSortMode>()) {
+            if let Some(session) = &mut self.session {
+                session.set_choice_sort_mode(parsed);
+            }
+
+            self.choice_sort_mode = mode;
+        }
+    }
+
+    #[method]
+    fn _ready(&mut self, #[base] owner: &Node) {
+        if Engine::godot_singleton().is_editor_hint() {
+            return;
+        }
+
+        if let Some(path) = &self.database_path {
+            self.set_database(owner, path.new_ref(), false);
+        }
+
+        if self.autostart && self.session.is_some() && !self.start_node.is_empty() {
+            let id = start_node_id(&self.start_node).to_owned();
+            self.start(owner, id);
+        }
+    }
+
+    // NOTE: Absolute paths usually point at an AutoLoad, which isn't around in the editor to check
+    #[method]
+    fn _get_configuration_warning(&self, #[base] owner: &Node) -> String {
+        let mut warnings = vec![];
+
+        if let Some(path) = &self.database_path {
+            let display = path.to_string();
+            let is_absolute = display.starts_with('/');
+
+            match owner.get_node(path.to_godot_string()) {
+                Some(node)
+                    if unsafe { node.assume_safe() }
+                        .cast_instance::<Database>()
+                        .is_none() =>
+                {
+                    warnings.push(format!(
+                        "`database_path` ({display}) doesn't point to a Database."
+                    ))
+                }
+                None if !is_absolute => warnings.push(format!(
+                    "`database_path` ({display}) doesn't point to a node."
+                )),
+                _ => {}
+            }
+        }
+
+        if self.autostart && self.start_node.is_empty() {
+            warnings.push("`autostart` is on, but there's no `start_node` to start.".to_owned());
+        }
+
+        warnings.join("\n")
+    }
+
+    // NOTE: Dialogues are only offered when the Database is in the edited scene, otherwise any id can be typed in
+    #[method]
+    fn _get_property_list(&self, #[base] owner: &Node) -> VariantArray {
+        let dialogues = self
+            .database_path
+            .as_ref()
+            .filter(|path| owner.has_node(path.to_godot_string()))
+            .and_then(|path| {
+                with_database(owner, path, |library| {
+                    Ok(library
+                        .models_of_type("Dialogue")?
+                        .into_iter()
+                        .map(|dialogue| {
+                            let name = model_json(dialogue)["Properties"]["DisplayName"]
+                                .as_str()
+                                .unwrap_or_default()
+                                .replace(',', " ");
+
+                            format!("{name} ({})", dialogue.id().to_inner())
+                        })
+                        .collect::<Vec<String>>())
+                })
+                .ok()
+            })
+            .unwrap_or_default();
+
+        let property = Dictionary::new();
+        property.insert("name", "start_node");
+        property.insert("type", VariantType::GodotString as i64);
+        property.insert("usage", GlobalConstants::PROPERTY_USAGE_DEFAULT);
+
+        if !dialogues.is_empty() {
+            property.insert("hint", GlobalConstants::PROPERTY_HINT_ENUM);
+            property.insert("hint_string", dialogues.join(","));
+        }
+
+        VariantArray::from_iter([property.owned_to_variant()]).into_shared()
+    }
+
+    #[method]
+    fn _get(&self, property: String) -> Variant {
+        match property.as_str() {
+            "start_node" => self.start_node.to_variant(),
+            _ => Variant::nil(),
+        }
+    }
+
+    #[method]
+    fn _set(&mut self, property: String, value: Variant) -> bool {
+        match property.as_str() {
+            "start_node" => {
+                self.start_node = value.to_string();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    #[method]
+    // TODO: Perhaps do a getter and a setter on the node_path exported property instead of a method
+    // NOTE: Also used to swap in a reloaded export, which stops the running dialogue and starts over on a fresh session
+    fn set_database(
+        &mut self,
+        #[base] owner: &Node,
+        path: NodePath,
+        #[opt] migrate_state: bool,
+    ) -> bool {
+        if let Some(shared) = report(owner, get_database(owner, &path)) {
+            self.database_path = Some(path.new_ref());
+
+            let mut states = vec![];
+            self.paused = None;
+            if let Some(mut previous) = self.session.take() {
+                if migrate_state {
+                    states = report(owner, previous.states()).unwrap_or_default();
+                }
+
+                if previous.is_running() {
+                    emit_events(owner, Ok(previous.stop()), &[]);
+                }
+            }
+
+            // NOTE: You can also just add the Database in your scene instead of as an AutoLoad, and refer to it with $Database
+            let mut session = Session::new(shared);
+            session.set_hub_strategy(self.hub_strategy.parse().unwrap_or_default());
+            session.set_choice_sort_mode(self.choice_sort_mode.parse().unwrap_or_default());
+            session.set_emit_bbcode(self.emit_bbcode);
+            session.set_flatten_template(self.flatten_template);
+            session.set_interpolate(self.interpolate_text);
+            session.set_group_lines(self.group_lines);
+            session.set_include_unavailable(self.include_unavailable_choices);
+            session.set_hide_seen_choices(self.hide_seen_choices);
+            session.set_skip_types(string_vec(&self.skip_types));
+            session.set_max_iterations(iterations(self.max_iterations));
+            session.set_coverage(self.coverage);
+            session.set_voice_over_property(property_name(
+                &self.voice_over_property,
+                DEFAULT_VOICE_OVER_PROPERTY,
+            ));
+            session.set_fallback_property(property_name(
+                &self.fallback_property,
+                DEFAULT_FALLBACK_PROPERTY,
+            ));
+            session.set_time_limit_property(property_name(
+                &self.time_limit_property,
+                DEFAULT_TIME_LIMIT_PROPERTY,
+            ));
+            if let Some(seed) = self.rng_seed {
+                session.set_rng_seed(seed);
+            }
+            for (name, (target, method)) in &self.functions {
+                session.register_function(name, godot_function(name, target.clone(), method));
+            }
+            for (name, value) in &self.tokens {
+                session.set_token(name, value);
+            }
+            for (name, expression) in &self.watches {
+                session.add_watch(name, expression).ok();
+            }
+
+            self.session = Some(session);
+            report(
+                owner,
+                connect_unloaded(owner, &path, "_on_database_unloaded"),
+            );
+
+            if !states.is_empty() {
+                let events = self
+                    .session()
+                    .and_then(|session| session.migrate_states(&states));
+                self.emit(owner, events);
+            }
+
+            owner.emit_signal("database_changed", &[path.to_variant()]);
+
+            godot_print!("Loaded Articy Interpreter with \"{path:?}\" as a source!");
+            true
+        } else {
+            false
+        }
+    }
+
+    #[method]
+    fn register_function(
+        &mut self,
+        #[base] owner: &Node,
+        name: String,
+        target: Ref<Object>,
+        method: String,
+    ) -> bool {
+        if !unsafe { target.assume_safe() }.has_method(method.as_str()) {
+            report_error(
+                owner,
+                Error::FunctionNotRegistered(format!("{name} ({method} not found on target)")),
+            );
+
+            return false;
+        }
+
+        if let Some(session) = &mut self.session {
+            session.register_function(&name, godot_function(&name, target.clone(), &method));
+        }
+
+        // NOTE: Kept around so functions survive a `set_database` call
+        self.functions.insert(name, (target, method));
+        true
+    }
+
+    // NOTE: `method` is called as `method(signal, payload)` with the "line" dictionary or "choices" array before they're
+    //       emitted. Returning false drops the signal, a Dictionary or Array takes the place of the payload and
+    //       anything else lets it through as is. Middleware runs in the order it was registered.
+    #[method]
+    fn register_middleware(
+        &mut self,
+        #[base] owner: &Node,
+        target: Ref<Object>,
+        method: String,
+    ) -> bool {
+        if !unsafe { target.assume_safe() }.has_method(method.as_str()) {
+            report_error(
+                owner,
+                Error::InvalidOption(format!("middleware {method} not found on target")),
+            );
+
+            return false;
+        }
+
+        self.middleware.push((target, method));
+        true
+    }
+
+    // NOTE: Models of `type_name` go to `method(model)` instead of the "model" signal. Returning true advances past the
+    //       model right after, anything else leaves the flow waiting on it like the signal would
+    #[method]
+    fn register_model_handler(
+        &mut self,
+        #[base] owner: &Node,
+        type_name: String,
+        target: Ref<Object>,
+        method: String,
+    ) -> bool {
+        if !unsafe { target.assume_safe() }.has_method(method.as_str()) {
+            report_error(
+                owner,
+                Error::InvalidOption(format!("model handler {method} not found on target")),
+            );
+
+            return false;
+        }
+
+        self.model_handlers.insert(type_name, (target, method));
+        true
+    }
+
+    #[method]
+    fn remove_model_handler(&mut self, type_name: String) -> bool {
+        self.model_handlers.remove(&type_name).is_some()
+    }
+
+    #[method]
+    fn remove_middleware(&mut self, target: Ref<Object>, method: String) -> bool {
+        let count = self.middleware.len();
+        self.middleware
+            .retain(|(other, other_method)| !(*other == target && *other_method == method));
+
+        self.middleware.len() != count
+    }
+
+    // NOTE: Tokens go before variables and entity names of the same name
+    #[method]
+    fn set_token(&mut self, name: String, value: String) {
+        if let Some(session) = &mut self.session {
+            session.set_token(&name, &value);
+        }
+
+        self.tokens.insert(name, value);
+    }
+
+    #[method]
+    fn remove_token(&mut self, name: String) -> bool {
+        if let Some(session) = &mut self.session {
+            session.remove_token(&name);
+        }
+
+        self.tokens.remove(&name).is_some()
+    }
+
+    // NOTE: `watch_triggered` is emitted whenever a variable change gives the expression a different result
+    #[method]
+    fn add_watch(&mut self, #[base] owner: &Node, name: String, expression: String) -> bool {
+        let result = match &mut self.session {
+            Some(session) => session.add_watch(&name, &expression),
+            None => script::parse_expression(&expression).map(|_| ()),
+        };
+
+        if report(owner, result).is_none() {
+            return false;
+        }
+
+        // NOTE: Kept around so watches survive a `set_database` call
+        self.watches.retain(|(other, _)| *other != name);
+        self.watches.push((name, expression));
+        true
+    }
+
+    #[method]
+    fn remove_watch(&mut self, name: String) -> bool {
+        if let Some(session) = &mut self.session {
+            session.remove_watch(&name);
+        }
+
+        let count = self.watches.len();
+        self.watches.retain(|(other, _)| *other != name);
+
+        self.watches.len() != count
+    }
+
+    // NOTE: Every random pick (e.g. a "random" hub_strategy) goes through this seed
+    #[method]
+    fn set_rng_seed(&mut self, seed: i64) {
+        if let Some(session) = &mut self.session {
+            session.set_rng_seed(seed as u64);
+        }
+
+        self.rng_seed = Some(seed as u64);
+    }
+
+    #[method]
+    fn get_coverage_report(&mut self, #[base] owner: &Node) -> Option<Dictionary> {
+        report(owner, self.coverage_report())
+            .and_then(|report| json_to_variant(&report).to::<Dictionary>())
+    }
+
+    #[method]
+    fn export_coverage(&mut self, #[base] owner: &Node, path: String) -> bool {
+        let result = self.coverage_report().and_then(|report| {
+            let file = File::new();
+            file.open(&path, File::WRITE).map_err(|error| {
+                Error::InvalidResource(format!("can't write to {path:?}: {error:?}"))
+            })?;
+
+            file.store_string(serde_json::to_string_pretty(&report).unwrap_or_default());
+            file.close();
+
+            Ok(())
+        });
+
+        report(owner, result).is_some()
+    }
+
+    // NOTE: Starts off with a snapshot of the state, so a replay picks up from where the recording did
+    #[method]
+    fn start_recording(&mut self, #[base] owner: &Node) -> bool {
+        let snapshot = match self.save_state(owner) {
+            Some(snapshot) => snapshot,
+            None => return false,
+        };
+
+        match report(owner, variant_to_json(&snapshot.to_variant())) {
+            Some(snapshot) => {
+                self.recording = Some(Recording {
+                    started: Instant::now(),
+                    actions: vec![],
+                });
+                self.record("load_state", vec![snapshot]);
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[method]
+    fn stop_recording(&mut self) -> VariantArray {
+        let recording = self.get_recording();
+        self.recording = None;
+
+        recording
+    }
+
+    #[method]
+    fn get_recording(&self) -> VariantArray {
+        VariantArray::from_iter(
+            self.recording
+                .iter()
+                .flat_map(|recording| recording.actions.iter())
+                .map(json_to_variant),
+        )
+        .into_shared()
+    }
+
+    #[method]
+    fn save_recording(&mut self, #[base] owner: &Node, path: String) -> bool {
+        let actions = match &self.recording {
+            Some(recording) => Ok(Value::Array(recording.actions.to_owned())),
+            None => Err(Error::InvalidOption("nothing is being recorded".to_owned())),
+        };
+
+        let result = actions.and_then(|actions| write_json(&path, &actions));
+
+        report(owner, result).is_some()
+    }
+
+    // NOTE: Takes the actions of a recording, e.g. parsed back from `save_recording`, and runs them straight after
+    //       each other. The times are left for whoever reads the recording; it stops at the first action that fails.
+    //       Replaying would end up in the recording itself, so it's refused while one is going.
+    #[method]
+    fn replay(&mut self, #[base] owner: &Node, actions: VariantArray) -> bool {
+        if self.recording.is_some() {
+            report_error(
+                owner,
+                Error::InvalidOption(
+                    "can't replay while recording, stop the recording first".into(),
+                ),
+            );
+            return false;
+        }
+
+        for action in actions.iter() {
+            let action = action
+                .to::<Dictionary>()
+                .unwrap_or_else(|| Dictionary::new().into_shared());
+            let arguments = action
+                .get("arguments")
+                .and_then(|arguments| arguments.to::<VariantArray>())
+                .map(|arguments| arguments.iter().collect::<Vec<Variant>>())
+                .unwrap_or_default();
+            let argument = |position: usize| arguments.get(position).cloned().unwrap_or_default();
+
+            let replayed = match action.get("action").map(|name| name.to_string()).as_deref() {
+                Some("load_state") => self.load_state(
+                    owner,
+                    argument(0)
+                        .to::<Dictionary>()
+                        .unwrap_or_else(|| Dictionary::new().into_shared()),
+                ),
+                Some("start") => self.start(owner, argument(0).to_string()),
+                Some("advance") => self.advance(owner),
+                Some("choose") => self.choose(owner, argument(0).to_string()),
+                Some("choose_by_index") => {
+                    self.choose_by_index(owner, argument(0).to::<i64>().unwrap_or_default())
+                }
+                Some("set_state") => self.set_state(
+                    owner,
+                    GodotString::from(argument(0).to_string()),
+                    argument(1),
+                ),
+                name => {
+                    report_error(
+                        owner,
+                        Error::InvalidOption(format!(
+                            "can't replay {:?}",
+                            name.unwrap_or_default()
+                        )),
+                    );
+                    false
+                }
+            };
+
+            if !replayed {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // NOTE: Runs on its own copy of the flow and variables, the live dialogue isn't touched
+    #[method]
+    fn soak_test(
+        &mut self,
+        #[base] owner: &Node,
+        dialogue_id: String,
+        iterations: i64,
+        seed: i64,
+    ) -> Option<Dictionary> {
+        let library = report(owner, self.session().map(|session| session.library()))?;
+        let result = soak::soak_test(
+            library,
+            &dialogue_id,
+            iterations.max(0) as usize,
+            seed as u64,
+        );
+
+        json_to_variant(&result).to::<Dictionary>()
+    }
+
+    fn coverage_report(&mut self) -> Result<Value, Error> {
+        self.session()?
+            .coverage_report()
+            .ok_or_else(|| Error::InvalidOption("coverage isn't turned on".to_owned()))
+    }
+
+    #[method]
+    fn set_state(&mut self, #[base] owner: &Node, key: GodotString, value: Variant) -> bool {
+        if let Ok(recorded) = variant_to_json(&value) {
+            self.record("set_state", vec![json!(key.to_string()), recorded]);
+        }
+
+        let events = variant_to_json(&value)
+            .and_then(|value| self.session()?.set_state(&key.to_string(), &value));
+
+        self.emit(owner, events)
+    }
+
+    #[method]
+    fn print_state(&self, #[base] owner: &Node) {
+        if let Some(session) = report(
+            owner,
+            self.session.as_ref().ok_or(Error::InterpreterNotSetup),
+        ) {
+            let state = &session.interpreter().state;
+
+            godot_print!("{state:#?}");
+        }
+    }
+
+    #[method]
+    fn evaluate(&mut self, #[base] owner: &Node, expression: String) -> Variant {
+        let result = self
+            .session()
+            .and_then(|session| session.evaluate(&expression));
+
+        report(owner, result)
+            .map(|value| json_to_variant(&value))
+            .unwrap_or_else(Variant::nil)
+    }
+
+    #[method]
+    fn execute(&mut self, #[base] owner: &Node, script: String) -> bool {
+        let events = self.session().and_then(|session| session.execute(&script));
+
+        self.emit(owner, events)
+    }
+
+    #[method]
+    fn get_state(&mut self, #[base] owner: &Node, key: GodotString) -> Variant {
+        let result = self
+            .session()
+            .and_then(|session| session.get_state(&key.to_string()));
+
+        report(owner, result)
+            .map(|value| json_to_variant(&value))
+            .unwrap_or_else(Variant::nil)
+    }
+
+    #[method]
+    fn get_namespace(&mut self, #[base] owner: &Node, name: String) -> Dictionary {
+        report(
+            owner,
+            self.session().and_then(|session| session.namespace(&name)),
+        )
+        .map(|states| states_to_dictionary(&states))
+        .unwrap_or_else(Dictionary::new)
+        .into_shared()
+    }
+
+    // NOTE: Keys are the variable names without the namespace, e.g. `{"Started": true}` for "Quests"
+    #[method]
+    fn set_namespace(&mut self, #[base] owner: &Node, name: String, states: Dictionary) -> bool {
+        let events = dictionary_to_states(&states)
+            .and_then(|states| self.session()?.set_namespace(&name, &states));
+
+        self.emit(owner, events)
+    }
+
+    #[method]
+    fn list_namespaces(&mut self, #[base] owner: &Node) -> Vec<String> {
+        report(owner, self.session().map(|session| session.namespaces())).unwrap_or_default()
+    }
+
+    #[method]
+    fn get_state_bool(&mut self, #[base] owner: &Node, key: String, #[opt] default: bool) -> bool {
+        self.typed_state(owner, &key, "bool", Value::as_bool)
+            .unwrap_or(default)
+    }
+
+    #[method]
+    fn get_state_int(&mut self, #[base] owner: &Node, key: String, #[opt] default: i64) -> i64 {
+        self.typed_state(owner, &key, "int", Value::as_i64)
+            .unwrap_or(default)
+    }
+
+    #[method]
+    fn get_state_float(&mut self, #[base] owner: &Node, key: String, #[opt] default: f64) -> f64 {
+        self.typed_state(owner, &key, "float", Value::as_f64)
+            .unwrap_or(default)
+    }
+
+    #[method]
+    fn get_state_string(
+        &mut self,
+        #[base] owner: &Node,
+        key: String,
+        #[opt] default: String,
+    ) -> String {
+        self.typed_state(owner, &key, "string", |value| {
+            value.as_str().map(str::to_owned)
+        })
+        .unwrap_or(default)
+    }
+
+    // NOTE: A missing key quietly gives the default, a value of another type warns before doing the same
+    fn typed_state<T>(
+        &mut self,
+        owner: &Node,
+        key: &str,
+        expected: &str,
+        convert: impl Fn(&Value) -> Option<T>,
+    ) -> Option<T> {
+        let session = report(owner, self.session())?;
+        let value = session.get_state(key).ok()?;
+
+        let converted = convert(&value);
+        if converted.is_none() {
+            godot_warn!("State {key:?} holds {value}, which isn't a {expected}");
+        }
+
+        converted
+    }
+
+    #[method]
+    fn get_state_dict(&mut self, #[base] owner: &Node) -> Dictionary {
+        report(owner, self.session().and_then(|session| session.states()))
+            .map(|states| states_to_dictionary(&states))
+            .unwrap_or_else(Dictionary::new)
+            .into_shared()
+    }
+
+    #[method]
+    fn set_state_dict(&mut self, #[base] owner: &Node, states: Dictionary) -> bool {
+        let events =
+            dictionary_to_states(&states).and_then(|states| self.session()?.set_states(&states));
+
+        self.emit(owner, events)
+    }
+
+    #[method]
+    fn start(&mut self, #[base] owner: &Node, id: String) -> bool {
+        self.record("start", vec![json!(id)]);
+        self.step(owner, |session| session.start(&id))
+    }
+
+    // NOTE: For loading a save mid-conversation, after `load_state`. Emits the node again without running its scripts
+    #[method]
+    fn resume_at(&mut self, #[base] owner: &Node, node_id: String) -> bool {
+        self.step(owner, |session| session.resume_at(&node_id))
+    }
+
+    #[method]
+    fn advance(&mut self, #[base] owner: &Node) -> bool {
+        self.record("advance", vec![]);
+        self.step(owner, Session::advance)
+    }
+
+    #[method]
+    fn choose(&mut self, #[base] owner: &Node, id: String) -> bool {
+        self.record("choose", vec![json!(id)]);
+        self.step(owner, |session| session.choose(&id))
+    }
+
+    #[method]
+    fn push_dialogue(&mut self, #[base] owner: &Node, id: String) -> bool {
+        self.step(owner, |session| session.push_dialogue(&id))
+    }
+
+    #[method]
+    fn pop_dialogue(&mut self, #[base] owner: &Node) -> bool {
+        self.step(owner, Session::pop_dialogue)
+    }
+
+    // NOTE: For timed choices running out, takes the choice flagged through `fallback_property`
+    #[method]
+    fn choose_fallback(&mut self, #[base] owner: &Node) -> bool {
+        self.step(owner, Session::choose_fallback)
+    }
+
+    #[method]
+    fn choose_by_index(&mut self, #[base] owner: &Node, index: i64) -> bool {
+        self.record("choose_by_index", vec![json!(index)]);
+        self.step(owner, |session| session.choose_by_index(index))
+    }
+
+    #[method]
+    fn get_available_choices(&self, #[base] owner: &Node) -> VariantArray {
+        let choices = report(
+            owner,
+            self.session.as_ref().ok_or(Error::InterpreterNotSetup),
+        )
+        .map(Session::choices)
+        .unwrap_or_default();
+
+        VariantArray::from_iter(
+            choices
+                .iter()
+                .map(|choice| choice_to_dictionary(choice).owned_to_variant()),
+        )
+        .into_shared()
+    }
+
+    #[method]
+    fn preview_choice(&self, #[base] owner: &Node, id: String, depth: i64) -> VariantArray {
+        let models = self
+            .session
+            .as_ref()
+            .ok_or(Error::InterpreterNotSetup)
+            .and_then(|session| session.preview(&id, depth.max(1) as usize));
+
+        VariantArray::from_iter(
+            report(owner, models)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|model| ArticyModel(model, false).to_variant()),
+        )
+        .into_shared()
+    }
+
+    #[method]
+    fn get_connections(&self, #[base] owner: &Node, id: Variant) -> Variant {
+        let id = if id.is_nil() {
+            None
+        } else {
+            Some(id.to_string())
+        };
+        let result = self
+            .session
+            .as_ref()
+            .ok_or(Error::InterpreterNotSetup)
+            .and_then(|session| session.connections(id.as_deref()));
+
+        match report(owner, result) {
+            Some(models) => VariantArray::from_iter(
+                models
+                    .into_iter()
+                    .map(|model| ArticyModel(model, false).to_variant()),
+            )
+            .owned_to_variant(),
+            None => Variant::nil(),
+        }
+    }
+
+    #[method]
+    fn exhaust_maximally(&mut self, #[base] owner: &Node) -> bool {
+        self.step(owner, Session::exhaust_maximally)
+    }
+
+    #[method]
+    fn stop(&mut self, #[base] owner: &Node) -> bool {
+        self.paused = None;
+        let events = self.session().map(Session::stop);
+
+        self.emit(owner, events)
+    }
+
+    #[method]
+    pub(super) fn set_breakpoint(&mut self, id: String) {
+        self.breakpoints.insert(id);
+    }
+
+    #[method]
+    pub(super) fn clear_breakpoint(&mut self, id: String) {
+        self.breakpoints.remove(&id);
+    }
+
+    #[method]
+    pub(super) fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    // NOTE: Emits what was held back at the breakpoint, up until the next one
+    #[method]
+    pub(super) fn resume(&mut self, #[base] owner: &Node) -> bool {
+        let mut events = match self.paused.take() {
+            Some(events) => events,
+            None => return false,
+        };
+
+        let rest = events.split_off(1);
+        emit_events(owner, Ok(events), &[]);
+        self.release(owner, rest);
+
+        true
+    }
+
+    #[method]
+    fn is_running(&self) -> bool {
+        self.session.as_ref().map_or(false, Session::is_running)
+    }
+
+    #[method]
+    fn get_current_id(&self) -> Option<String> {
+        self.session
+            .as_ref()
+            .and_then(Session::current_id)
+            .map(str::to_owned)
+    }
+
+    #[method]
+    fn get_current_type(&self) -> Option<String> {
+        self.session
+            .as_ref()
+            .and_then(Session::current_kind)
+            .map(str::to_owned)
+    }
+
+    #[method]
+    fn get_current_model(&self) -> Variant {
+        self.session
+            .as_ref()
+            .and_then(Session::current_model)
+            .map_or_else(Variant::nil, |model| ArticyModel(model, false).to_variant())
+    }
+
+    #[method]
+    fn get_history(&self) -> VariantArray {
+        let history = self
+            .session
+            .as_ref()
+            .map(Session::history)
+            .unwrap_or_default();
+
+        VariantArray::from_iter(
+            history
+                .iter()
+                .map(|entry| history_entry_to_dictionary(entry).owned_to_variant()),
+        )
+        .into_shared()
+    }
+
+    #[method]
+    fn clear_history(&mut self) {
+        if let Some(session) = &mut self.session {
+            session.clear_history();
+        }
+    }
+
+    #[method]
+    fn get_visit_count(&self, id: String) -> u64 {
+        self.session
+            .as_ref()
+            .map_or(0, |session| session.visit_count(&id))
+    }
+
+    #[method]
+    fn has_seen(&self, id: String) -> bool {
+        self.session
+            .as_ref()
+            .map_or(false, |session| session.has_seen(&id))
+    }
+
+    #[method]
+    fn save_state(&mut self, #[base] owner: &Node) -> Option<Dictionary> {
+        let saved = report(owner, self.session().and_then(|session| session.save()))?;
+
+        let dictionary = Dictionary::new();
+        dictionary.insert("cursor", saved.cursor);
+
+        let visited = Dictionary::new();
+        for (id, count) in saved.visited {
+            visited.insert(id, count);
+        }
+        dictionary.insert("visited", visited);
+
+        dictionary.insert("state", states_to_dictionary(&saved.state));
+
+        if let Some(rng) = saved.rng {
+            dictionary.insert("rng", rng as i64);
+        }
+
+        let mut grouped = HashMap::<&str, Dictionary<Unique>>::new();
+        for (id, property, value) in &saved.overrides {
+            grouped
+                .entry(id)
+                .or_insert_with(Dictionary::new)
+                .insert(property, property_to_variant(value, property == "Color"));
+        }
+
+        let overrides = Dictionary::new();
+        for (id, properties) in grouped {
+            overrides.insert(id, properties.into_shared());
+        }
+        dictionary.insert("overrides", overrides);
+
+        let references = Dictionary::new();
+        for (id, technical_name, external_id) in saved.references {
+            let reference = Dictionary::new();
+            reference.insert("technical_name", technical_name);
+            reference.insert("external_id", external_id);
+
+            references.insert(id, reference.into_shared());
+        }
+        dictionary.insert("references", references);
+
+        Some(dictionary.into_shared())
+    }
+
+    #[method]
+    fn load_state(&mut self, #[base] owner: &Node, dictionary: Dictionary) -> bool {
+        let saved = saved_state_from_dictionary(&dictionary);
+        let result = saved.and_then(|saved| self.session()?.load(&saved));
+
+        match report(owner, result) {
+            Some(unmapped) => {
+                for (id, kind) in unmapped {
+                    owner.emit_signal("save_entry_unmapped", &[id.to_variant(), kind.to_variant()]);
+                }
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[method]
+    fn _on_database_unloaded(&mut self, #[base] owner: &Node, database: Ref<Node>) {
+        if !is_database(owner, &self.database_path, database) {
+            return;
+        }
+
+        self.paused = None;
+        if let Some(mut session) = self.session.take() {
+            if session.is_running() {
+                emit_events(owner, Ok(session.stop()), &[]);
+            }
+        }
+    }
+
+    // NOTE: Writes what `save_state` gives back as JSON, along with the save format version and a hash of the export
+    #[method]
+    fn save_state_to_file(&mut self, #[base] owner: &Node, path: String) -> bool {
+        let state = match self.save_state(owner) {
+            Some(state) => state,
+            None => return false,
+        };
+
+        let result = variant_to_json(&state.owned_to_variant()).and_then(|state| {
+            let save = json!({
+                "version": SAVE_VERSION,
+                "export_hash": self.session()?.library().export.hash,
+                "state": state,
+            });
+
+            write_json(&path, &save)
+        });
+
+        report(owner, result).is_some()
+    }
+
+    // NOTE: Saves made against another export are refused, unless `ignore_export_mismatch` is set because the
+    //       changes to it are known to be harmless
+    #[method]
+    fn load_state_from_file(
+        &mut self,
+        #[base] owner: &Node,
+        path: String,
+        #[opt] ignore_export_mismatch: bool,
+    ) -> bool {
+        let state = read_json(&path).and_then(|save| {
+            let version = save["version"].as_u64().unwrap_or_default();
+            if version == 0 || version > SAVE_VERSION {
+                return Err(Error::IncompatibleSave(format!(
+                    "{path:?} has save version {version}, up to {SAVE_VERSION} is supported"
+                )));
+            }
+
+            let export_hash = &self.session()?.library().export.hash;
+            if !ignore_export_mismatch && save["export_hash"] != *export_hash {
+                return Err(Error::IncompatibleSave(format!(
+                    "{path:?} was saved against another export"
+                )));
+            }
+
+            json_to_variant(&save["state"])
+                .to::<Dictionary>()
+                .ok_or_else(|| Error::InvalidResource(format!("{path:?} holds no state")))
+        });
+
+        match report(owner, state) {
+            Some(state) => self.load_state(owner, state),
+            None => false,
+        }
+    }
+}
+
+fn write_json(path: &str, value: &Value) -> Result<(), Error> {
+    let file = File::new();
+    file.open(path, File::WRITE)
+        .map_err(|error| Error::InvalidResource(format!("can't write to {path:?}: {error:?}")))?;
+
+    file.store_string(serde_json::to_string_pretty(value).unwrap_or_default());
+    file.close();
+
+    Ok(())
+}
+
+fn read_json(path: &str) -> Result<Value, Error> {
+    let file = File::new();
+    file.open(path, File::READ)
+        .map_err(|error| Error::InvalidResource(format!("can't open {path:?}: {error:?}")))?;
+
+    let bytes = file.get_buffer(file.get_len());
+    file.close();
+
+    serde_json::from_slice(&bytes.to_vec())
+        .map_err(|error| Error::InvalidResource(format!("{path:?} isn't valid JSON: {error}")))
+}
+
+// NOTE: `tag` goes in front of every signal's arguments, e.g. the handle of a managed conversation
+pub(super) fn emit_events(
+    owner: &Node,
+    events: Result<Vec<Event>, Error>,
+    tag: &[Variant],
+) -> bool {
+    let hooks = Hooks {
+        middleware: &[],
+        model_handlers: &HashMap::new(),
+        group_lines: false,
+    };
+
+    emit_filtered(owner, events, tag, &hooks)
+}
+
+fn emit_filtered(
+    owner: &Node,
+    events: Result<Vec<Event>, Error>,
+    tag: &[Variant],
+    hooks: &Hooks,
+) -> bool {
+    let events = match report(owner, events) {
+        Some(events) => events,
+        None => return false,
+    };
+
+    let emit = |signal: &str, arguments: &[Variant]| {
+        let arguments = tag
+            .iter()
+            .chain(arguments)
+            .cloned()
+            .collect::<Vec<Variant>>();
+
+        owner.emit_signal(signal, &arguments)
+    };
+
+    let mut events = events.into_iter().peekable();
+    while let Some(event) = events.next() {
+        match event {
+            Event::Started(id) => emit("started", &[id.to_variant()]),
+            Event::NodeEntered { id, kind } => {
+                emit("node_entered", &[id.to_variant(), kind.to_variant()])
+            }
+            // NOTE: Grouped, every line in a row goes out together, even if it's just the one
+            Event::Line(line) if hooks.group_lines => {
+                let mut lines = vec![line_to_dictionary(&line).owned_to_variant()];
+                while let Some(Event::Line(line)) =
+                    events.next_if(|event| matches!(event, Event::Line(..)))
+                {
+                    lines.push(line_to_dictionary(&line).owned_to_variant());
+                }
+
+                let lines = VariantArray::from_iter(lines).owned_to_variant();
+                match run_middleware(hooks.middleware, "lines", lines) {
+                    Some(lines) => emit("lines", &[lines]),
+                    None => continue,
+                }
+            }
+            Event::Line(line) => {
+                let line = line_to_dictionary(&line).owned_to_variant();
+                match run_middleware(hooks.middleware, "line", line) {
+                    Some(line) => emit("line", &[line]),
+                    None => continue,
+                }
+            }
+            Event::Choices(choices) => {
+                let choices = VariantArray::from_iter(
+                    choices
+                        .iter()
+                        .map(|choice| choice_to_dictionary(choice).owned_to_variant()),
+                )
+                .owned_to_variant();
+
+                match run_middleware(hooks.middleware, "choices", choices) {
+                    Some(choices) => emit("choices", &[choices]),
+                    None => continue,
+                }
+            }
+            Event::Model(model) => {
+                // NOTE: Custom models carry their type in "type" rather than "Type"
+                let handler = model["Type"]
+                    .as_str()
+                    .or_else(|| model["type"].as_str())
+                    .and_then(|kind| hooks.model_handlers.get(kind))
+                    .and_then(|(target, method)| {
+                        Some((unsafe { target.assume_safe_if_sane() }?, method))
+                    });
+
+                match handler {
+                    Some((target, method)) => {
+                        let advance =
+                            unsafe { target.call(method.as_str(), &[json_to_variant(&model)]) };
+
+                        // NOTE: Deferred, the step that got here is still being emitted
+                        if advance.to::<bool>() == Some(true) {
+                            unsafe { owner.call_deferred("advance", &[]) };
+                        }
+                    }
+                    None => emit("model", &[json_to_variant(&model)]),
+                }
+            }
+            Event::ConditionEvaluated {
+                id,
+                expression,
+                result,
+            } => emit(
+                "condition_evaluated",
+                &[
+                    id.to_variant(),
+                    expression.to_variant(),
+                    result.to_variant(),
+                ],
+            ),
+            Event::InstructionExecuted { id, expression } => emit(
+                "instruction_executed",
+                &[id.to_variant(), expression.to_variant()],
+            ),
+            Event::PinConditionEvaluated {
+                id,
+                pin,
+                expression,
+                result,
+            } => emit(
+                "pin_condition_evaluated",
+                &[
+                    id.to_variant(),
+                    pin.to_variant(),
+                    expression.to_variant(),
+                    result.to_variant(),
+                ],
+            ),
+            Event::PinInstructionExecuted {
+                id,
+                pin,
+                expression,
+            } => emit(
+                "pin_instruction_executed",
+                &[id.to_variant(), pin.to_variant(), expression.to_variant()],
+            ),
+            Event::StateChanged { key, old, new } => emit(
+                "state_changed",
+                &[
+                    key.to_variant(),
+                    json_to_variant(&old),
+                    json_to_variant(&new),
+                ],
+            ),
+            Event::HubEntered(id) => emit("hub_entered", &[id.to_variant()]),
+            Event::DialoguePushed(id) => emit("dialogue_pushed", &[id.to_variant()]),
+            Event::DialoguePopped(cursor) => emit("dialogue_popped", &[cursor.to_variant()]),
+            Event::JumpFollowed { from, target } => {
+                emit("jump_followed", &[from.to_variant(), target.to_variant()])
+            }
+            Event::Stopped { id, reason } => {
+                emit("stopped", &[id.to_variant(), reason.as_str().to_variant()])
+            }
+            Event::WatchTriggered { name, value } => emit(
+                "watch_triggered",
+                &[name.to_variant(), json_to_variant(&value)],
+            ),
+            Event::SpeakerChanged { previous, new } => emit(
+                "speaker_changed",
+                &[previous.to_variant(), new.to_variant()],
+            ),
+        };
+    }
+
+    true
+}
+
+// NOTE: What the Interpreter does on top of emitting signals, a ConversationManager goes without
+struct Hooks<'a> {
+    middleware: &'a [(Ref<Object>, String)],
+    model_handlers: &'a HashMap<String, (Ref<Object>, String)>,
+    group_lines: bool,
+}
+
+fn run_middleware(
+    middleware: &[(Ref<Object>, String)],
+    signal: &str,
+    mut payload: Variant,
+) -> Option<Variant> {
+    for (target, method) in middleware {
+        // NOTE: Middleware on a freed object is skipped rather than holding up every line
+        let target = match unsafe { target.assume_safe_if_sane() } {
+            Some(target) => target,
+            None => continue,
+        };
+
+        let result =
+            unsafe { target.call(method.as_str(), &[signal.to_variant(), payload.clone()]) };
+        match result.dispatch() {
+            VariantDispatch::Bool(false) => return None,
+            VariantDispatch::Dictionary(..) | VariantDispatch::VariantArray(..) => payload = result,
+            _ => {}
+        }
+    }
+
+    Some(payload)
+}
+
+// NOTE: The target gets called while the Interpreter is busy, so it can't call back into the Interpreter
+fn godot_function(name: &str, target: Ref<Object>, method: &str) -> Function {
+    let name = name.to_owned();
+    let method = method.to_owned();
+
+    Box::new(move |arguments| {
+        let target = unsafe { target.assume_safe_if_sane() }
+            .ok_or_else(|| Error::FunctionNotRegistered(format!("{name} (target was freed)")))?;
+
+        let arguments = arguments
+            .iter()
+            .map(json_to_variant)
+            .collect::<Vec<Variant>>();
+        let result = unsafe { target.call(method.as_str(), &arguments) };
+
+        variant_to_json(&result)
+    })
+}
+
+fn saved_state_from_dictionary(dictionary: &Dictionary) -> Result<SavedState, Error> {
+    let saved = SavedState {
+        cursor: dictionary
+            .get("cursor")
+            .filter(|cursor| !cursor.is_nil())
+            .map(|cursor| cursor.to_string()),
+        visited: match dictionary.get("visited").map(|visited| visited.dispatch()) {
+            Some(VariantDispatch::Dictionary(visited)) => visited
+                .iter()
+                .map(|(id, count)| (id.to_string(), count.to::<u64>().unwrap_or(1)))
+                .collect(),
+            // NOTE: Saves from before visit counts stored a plain list of seen ids
+            Some(VariantDispatch::VariantArray(visited)) => {
+                visited.iter().map(|id| (id.to_string(), 1)).collect()
+            }
+            _ => vec![],
+        },
+        state: match dictionary
+            .get("state")
+            .and_then(|state| state.to::<Dictionary>())
+        {
+            Some(state) => dictionary_to_states(&state)?,
+            None => vec![],
+        },
+        rng: dictionary
+            .get("rng")
+            .and_then(|rng| rng.to::<i64>())
+            .map(|rng| rng as u64),
+        overrides: match dictionary
+            .get("overrides")
+            .and_then(|overrides| overrides.to::<Dictionary>())
+        {
+            Some(overrides) => overrides
+                .iter()
+                .filter_map(|(id, properties)| Some((id, properties.to::<Dictionary>()?)))
+                .flat_map(|(id, properties)| {
+                    properties
+                        .iter()
+                        .map(|(property, value)| {
+                            Ok((
+                                id.to_string(),
+                                property.to_string(),
+                                variant_to_json(&value)?,
+                            ))
+                        })
+                        .collect::<Vec<Result<(String, String, Value), Error>>>()
+                })
+                .collect::<Result<Vec<(String, String, Value)>, Error>>()?,
+            None => vec![],
+        },
+        references: match dictionary
+            .get("references")
+            .and_then(|references| references.to::<Dictionary>())
+        {
+            Some(references) => references
+                .iter()
+                .filter_map(|(id, reference)| {
+                    let reference = reference.to::<Dictionary>()?;
+                    let field = |name: &str| {
+                        reference
+                            .get(name)
+                            .map(|value| value.to_string())
+                            .unwrap_or_default()
+                    };
+
+                    Some((
+                        id.to_string(),
+                        field("technical_name"),
+                        field("external_id"),
+                    ))
+                })
+                .collect(),
+            None => vec![],
+        },
+    };
+
+    Ok(saved)
+}
+
+pub(super) fn get_database(owner: &Node, path: &NodePath) -> Result<SharedLibrary, Error> {
+    with_database(owner, path, Library::shared)
+}
+
+fn string_vec(strings: &PoolArray<GodotString>) -> Vec<String> {
+    strings.read().iter().map(GodotString::to_string).collect()
+}
+
+fn iterations(max_iterations: i64) -> usize {
+    match usize::try_from(max_iterations) {
+        Ok(0) | Err(_) => DEFAULT_MAX_ITERATIONS,
+        Ok(max_iterations) => max_iterations,
+    }
+}
+
+// NOTE: Picked from the inspector it's "Name (id)", typed in by hand it can be just the id
+fn start_node_id(start_node: &str) -> &str {
+    start_node
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+        .map_or(start_node, |(_, id)| id)
+}
+
+fn property_name<'a>(property: &'a str, default: &'a str) -> &'a str {
+    if property.is_empty() {
+        default
+    } else {
+        property
+    }
+}
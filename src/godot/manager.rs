@@ -0,0 +1,456 @@
+use super::{
+    caught,
+    database::{connect_unloaded, is_database},
+    interpreter::{emit_events, get_database},
+    report, report_error,
+};
+use crate::{
+    error::Error,
+    library::SharedLibrary,
+    script,
+    session::{Event, Session, StopReason},
+};
+use gdnative::prelude::*;
+use serde_json::Value;
+
+// NOTE: Every signal of the Interpreter, re-emitted here with the conversation handle as the first argument
+#[derive(NativeClass)]
+#[inherit(Node)]
+#[register_with(Self::register_signals)]
+pub struct ConversationManager {
+    #[property]
+    database_path: Option<NodePath>,
+    // NOTE: How many conversations can run side by side, e.g. a main conversation and a bark
+    #[property]
+    max_concurrent: i64,
+    library: Option<SharedLibrary>,
+    active: Vec<Conversation>,
+    queued: Vec<Queued>,
+    state: Vec<(String, Value)>,
+    watches: Vec<(String, String)>,
+    next_handle: i64,
+}
+
+struct Conversation {
+    handle: i64,
+    priority: i64,
+    session: Session,
+}
+
+struct Queued {
+    handle: i64,
+    priority: i64,
+    id: String,
+}
+
+#[methods]
+impl ConversationManager {
+    fn new(_base: &Node) -> Self {
+        ConversationManager {
+            database_path: None,
+            max_concurrent: 1,
+            library: None,
+            active: vec![],
+            queued: vec![],
+            state: vec![],
+            watches: vec![],
+            next_handle: 1,
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        let handle = "handle";
+
+        for signal in ["queued", "interrupted"] {
+            builder
+                .signal(signal)
+                .with_param(handle, VariantType::I64)
+                .done();
+        }
+
+        builder
+            .signal("started")
+            .with_param(handle, VariantType::I64)
+            .with_param("id", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("node_entered")
+            .with_param(handle, VariantType::I64)
+            .with_param("id", VariantType::GodotString)
+            .with_param("type", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("line")
+            .with_param(handle, VariantType::I64)
+            .with_param("line", VariantType::Dictionary)
+            .done();
+
+        builder
+            .signal("choices")
+            .with_param(handle, VariantType::I64)
+            .with_param("choices", VariantType::VariantArray)
+            .done();
+
+        builder
+            .signal("model")
+            .with_param(handle, VariantType::I64)
+            .with_param("model", VariantType::Dictionary)
+            .done();
+
+        builder
+            .signal("condition_evaluated")
+            .with_param(handle, VariantType::I64)
+            .with_param("id", VariantType::GodotString)
+            .with_param("expression", VariantType::GodotString)
+            .with_param("result", VariantType::Bool)
+            .done();
+
+        builder
+            .signal("state_changed")
+            .with_param(handle, VariantType::I64)
+            .with_param("key", VariantType::GodotString)
+            .with_param("old_value", VariantType::Nil)
+            .with_param("new_value", VariantType::Nil)
+            .done();
+
+        builder
+            .signal("hub_entered")
+            .with_param(handle, VariantType::I64)
+            .with_param("id", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("jump_followed")
+            .with_param(handle, VariantType::I64)
+            .with_param("from_id", VariantType::GodotString)
+            .with_param("target_id", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("instruction_executed")
+            .with_param(handle, VariantType::I64)
+            .with_param("id", VariantType::GodotString)
+            .with_param("expression", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("pin_condition_evaluated")
+            .with_param(handle, VariantType::I64)
+            .with_param("id", VariantType::GodotString)
+            .with_param("pin_id", VariantType::GodotString)
+            .with_param("expression", VariantType::GodotString)
+            .with_param("result", VariantType::Bool)
+            .done();
+
+        builder
+            .signal("pin_instruction_executed")
+            .with_param(handle, VariantType::I64)
+            .with_param("id", VariantType::GodotString)
+            .with_param("pin_id", VariantType::GodotString)
+            .with_param("expression", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("stopped")
+            .with_param(handle, VariantType::I64)
+            .with_param("id", VariantType::GodotString)
+            .with_param("reason", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("speaker_changed")
+            .with_param(handle, VariantType::I64)
+            .with_param("previous_id", VariantType::GodotString)
+            .with_param("new_id", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("watch_triggered")
+            .with_param(handle, VariantType::I64)
+            .with_param("name", VariantType::GodotString)
+            .with_param("value", VariantType::Nil)
+            .done();
+
+        builder
+            .signal("error")
+            .with_param("code", VariantType::I64)
+            .with_param("message", VariantType::GodotString)
+            .done();
+    }
+
+    #[method]
+    fn _ready(&mut self, #[base] owner: &Node) {
+        if let Some(path) = &self.database_path {
+            self.set_database(owner, path.new_ref());
+        }
+    }
+
+    #[method]
+    fn set_database(&mut self, #[base] owner: &Node, path: NodePath) -> bool {
+        self.library = report(owner, get_database(owner, &path));
+        if self.library.is_none() {
+            return false;
+        }
+
+        report(
+            owner,
+            connect_unloaded(owner, &path, "_on_database_unloaded"),
+        );
+        self.database_path = Some(path);
+        true
+    }
+
+    #[method]
+    fn _on_database_unloaded(&mut self, #[base] owner: &Node, database: Ref<Node>) {
+        if is_database(owner, &self.database_path, database) {
+            self.stop_all(owner);
+            self.library = None;
+        }
+    }
+
+    // NOTE: Returns the handle of the conversation, or -1 when it couldn't be queued
+    #[method]
+    fn queue_dialogue(&mut self, #[base] owner: &Node, id: String, #[opt] priority: i64) -> i64 {
+        if self.library.is_none() {
+            report_error(owner, Error::DatabaseNotSetup);
+            return -1;
+        }
+
+        let handle = self.next_handle;
+        self.next_handle += 1;
+
+        let queued = Queued {
+            handle,
+            priority,
+            id,
+        };
+
+        if self.active.len() >= self.max_concurrent.max(1) as usize {
+            let lowest = self
+                .active
+                .iter()
+                .enumerate()
+                .filter(|(_, conversation)| conversation.priority < priority)
+                .min_by_key(|(_, conversation)| conversation.priority)
+                .map(|(position, _)| position);
+
+            match lowest {
+                Some(position) => {
+                    let mut interrupted = self.active.remove(position);
+                    interrupted.session.stop();
+
+                    owner.emit_signal("interrupted", &[interrupted.handle.to_variant()]);
+                }
+                None => {
+                    // NOTE: Same priorities queue up in the order they came in
+                    let position = self
+                        .queued
+                        .iter()
+                        .position(|other| other.priority < priority)
+                        .unwrap_or(self.queued.len());
+                    self.queued.insert(position, queued);
+
+                    owner.emit_signal("queued", &[handle.to_variant()]);
+                    return handle;
+                }
+            }
+        }
+
+        self.start(owner, queued);
+        handle
+    }
+
+    #[method]
+    fn advance(&mut self, #[base] owner: &Node, handle: i64) -> bool {
+        self.step(owner, handle, |session| session.advance())
+    }
+
+    #[method]
+    fn choose(&mut self, #[base] owner: &Node, handle: i64, id: String) -> bool {
+        self.step(owner, handle, |session| session.choose(&id))
+    }
+
+    #[method]
+    fn choose_by_index(&mut self, #[base] owner: &Node, handle: i64, index: i64) -> bool {
+        self.step(owner, handle, |session| session.choose_by_index(index))
+    }
+
+    #[method]
+    fn choose_fallback(&mut self, #[base] owner: &Node, handle: i64) -> bool {
+        self.step(owner, handle, Session::choose_fallback)
+    }
+
+    // NOTE: Also takes conversations out of the queue before they get to start
+    #[method]
+    fn stop(&mut self, #[base] owner: &Node, handle: i64) -> bool {
+        if let Some(position) = self
+            .queued
+            .iter()
+            .position(|queued| queued.handle == handle)
+        {
+            let queued = self.queued.remove(position);
+            owner.emit_signal(
+                "stopped",
+                &[
+                    handle.to_variant(),
+                    queued.id.to_variant(),
+                    StopReason::Stopped.as_str().to_variant(),
+                ],
+            );
+
+            return true;
+        }
+
+        self.step(owner, handle, |session| Ok(session.stop()))
+    }
+
+    #[method]
+    fn stop_all(&mut self, #[base] owner: &Node) {
+        self.queued.clear();
+
+        for mut conversation in std::mem::take(&mut self.active) {
+            let events = conversation.session.stop();
+            emit_events(owner, Ok(events), &[conversation.handle.to_variant()]);
+        }
+    }
+
+    // NOTE: Watches every conversation, including the ones that haven't started yet
+    #[method]
+    fn add_watch(&mut self, #[base] owner: &Node, name: String, expression: String) -> bool {
+        if report(owner, script::parse_expression(&expression)).is_none() {
+            return false;
+        }
+
+        for conversation in &mut self.active {
+            conversation.session.add_watch(&name, &expression).ok();
+        }
+
+        self.watches.retain(|(other, _)| *other != name);
+        self.watches.push((name, expression));
+        true
+    }
+
+    #[method]
+    fn remove_watch(&mut self, name: String) -> bool {
+        for conversation in &mut self.active {
+            conversation.session.remove_watch(&name);
+        }
+
+        let count = self.watches.len();
+        self.watches.retain(|(other, _)| *other != name);
+
+        self.watches.len() != count
+    }
+
+    #[method]
+    fn get_active_conversations(&self) -> Vec<i64> {
+        self.active
+            .iter()
+            .map(|conversation| conversation.handle)
+            .collect()
+    }
+
+    #[method]
+    fn get_queued_conversations(&self) -> Vec<i64> {
+        self.queued.iter().map(|queued| queued.handle).collect()
+    }
+
+    #[method]
+    fn is_active(&self, handle: i64) -> bool {
+        self.active
+            .iter()
+            .any(|conversation| conversation.handle == handle)
+    }
+
+    fn start(&mut self, owner: &Node, queued: Queued) {
+        let library = match &self.library {
+            Some(library) => library.clone(),
+            None => return,
+        };
+
+        // NOTE: Every conversation picks up the variables where the others left them
+        let mut session = Session::new(library);
+        if let Err(error) = session.set_states(&self.state) {
+            report_error(owner, error);
+        }
+        for (name, expression) in &self.watches {
+            session.add_watch(name, expression).ok();
+        }
+
+        let events = caught(|| session.start(&queued.id));
+
+        self.active.push(Conversation {
+            handle: queued.handle,
+            priority: queued.priority,
+            session,
+        });
+
+        self.finish(owner, queued.handle, events);
+    }
+
+    fn step(
+        &mut self,
+        owner: &Node,
+        handle: i64,
+        step: impl FnOnce(&mut Session) -> Result<Vec<Event>, Error>,
+    ) -> bool {
+        let conversation = self
+            .active
+            .iter_mut()
+            .find(|conversation| conversation.handle == handle)
+            .ok_or(Error::ConversationNotFound(handle));
+
+        match report(owner, conversation) {
+            Some(conversation) => {
+                let events = caught(|| step(&mut conversation.session));
+                self.finish(owner, handle, events)
+            }
+            None => false,
+        }
+    }
+
+    fn finish(&mut self, owner: &Node, handle: i64, events: Result<Vec<Event>, Error>) -> bool {
+        let failed = matches!(events, Err(Error::ArticyError(..) | Error::Panicked(..)));
+        let emitted = emit_events(owner, events, &[handle.to_variant()]);
+
+        if let Some(position) = self
+            .active
+            .iter()
+            .position(|conversation| conversation.handle == handle)
+        {
+            let session = &mut self.active[position].session;
+            if failed && session.is_running() {
+                emit_events(owner, Ok(session.abort()), &[handle.to_variant()]);
+            }
+
+            self.share_state(position);
+
+            if !self.active[position].session.is_running() {
+                self.active.remove(position);
+            }
+        }
+
+        while self.active.len() < self.max_concurrent.max(1) as usize && !self.queued.is_empty() {
+            let queued = self.queued.remove(0);
+            self.start(owner, queued);
+        }
+
+        emitted
+    }
+
+    // NOTE: Sessions each hold their own variables, the one that just moved is copied over to the rest
+    fn share_state(&mut self, position: usize) {
+        self.state = match self.active[position].session.states() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+
+        for (other, conversation) in self.active.iter_mut().enumerate() {
+            if other != position {
+                conversation.session.set_states(&self.state).ok();
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+mod bark;
+mod convert;
+mod database;
+#[cfg(feature = "debug-server")]
+mod debugger;
+mod dialogue;
+mod dock;
+mod entity;
+mod import;
+mod interpreter;
+mod manager;
+mod quest;
+mod serializer;
+
+use crate::error::Error;
+use gdnative::prelude::*;
+
+pub use bark::BarkManager;
+pub use database::Database;
+#[cfg(feature = "debug-server")]
+pub use debugger::NarrativeDebugger;
+pub use dialogue::ArticyDialogue;
+pub use dock::ArticyDock;
+pub use entity::ArticyEntity;
+pub use import::{ArticyEditorPlugin, ArticyImportPlugin};
+pub use interpreter::Interpreter;
+pub use manager::ConversationManager;
+pub use quest::QuestTracker;
+
+fn report_error(owner: &Node, error: Error) {
+    godot_error!("{error}");
+    owner.emit_signal(
+        "error",
+        &[Variant::new(error.code()), Variant::new(error.to_string())],
+    );
+}
+
+fn report<T>(owner: &Node, result: Result<T, Error>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(error) => {
+            report_error(owner, error);
+            None
+        }
+    }
+}
+
+// NOTE: Turns a panic in `body` into an error, so it reaches the `error` signal instead of only failing the call.
+//       The panic hook has printed it with its backtrace by then.
+fn caught<T>(body: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_owned());
+
+        Err(Error::Panicked(message))
+    })
+}
+
+fn init(handle: InitHandle) {
+    // NOTE: Without this a panic only shows up in the terminal Godot was started from, if at all
+    std::panic::set_hook(Box::new(|info| {
+        let backtrace = std::backtrace::Backtrace::force_capture();
+        godot_error!("godot-articy {info}\n{backtrace}");
+    }));
+
+    handle.add_tool_class::<Database>();
+    // NOTE: A tool class so the inspector can list dialogues for `start_node`, it doesn't do anything else in the editor
+    handle.add_tool_class::<Interpreter>();
+    handle.add_class::<ConversationManager>();
+    handle.add_class::<QuestTracker>();
+    handle.add_class::<BarkManager>();
+    handle.add_class::<ArticyEntity>();
+    handle.add_class::<ArticyDialogue>();
+    #[cfg(feature = "debug-server")]
+    handle.add_class::<NarrativeDebugger>();
+    handle.add_tool_class::<ArticyImportPlugin>();
+    handle.add_tool_class::<ArticyEditorPlugin>();
+    handle.add_tool_class::<ArticyDock>();
+}
+
+godot_init!(init);
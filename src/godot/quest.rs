@@ -0,0 +1,152 @@
+use super::{
+    convert::quest_to_dictionary, database::with_database, manager::ConversationManager, report,
+    report_error,
+};
+use crate::{error::Error, quest::QuestLog};
+use gdnative::prelude::*;
+
+#[derive(NativeClass)]
+#[inherit(Node)]
+#[register_with(Self::register_signals)]
+pub struct QuestTracker {
+    #[property]
+    database_path: Option<NodePath>,
+    // NOTE: Either an Interpreter or a ConversationManager
+    #[property]
+    interpreter_path: Option<NodePath>,
+    // NOTE: The exported type of the quest fragments, or a feature of their template
+    #[property]
+    template: String,
+    log: QuestLog,
+}
+
+#[methods]
+impl QuestTracker {
+    fn new(_base: &Node) -> Self {
+        QuestTracker {
+            database_path: None,
+            interpreter_path: None,
+            template: "Quest".to_owned(),
+            log: QuestLog::default(),
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder
+            .signal("quest_updated")
+            .with_param("id", VariantType::GodotString)
+            .with_param("state", VariantType::GodotString)
+            .with_param("stage", VariantType::GodotString)
+            .done();
+
+        builder
+            .signal("error")
+            .with_param("code", VariantType::I64)
+            .with_param("message", VariantType::GodotString)
+            .done();
+    }
+
+    #[method]
+    fn _ready(&mut self, #[base] owner: &Node) {
+        if self.database_path.is_some() {
+            self.scan(owner);
+        }
+
+        if let Some(path) = &self.interpreter_path {
+            match owner.get_node(path.to_godot_string()) {
+                Some(source) => {
+                    self.watch(owner, source);
+                }
+                None => report_error(owner, Error::NodeNotFound(path.to_string())),
+            }
+        }
+    }
+
+    // NOTE: Scanning again forgets the progress made so far
+    #[method]
+    fn scan(&mut self, #[base] owner: &Node) -> bool {
+        let log = match &self.database_path {
+            Some(path) => with_database(owner, path, |library| {
+                QuestLog::scan(library, &self.template)
+            }),
+            None => Err(Error::DatabaseNotSetup),
+        };
+
+        match report(owner, log) {
+            Some(log) => {
+                self.log = log;
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[method]
+    fn watch(&mut self, #[base] owner: &Node, source: Ref<Node>) -> bool {
+        let source = unsafe { source.assume_safe() };
+        let method = match source.cast_instance::<ConversationManager>() {
+            Some(_) => "_on_conversation_node_entered",
+            None => "_on_node_entered",
+        };
+
+        let result = source.connect(
+            "node_entered",
+            unsafe { owner.assume_shared() },
+            method,
+            VariantArray::new_shared(),
+            0,
+        );
+
+        report(
+            owner,
+            result.map_err(|error| Error::InvalidOption(format!("can't watch node: {error:?}"))),
+        )
+        .is_some()
+    }
+
+    #[method]
+    fn get_quests(&self) -> VariantArray {
+        VariantArray::from_iter(
+            self.log
+                .quests()
+                .iter()
+                .map(|quest| quest_to_dictionary(quest).owned_to_variant()),
+        )
+        .into_shared()
+    }
+
+    #[method]
+    fn get_quest_state(&self, #[base] owner: &Node, id: String) -> Option<Dictionary> {
+        let quest = self
+            .log
+            .quest(&id)
+            .ok_or_else(|| Error::ModelNotFound(id.to_owned()));
+
+        report(owner, quest).map(|quest| quest_to_dictionary(quest).into_shared())
+    }
+
+    #[method]
+    fn _on_node_entered(&mut self, #[base] owner: &Node, id: String, _kind: String) {
+        if let Some(quest) = self.log.pass(&id) {
+            owner.emit_signal(
+                "quest_updated",
+                &[
+                    quest.id.to_variant(),
+                    quest.state.as_str().to_variant(),
+                    quest.stage.to_variant(),
+                ],
+            );
+        }
+    }
+
+    #[method]
+    fn _on_conversation_node_entered(
+        &mut self,
+        #[base] owner: &Node,
+        _handle: i64,
+        id: String,
+        kind: String,
+    ) {
+        self._on_node_entered(owner, id, kind);
+    }
+}
@@ -0,0 +1,355 @@
+use crate::error::Error;
+use gdnative::prelude::*;
+use serde::ser::{self, Serialize};
+use std::fmt;
+
+// NOTE: Builds Variants straight from serde, following serde_json's layout for enums and options
+
+pub fn to_variant<T: Serialize + ?Sized>(value: &T) -> Result<Variant, Error> {
+    value
+        .serialize(VariantSerializer)
+        .map_err(|SerializeError(reason)| Error::UnsupportedType(reason))
+}
+
+#[derive(Debug)]
+pub struct SerializeError(String);
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.0)
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+impl ser::Error for SerializeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        SerializeError(message.to_string())
+    }
+}
+
+pub struct VariantSerializer;
+
+pub struct SerializeArray(VariantArray<Unique>);
+
+pub struct SerializeDictionary {
+    dictionary: Dictionary<Unique>,
+    key: Option<Variant>,
+}
+
+pub struct SerializeEnumVariant<T> {
+    name: &'static str,
+    inner: T,
+}
+
+impl ser::Serializer for VariantSerializer {
+    type Ok = Variant;
+    type Error = SerializeError;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeEnumVariant<SerializeArray>;
+    type SerializeMap = SerializeDictionary;
+    type SerializeStruct = SerializeDictionary;
+    type SerializeStructVariant = SerializeEnumVariant<SerializeDictionary>;
+
+    fn serialize_bool(self, value: bool) -> Result<Variant, SerializeError> {
+        Ok(value.to_variant())
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<Variant, SerializeError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<Variant, SerializeError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<Variant, SerializeError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<Variant, SerializeError> {
+        Ok(value.to_variant())
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<Variant, SerializeError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<Variant, SerializeError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<Variant, SerializeError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<Variant, SerializeError> {
+        // NOTE: Godot only has signed integers, so anything bigger falls back to a float like JSON would
+        match i64::try_from(value) {
+            Ok(value) => self.serialize_i64(value),
+            Err(_) => self.serialize_f64(value as f64),
+        }
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<Variant, SerializeError> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<Variant, SerializeError> {
+        Ok(value.to_variant())
+    }
+
+    fn serialize_char(self, value: char) -> Result<Variant, SerializeError> {
+        Ok(value.to_string().to_variant())
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Variant, SerializeError> {
+        Ok(value.to_variant())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Variant, SerializeError> {
+        Ok(PoolArray::<u8>::from_slice(value).to_variant())
+    }
+
+    fn serialize_none(self) -> Result<Variant, SerializeError> {
+        Ok(Variant::nil())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Variant, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Variant, SerializeError> {
+        Ok(Variant::nil())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Variant, SerializeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Variant, SerializeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Variant, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Variant, SerializeError> {
+        let dictionary = Dictionary::new();
+        dictionary.insert(variant, value.serialize(self)?);
+
+        Ok(dictionary.owned_to_variant())
+    }
+
+    fn serialize_seq(self, _length: Option<usize>) -> Result<SerializeArray, SerializeError> {
+        Ok(SerializeArray(VariantArray::new()))
+    }
+
+    fn serialize_tuple(self, length: usize) -> Result<SerializeArray, SerializeError> {
+        self.serialize_seq(Some(length))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        length: usize,
+    ) -> Result<SerializeArray, SerializeError> {
+        self.serialize_seq(Some(length))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        length: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        Ok(SerializeEnumVariant {
+            name: variant,
+            inner: self.serialize_seq(Some(length))?,
+        })
+    }
+
+    fn serialize_map(self, _length: Option<usize>) -> Result<SerializeDictionary, SerializeError> {
+        Ok(SerializeDictionary {
+            dictionary: Dictionary::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        length: usize,
+    ) -> Result<SerializeDictionary, SerializeError> {
+        self.serialize_map(Some(length))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        length: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        Ok(SerializeEnumVariant {
+            name: variant,
+            inner: self.serialize_map(Some(length))?,
+        })
+    }
+}
+
+impl SerializeArray {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.0.push(value.serialize(VariantSerializer)?);
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Variant;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Variant, SerializeError> {
+        Ok(self.0.owned_to_variant())
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Variant;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Variant, SerializeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Variant;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Variant, SerializeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeEnumVariant<SerializeArray> {
+    type Ok = Variant;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.inner.push(value)
+    }
+
+    fn end(self) -> Result<Variant, SerializeError> {
+        let dictionary = Dictionary::new();
+        dictionary.insert(self.name, ser::SerializeSeq::end(self.inner)?);
+
+        Ok(dictionary.owned_to_variant())
+    }
+}
+
+impl ser::SerializeMap for SerializeDictionary {
+    type Ok = Variant;
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        self.key = Some(key.serialize(VariantSerializer)?);
+
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| SerializeError("map value serialized before its key".to_owned()))?;
+
+        self.dictionary
+            .insert(key, value.serialize(VariantSerializer)?);
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Variant, SerializeError> {
+        Ok(self.dictionary.owned_to_variant())
+    }
+}
+
+impl ser::SerializeStruct for SerializeDictionary {
+    type Ok = Variant;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.dictionary
+            .insert(key, value.serialize(VariantSerializer)?);
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Variant, SerializeError> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeEnumVariant<SerializeDictionary> {
+    type Ok = Variant;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Variant, SerializeError> {
+        let dictionary = Dictionary::new();
+        dictionary.insert(self.name, ser::SerializeMap::end(self.inner)?);
+
+        Ok(dictionary.owned_to_variant())
+    }
+}
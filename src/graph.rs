@@ -0,0 +1,262 @@
+use crate::{
+    error::Error,
+    library::Library,
+    model::{self, model_json, Connection},
+};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// NOTE: Boolean property on a node, or one of its template's features, marking a node without exits as an ending
+pub const ENDING_PROPERTY: &str = "IsEnding";
+
+// NOTE: Shaped after Godot's GraphEdit, ports are the positions of the pins on either side of a node
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowGraph {
+    pub nodes: Vec<FlowNode>,
+    pub edges: Vec<FlowEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowNode {
+    pub id: String,
+    pub kind: String,
+    pub label: String,
+    pub position: [f32; 2],
+    pub size: Option<[f32; 2]>,
+    pub inputs: usize,
+    pub outputs: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowEdge {
+    pub from: String,
+    pub from_port: usize,
+    pub to: String,
+    pub to_port: usize,
+    pub label: String,
+    pub condition: String,
+    pub instruction: String,
+}
+
+// NOTE: The nodes directly inside the container, connections leaving it end on the container itself
+pub fn flow_graph(library: &Library, id: &str) -> Result<FlowGraph, Error> {
+    library.find_model(id)?;
+
+    let mut nodes = vec![];
+    let mut edges = vec![];
+
+    for child in library.children(id)? {
+        let json = model_json(library.find_model(child)?);
+        let properties = &json["Properties"];
+
+        nodes.push(FlowNode {
+            id: child.to_owned(),
+            kind: json["Type"].as_str().unwrap_or_default().to_owned(),
+            label: ["DisplayName", "MenuText", "Text"]
+                .into_iter()
+                .find_map(|property| {
+                    properties[property]
+                        .as_str()
+                        .filter(|text| !text.is_empty())
+                })
+                .unwrap_or_default()
+                .to_owned(),
+            position: [
+                properties["Position"]["x"].as_f64().unwrap_or_default() as f32,
+                properties["Position"]["y"].as_f64().unwrap_or_default() as f32,
+            ],
+            size: properties["Size"]["w"]
+                .as_f64()
+                .zip(properties["Size"]["h"].as_f64())
+                .map(|(width, height)| [width as f32, height as f32]),
+            inputs: pins(properties, "InputPins").len(),
+            outputs: pins(properties, "OutputPins").len(),
+        });
+
+        for connection in library.outgoing_connections(child)? {
+            edges.push(edge(library, connection, &json)?);
+        }
+    }
+
+    Ok(FlowGraph { nodes, edges })
+}
+
+fn edge(library: &Library, connection: &Connection, source: &Value) -> Result<FlowEdge, Error> {
+    let target = model_json(library.find_model(&connection.target)?);
+    let (from_port, output) = pin(&source["Properties"], "OutputPins", &connection.source_pin);
+    let (to_port, input) = pin(&target["Properties"], "InputPins", &connection.target_pin);
+
+    Ok(FlowEdge {
+        from: connection.source.to_owned(),
+        from_port,
+        to: connection.target.to_owned(),
+        to_port,
+        label: connection.label.to_owned(),
+        condition: input["Text"].as_str().unwrap_or_default().to_owned(),
+        instruction: output["Text"].as_str().unwrap_or_default().to_owned(),
+    })
+}
+
+fn pins<'a>(properties: &'a Value, side: &str) -> &'a [Value] {
+    properties[side].as_array().map_or(&[], Vec::as_slice)
+}
+
+fn pin<'a>(properties: &'a Value, side: &str, id: &str) -> (usize, &'a Value) {
+    pins(properties, side)
+        .iter()
+        .enumerate()
+        .find(|(_, pin)| pin["Id"].as_str() == Some(id))
+        .unwrap_or((0, &Value::Null))
+}
+
+// NOTE: Breadth first over the connections, so the path found is one of the shortest. Gives back the ids from `from`
+//       to `to`, or nothing when `to` can't be reached within `max_depth` connections.
+pub fn find_path(
+    library: &Library,
+    from: &str,
+    to: &str,
+    max_depth: Option<usize>,
+) -> Result<Vec<String>, Error> {
+    library.find_model(from)?;
+    library.find_model(to)?;
+
+    if from == to {
+        return Ok(vec![from.to_owned()]);
+    }
+
+    let mut previous = HashMap::<Step, Option<Step>>::new();
+    let mut pending = VecDeque::new();
+
+    for next in first_steps(library, from)? {
+        if !previous.contains_key(&next) {
+            previous.insert(next.clone(), None);
+            pending.push_back((next, 1));
+        }
+    }
+
+    while let Some((step, depth)) = pending.pop_front() {
+        if step.0 == to {
+            let mut path = vec![];
+            let mut current = Some(step);
+
+            while let Some(step) = current {
+                current = previous[&step].clone();
+                path.push(step.0);
+            }
+
+            path.push(from.to_owned());
+            path.reverse();
+            return Ok(path);
+        }
+
+        if max_depth.map_or(false, |max_depth| depth >= max_depth) {
+            continue;
+        }
+
+        for next in next_steps(library, &step)? {
+            if !previous.contains_key(&next) {
+                previous.insert(next.clone(), Some(step.clone()));
+                pending.push_back((next, depth + 1));
+            }
+        }
+    }
+
+    Ok(vec![])
+}
+
+// NOTE: Unreachable nodes can't be got to from the dialogue's input pins. Dead ends lead nowhere, unless they're a
+//       jump or marked with a true `IsEnding` property. Hubs are flagged when only one of their exits leads anywhere.
+pub fn analyze_dialogue(library: &Library, id: &str) -> Result<Value, Error> {
+    if model_json(library.find_model(id)?)["Type"] != "Dialogue" {
+        return Err(Error::NotADialogue(id.to_owned()));
+    }
+
+    let mut reached = HashSet::<String>::new();
+    let mut seen = HashSet::<Step>::new();
+    let mut pending = first_steps(library, id)?
+        .into_iter()
+        .filter(|step| library.parent(&step.0).ok().flatten() == Some(id))
+        .collect::<Vec<Step>>();
+
+    while let Some(step) = pending.pop() {
+        if !seen.insert(step.clone()) {
+            continue;
+        }
+
+        reached.insert(step.0.to_owned());
+        pending.extend(next_steps(library, &step)?);
+    }
+
+    let mut unreachable = vec![];
+    let mut dead_ends = vec![];
+    let mut single_option_hubs = vec![];
+
+    let mut inner = library.children(id)?.to_vec();
+    inner.reverse();
+    while let Some(node) = inner.pop() {
+        inner.extend(library.children(&node)?.iter().rev().cloned());
+
+        let model = library.find_model(&node)?;
+        let json = model_json(model);
+
+        if !reached.contains(&node) {
+            unreachable.push(node.to_owned());
+        }
+
+        let exits = library
+            .exits(&node)?
+            .into_iter()
+            .filter(|connection| library.find_model(&connection.target).is_ok())
+            .count();
+
+        if exits == 0
+            && json["Type"] != "Jump"
+            && !model::bool_property(&json, ENDING_PROPERTY).unwrap_or_default()
+        {
+            dead_ends.push(node.to_owned());
+        }
+
+        if json["Type"] == "Hub" && exits == 1 {
+            single_option_hubs.push(node);
+        }
+    }
+
+    Ok(json!({
+        "unreachable": unreachable,
+        "dead_ends": dead_ends,
+        "single_option_hubs": single_option_hubs,
+    }))
+}
+
+// NOTE: A node along with the pin it was reached through. A container entered from outside leads into its children,
+//       one left through its output pin from inside leads on from the container.
+type Step = (String, String);
+
+fn first_steps(library: &Library, from: &str) -> Result<Vec<Step>, Error> {
+    Ok(library
+        .outgoing_connections(from)?
+        .iter()
+        .map(|connection| {
+            (
+                connection.target.to_owned(),
+                connection.target_pin.to_owned(),
+            )
+        })
+        .collect())
+}
+
+fn next_steps(library: &Library, (id, pin): &Step) -> Result<Vec<Step>, Error> {
+    let is_container = !library.children(id)?.is_empty();
+
+    Ok(library
+        .outgoing_connections(id)?
+        .iter()
+        .filter(|connection| !is_container || connection.source_pin == *pin)
+        .map(|connection| {
+            (
+                connection.target.to_owned(),
+                connection.target_pin.to_owned(),
+            )
+        })
+        .collect())
+}
@@ -0,0 +1,57 @@
+use serde_json::Value;
+
+// NOTE: Fills in `{name}` placeholders with whatever `resolve` gives back for the name between the braces. `{{` and
+//       `}}` stand for literal braces, and placeholders that can't be resolved are left as they were written.
+pub fn interpolate(text: &str, mut resolve: impl FnMut(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        let brace = &rest[start..start + 1];
+        rest = &rest[start + 1..];
+
+        if rest.starts_with(brace) {
+            output.push_str(brace);
+            rest = &rest[1..];
+            continue;
+        }
+
+        if brace == "}" {
+            output.push('}');
+            continue;
+        }
+
+        match rest.find('}') {
+            Some(end) => {
+                let name = &rest[..end];
+
+                match resolve(name.trim()) {
+                    Some(value) => output.push_str(&value),
+                    None => {
+                        output.push('{');
+                        output.push_str(name);
+                        output.push('}');
+                    }
+                }
+
+                rest = &rest[end + 1..];
+            }
+            None => {
+                output.push('{');
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+// NOTE: Strings go in without their quotes, null as nothing at all
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(string) => string.to_owned(),
+        value => value.to_string(),
+    }
+}
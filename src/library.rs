@@ -0,0 +1,1424 @@
+use crate::{
+    error::Error,
+    export::{self, ExportInfo, ExportMetadata, TemplateDefinition, VariableNamespace},
+    localization::{Localization, TranslationEntry, LOCALIZABLE_PROPERTIES},
+    model::{self, model_at, model_json, Connection, Lookup, ModelIndex, Position},
+    overrides::EntityOverrides,
+};
+use articy::types::{File as ArticyFile, Model};
+use serde_json::{value::RawValue, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, BufReader, Read},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Default)]
+pub struct Library {
+    file: Option<Arc<ArticyFile>>,
+    index: Arc<ModelIndex>,
+    localization: Arc<RwLock<Localization>>,
+    overrides: Arc<RwLock<EntityOverrides>>,
+    asset_mapping: HashMap<String, String>,
+    active_package: usize,
+    export: Arc<ExportInfo>,
+    // NOTE: What was loaded, JSON or precompiled, kept so additional exports can be merged in and parsed again
+    source: Vec<u8>,
+    // NOTE: Set by a lazy load, which keeps `source` without its packages as they're in `file` or `pending`
+    stripped: bool,
+    refuse_unsupported: bool,
+    lazy_packages: bool,
+    // NOTE: Packages left out of `file` by a lazy load, by name and as they are in the export
+    pending: Vec<(String, Box<RawValue>)>,
+    timings: LoadTimings,
+}
+
+// NOTE: How long the last load spent parsing the export and building the index over it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LoadTimings {
+    pub parse: Duration,
+    pub index: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    pub id: String,
+    pub kind: String,
+    pub property: String,
+    pub text: String,
+    pub dialogue: Option<(String, String)>,
+}
+
+// NOTE: `kind` is one of "property", "slot" (a template property), "connection" or "script"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub id: String,
+    pub kind: String,
+    pub path: String,
+    pub expression: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HierarchyNode {
+    pub id: String,
+    pub kind: String,
+    pub display_name: String,
+    pub technical_name: String,
+    pub children: Vec<HierarchyNode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialogueOverview {
+    pub id: String,
+    pub start_nodes: Vec<String>,
+    pub fragments: Vec<String>,
+    pub participants: Vec<String>,
+    pub word_count: usize,
+}
+
+// NOTE: Localization and overrides are shared rather than copied so changing them reaches running sessions. Cloning only bumps
+//       reference counts, and the parts are thread-safe so sessions on other threads can play from the same export.
+#[derive(Debug, Clone)]
+pub struct SharedLibrary {
+    pub file: Arc<ArticyFile>,
+    pub index: Arc<ModelIndex>,
+    pub localization: Arc<RwLock<Localization>>,
+    pub overrides: Arc<RwLock<EntityOverrides>>,
+    pub export: Arc<ExportInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageInfo {
+    pub name: String,
+    pub model_count: usize,
+    pub is_default: bool,
+    pub is_active: bool,
+    pub is_loaded: bool,
+}
+
+#[derive(Debug)]
+pub struct Parsed {
+    file: ArticyFile,
+    index: ModelIndex,
+    export: ExportInfo,
+    source: Vec<u8>,
+    stripped: bool,
+    pending: Vec<(String, Box<RawValue>)>,
+    timings: LoadTimings,
+}
+
+#[derive(Debug)]
+pub enum LoadMessage {
+    Progress(f64),
+    Loaded(Box<Parsed>),
+    Failed(Error),
+}
+
+// NOTE: Precompiled exports start with this, followed by a byte for the format version
+const PRECOMPILED_MAGIC: &[u8] = b"ARTICYRS";
+const PRECOMPILED_VERSION: u8 = 2;
+
+// NOTE: Feeds the parser the buffer in chunks, telling `report` how much of it has been read so far
+struct Tracked<'a, 'b> {
+    buffer: &'a [u8],
+    read: usize,
+    report: &'b mut dyn FnMut(f64),
+}
+
+impl Parsed {
+    // NOTE: Takes either an Articy JSON export or one made by `precompile`
+    pub fn from_buffer(buffer: &[u8]) -> Result<Self, Error> {
+        Parsed::read(buffer, None)
+    }
+
+    fn read(buffer: &[u8], report: Option<&mut dyn FnMut(f64)>) -> Result<Self, Error> {
+        let started = Instant::now();
+        let (file, export) = parse(buffer, report)?;
+
+        Ok(Parsed::indexed(
+            file,
+            export,
+            buffer.to_vec(),
+            vec![],
+            started,
+        ))
+    }
+
+    fn indexed(
+        file: ArticyFile,
+        export: ExportInfo,
+        source: Vec<u8>,
+        pending: Vec<(String, Box<RawValue>)>,
+        started: Instant,
+    ) -> Self {
+        let parsed = Instant::now();
+        let index = ModelIndex::build(&file);
+
+        Parsed {
+            file,
+            index,
+            export,
+            source,
+            stripped: !pending.is_empty(),
+            pending,
+            timings: LoadTimings {
+                parse: parsed - started,
+                index: parsed.elapsed(),
+            },
+        }
+    }
+
+    // NOTE: Only parses the packages named in `packages`, or the default one when that's `None`, the others are
+    //       skipped over without building their models. Precompiled exports are parsed already and load in full
+    pub fn partial(buffer: &[u8], packages: Option<&[String]>) -> Result<Self, Error> {
+        Parsed::read_partial(buffer, packages, None)
+    }
+
+    fn read_partial(
+        buffer: &[u8],
+        packages: Option<&[String]>,
+        report: Option<&mut dyn FnMut(f64)>,
+    ) -> Result<Self, Error> {
+        if buffer.starts_with(PRECOMPILED_MAGIC) {
+            return Parsed::read(buffer, report);
+        }
+
+        let started = Instant::now();
+        let mut top = serde_json::from_slice::<BTreeMap<String, &RawValue>>(buffer)
+            .map_err(invalid_export)?;
+        let all = match top.get("Packages") {
+            Some(packages) => {
+                serde_json::from_str::<Vec<&RawValue>>(packages.get()).map_err(invalid_export)?
+            }
+            None => vec![],
+        };
+
+        let mut headers = vec![];
+        for package in &all {
+            let header = serde_json::from_str::<BTreeMap<String, &RawValue>>(package.get())
+                .map_err(invalid_export)?;
+            let field = |key: &str| {
+                header
+                    .get(key)
+                    .and_then(|value| serde_json::from_str::<Value>(value.get()).ok())
+                    .unwrap_or_default()
+            };
+
+            headers.push((
+                field("Name").as_str().unwrap_or_default().to_owned(),
+                field("IsDefaultPackage") == true,
+            ));
+        }
+
+        // NOTE: Without a default package the first one is loaded, so there's always something to play
+        let wanted = |position: usize, (name, is_default): &(String, bool)| match packages {
+            Some(packages) => packages.contains(name),
+            None => *is_default || (position == 0 && !headers.iter().any(|(_, default)| *default)),
+        };
+
+        let mut kept = vec![];
+        let mut pending = vec![];
+        for (position, (package, header)) in all.iter().zip(&headers).enumerate() {
+            if wanted(position, header) {
+                kept.push(*package);
+            } else {
+                pending.push((header.0.to_owned(), (*package).to_owned()));
+            }
+        }
+
+        let kept = serde_json::value::to_raw_value(&kept)
+            .map_err(|error| Error::InvalidResource(format!("can't serialize export: {error}")))?;
+        top.insert("Packages".to_owned(), &kept);
+        let skeleton = serde_json::to_vec(&top)
+            .map_err(|error| Error::InvalidResource(format!("can't serialize export: {error}")))?;
+
+        let file = parse_file(&skeleton, report)?;
+        let mut export = ExportInfo::from_buffer(&skeleton);
+        export.hash = export::hash(buffer);
+
+        // NOTE: The packages are in `file` or `pending` already, so they're left out of what's kept of the export
+        let none = serde_json::value::to_raw_value(&[(); 0])
+            .map_err(|error| Error::InvalidResource(format!("can't serialize export: {error}")))?;
+        let source = match pending.is_empty() {
+            true => buffer.to_vec(),
+            false => {
+                top.insert("Packages".to_owned(), &none);
+                serde_json::to_vec(&top).map_err(|error| {
+                    Error::InvalidResource(format!("can't serialize export: {error}"))
+                })?
+            }
+        };
+
+        Ok(Parsed::indexed(file, export, source, pending, started))
+    }
+}
+
+// NOTE: Progress follows how far the parser got through the export, up to 90 percent. The rest is reading the
+//       definitions and building the index, which 100 is sent after
+pub fn load_in_background(buffer: Vec<u8>, lazy: bool) -> Receiver<LoadMessage> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        // NOTE: Only whole percents are sent, most chunks the parser reads don't move it that far
+        let mut sent = -1.0;
+        let mut report = |read: f64| {
+            let percent = (read * 90.0).floor();
+            if percent > sent {
+                sent = percent;
+                sender.send(LoadMessage::Progress(percent)).ok();
+            }
+        };
+
+        let parsed = match lazy {
+            true => Parsed::read_partial(&buffer, None, Some(&mut report)),
+            false => Parsed::read(&buffer, Some(&mut report)),
+        };
+
+        match parsed {
+            Ok(parsed) => {
+                sender.send(LoadMessage::Progress(100.0)).ok();
+                sender.send(LoadMessage::Loaded(Box::new(parsed))).ok();
+            }
+            Err(error) => {
+                sender.send(LoadMessage::Failed(error)).ok();
+            }
+        }
+    });
+
+    receiver
+}
+
+// NOTE: Stores the parsed export so loading skips the JSON parsing. This is MessagePack rather than something like
+//       bincode, as articy-rs' models are tagged and hold JSON values which need a self-describing format. The
+//       export itself isn't kept, only the hash saves are checked against
+pub fn precompile(buffer: &[u8]) -> Result<Vec<u8>, Error> {
+    if buffer.starts_with(PRECOMPILED_MAGIC) {
+        return Ok(buffer.to_vec());
+    }
+
+    if buffer.iter().all(u8::is_ascii_whitespace) {
+        return Err(Error::InvalidResource("export is empty".to_owned()));
+    }
+
+    let json = serde_json::from_slice::<Value>(buffer).map_err(invalid_export)?;
+
+    precompile_json(json, export::hash(buffer))
+}
+
+fn precompile_json(json: Value, hash: String) -> Result<Vec<u8>, Error> {
+    // NOTE: Only what `ExportInfo` reads, so it doesn't need the whole export parsed again
+    let definitions = definitions(&json).to_string();
+
+    let file = serde_json::from_value::<ArticyFile>(json).map_err(invalid_export)?;
+    let data = rmp_serde::to_vec_named(&(&file, definitions, hash))
+        .map_err(|error| Error::InvalidResource(format!("can't precompile export: {error}")))?;
+
+    let mut precompiled = PRECOMPILED_MAGIC.to_vec();
+    precompiled.push(PRECOMPILED_VERSION);
+    precompiled.extend(data);
+
+    Ok(precompiled)
+}
+
+fn definitions(json: &Value) -> Value {
+    serde_json::json!({
+        "GlobalVariables": json["GlobalVariables"],
+        "ObjectDefinitions": json["ObjectDefinitions"],
+        "Settings": json["Settings"],
+        "Project": json["Project"],
+    })
+}
+
+// NOTE: The export as Articy writes it, precompiled ones are put back together from the parsed file and definitions
+fn export_json(buffer: &[u8]) -> Result<Value, Error> {
+    let data = match buffer.strip_prefix(PRECOMPILED_MAGIC) {
+        Some(data) => data,
+        None => {
+            return serde_json::from_slice::<Value>(buffer).map_err(|error| {
+                Error::InvalidResource(format!("export isn't valid JSON: {error}"))
+            })
+        }
+    };
+
+    let (file, definitions, _) = read_precompiled(data, None)?;
+    let mut json = serde_json::to_value(&file)
+        .map_err(|error| Error::InvalidResource(format!("can't serialize export: {error}")))?;
+    let definitions = serde_json::from_str::<Value>(&definitions).map_err(|error| {
+        Error::InvalidResource(format!("precompiled export is broken: {error}"))
+    })?;
+
+    for (key, value) in definitions.as_object().into_iter().flatten() {
+        json[key] = value.to_owned();
+    }
+
+    Ok(json)
+}
+
+// NOTE: Deserialized here rather than through `ArticyFile::from_buffer`, which panics on a broken export. Reading
+//       through `Tracked` is slower than parsing the slice, so it's only done when there's progress to report
+fn parse_file(buffer: &[u8], report: Option<&mut dyn FnMut(f64)>) -> Result<ArticyFile, Error> {
+    if buffer.iter().all(u8::is_ascii_whitespace) {
+        return Err(Error::InvalidResource("export is empty".to_owned()));
+    }
+
+    match report {
+        Some(report) => serde_json::from_reader::<_, ArticyFile>(Tracked::reader(buffer, report)),
+        None => serde_json::from_slice::<ArticyFile>(buffer),
+    }
+    .map_err(invalid_export)
+}
+
+// NOTE: articy-rs' file can't be cloned, a copy of one that sessions still hold goes through MessagePack
+fn copy_file(file: &ArticyFile) -> Result<ArticyFile, Error> {
+    let data = rmp_serde::to_vec_named(file)
+        .map_err(|error| Error::InvalidResource(format!("can't copy export: {error}")))?;
+
+    rmp_serde::from_slice(&data)
+        .map_err(|error| Error::InvalidResource(format!("can't copy export: {error}")))
+}
+
+fn invalid_export(error: serde_json::Error) -> Error {
+    Error::InvalidExport(error.line(), error.column(), error.to_string())
+}
+
+fn parse(
+    buffer: &[u8],
+    report: Option<&mut dyn FnMut(f64)>,
+) -> Result<(ArticyFile, ExportInfo), Error> {
+    let data = match buffer.strip_prefix(PRECOMPILED_MAGIC) {
+        Some(data) => data,
+        None => {
+            let mut export = ExportInfo::from_buffer(buffer);
+            export.hash = export::hash(buffer);
+
+            return Ok((parse_file(buffer, report)?, export));
+        }
+    };
+
+    let (file, definitions, hash) = read_precompiled(data, report)?;
+    let mut export = ExportInfo::from_buffer(definitions.as_bytes());
+    export.hash = hash;
+
+    Ok((file, export))
+}
+
+// NOTE: Gives back the file, the definitions `ExportInfo` reads and the hash of the export it was made from
+fn read_precompiled(
+    data: &[u8],
+    report: Option<&mut dyn FnMut(f64)>,
+) -> Result<(ArticyFile, String, String), Error> {
+    match data.split_first() {
+        Some((&PRECOMPILED_VERSION, data)) => match report {
+            Some(report) => rmp_serde::from_read::<_, (ArticyFile, String, String)>(
+                Tracked::reader(data, report),
+            ),
+            None => rmp_serde::from_slice::<(ArticyFile, String, String)>(data),
+        }
+        .map_err(|error| Error::InvalidResource(format!("precompiled export is broken: {error}"))),
+        _ => Err(Error::InvalidResource(
+            "precompiled export was made by another version, precompile it again".to_owned(),
+        )),
+    }
+}
+
+impl<'a, 'b> Tracked<'a, 'b> {
+    // NOTE: Buffered, the parsers read a byte at a time and progress is only worth reporting per chunk
+    fn reader(buffer: &'a [u8], report: &'b mut dyn FnMut(f64)) -> BufReader<Self> {
+        BufReader::with_capacity(
+            64 * 1024,
+            Tracked {
+                buffer,
+                read: 0,
+                report,
+            },
+        )
+    }
+}
+
+impl Read for Tracked<'_, '_> {
+    fn read(&mut self, into: &mut [u8]) -> io::Result<usize> {
+        let count = into.len().min(self.buffer.len() - self.read);
+        into[..count].copy_from_slice(&self.buffer[self.read..self.read + count]);
+
+        self.read += count;
+        (self.report)(self.read as f64 / self.buffer.len() as f64);
+
+        Ok(count)
+    }
+}
+
+impl Library {
+    pub fn load(&mut self, buffer: &[u8]) -> Result<(), Error> {
+        match self.lazy_packages {
+            true => self.install(Parsed::partial(buffer, None)?),
+            false => self.install(Parsed::from_buffer(buffer)?),
+        }
+    }
+
+    // NOTE: Refusing leaves the file that was loaded before in place
+    pub fn install(&mut self, parsed: Parsed) -> Result<(), Error> {
+        let metadata = &parsed.export.metadata;
+        if self.refuse_unsupported && !metadata.is_supported() {
+            return Err(Error::UnsupportedExportVersion(
+                metadata.export_version.to_owned().unwrap_or_default(),
+            ));
+        }
+
+        let Parsed {
+            file,
+            index,
+            export,
+            source,
+            stripped,
+            pending,
+            timings,
+        } = parsed;
+
+        self.index = Arc::new(index);
+        self.export = Arc::new(export);
+        self.source = source;
+        self.stripped = stripped;
+        self.pending = pending;
+        self.timings = timings;
+        self.active_package = file
+            .packages
+            .iter()
+            .position(|package| std::ptr::eq(package, file.get_default_package()))
+            .unwrap_or_default();
+        self.file = Some(Arc::from(file));
+
+        Ok(())
+    }
+
+    pub fn set_refuse_unsupported(&mut self, refuse: bool) {
+        self.refuse_unsupported = refuse;
+    }
+
+    // NOTE: Keeps the settings and asset mapping. Sessions share parts of the export and keep those alive until
+    //       they're dropped themselves
+    pub fn unload(&mut self) {
+        *self = Library {
+            asset_mapping: std::mem::take(&mut self.asset_mapping),
+            refuse_unsupported: self.refuse_unsupported,
+            lazy_packages: self.lazy_packages,
+            ..Default::default()
+        };
+    }
+
+    pub fn load_timings(&self) -> LoadTimings {
+        self.timings
+    }
+
+    // NOTE: Model sizes are estimated from their JSON, which makes this slow on big exports
+    pub fn diagnostics(&self) -> Result<Value, Error> {
+        let file = self.file()?;
+        let models = file.get_models();
+
+        let models_bytes = models
+            .iter()
+            .map(|model| serde_json::to_vec(model).map_or(0, |json| json.len()))
+            .sum::<usize>();
+        let index_bytes = self.index.estimated_bytes();
+
+        Ok(serde_json::json!({
+            "parse_msec": self.timings.parse.as_secs_f64() * 1000.0,
+            "index_msec": self.timings.index.as_secs_f64() * 1000.0,
+            "model_count": models.len(),
+            "package_count": file.packages.len(),
+            "pending_package_count": self.pending.len(),
+            "index": self.index.sizes().into_iter().collect::<BTreeMap<&str, usize>>(),
+            "memory": {
+                "source": self.source.len(),
+                "models": models_bytes,
+                "index": index_bytes,
+                "total": self.source.len() + models_bytes + index_bytes,
+            },
+        }))
+    }
+
+    // NOTE: Takes effect on the next load, packages other than the default one are then parsed on first use
+    pub fn set_lazy_packages(&mut self, lazy: bool) {
+        self.lazy_packages = lazy;
+    }
+
+    // NOTE: Parses only the package, adding it after the ones loaded so far. Like `load_additional`, running
+    //       sessions keep the export they were made with
+    pub fn load_package(&mut self, name: &str) -> Result<(), Error> {
+        let position = match self.pending.iter().position(|(pending, _)| pending == name) {
+            Some(position) => position,
+            None => return self.package_scope(Some(name)).map(|_| ()),
+        };
+
+        let started = Instant::now();
+        let package =
+            serde_json::from_str(self.pending[position].1.get()).map_err(invalid_export)?;
+        self.timings.parse += started.elapsed();
+
+        let mut file = match self.file.take().map(Arc::try_unwrap) {
+            Some(Ok(file)) => file,
+            Some(Err(shared)) => {
+                let copy = copy_file(&shared);
+                self.file = Some(shared);
+                copy?
+            }
+            None => return Err(Error::DatabaseNotSetup),
+        };
+
+        file.packages.push(package);
+
+        let started = Instant::now();
+        Arc::make_mut(&mut self.index).add_package(&file, file.packages.len() - 1);
+        self.timings.index += started.elapsed();
+
+        self.file = Some(Arc::new(file));
+        self.pending.remove(position);
+
+        Ok(())
+    }
+
+    // NOTE: Loads the pending packages that mention `key` (an id, technical name, ...), so lookups on a lazy load find
+    //       models in packages that haven't been used yet. Gives back the names of the ones it loaded
+    pub fn require(&mut self, key: &str) -> Result<Vec<String>, Error> {
+        if self.pending.is_empty() || key.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let quoted = serde_json::to_string(key)
+            .map_err(|error| Error::InvalidResource(format!("can't serialize key: {error}")))?;
+        let names = self
+            .pending
+            .iter()
+            .filter(|(_, package)| package.get().contains(&quoted))
+            .map(|(name, _)| name.to_owned())
+            .collect::<Vec<String>>();
+
+        for name in &names {
+            self.load_package(name)?;
+        }
+
+        Ok(names)
+    }
+
+    pub fn export_metadata(&self) -> Result<&ExportMetadata, Error> {
+        self.file()?;
+
+        Ok(&self.export.metadata)
+    }
+
+    // NOTE: Running sessions keep playing the export they were made with until they're made again
+    pub fn load_additional(&mut self, buffer: &[u8]) -> Result<Vec<String>, Error> {
+        self.file()?;
+
+        let mut merged = self.source_json()?;
+        let added = export::merge(&mut merged, export_json(buffer)?)?;
+        let merged = serde_json::to_vec(&merged)
+            .map_err(|error| Error::InvalidResource(format!("can't serialize export: {error}")))?;
+
+        let active = self
+            .file()?
+            .packages
+            .get(self.active_package)
+            .map(|package| package.name.to_owned());
+
+        // NOTE: A lazy load stays lazy, only the packages loaded so far and the added ones are parsed
+        let parsed = if self.pending.is_empty() {
+            Parsed::from_buffer(&merged)?
+        } else {
+            let mut names = self
+                .file()?
+                .packages
+                .iter()
+                .map(|package| package.name.to_owned())
+                .collect::<Vec<String>>();
+            names.extend(added.iter().cloned());
+
+            Parsed::partial(&merged, Some(&names))?
+        };
+
+        self.install(parsed)?;
+        if let Some(active) = active {
+            self.set_active_package(&active).ok();
+        }
+
+        Ok(added)
+    }
+
+    pub fn precompile(&self) -> Result<Vec<u8>, Error> {
+        self.file()?;
+
+        match self.stripped {
+            true => precompile_json(self.source_json()?, self.export.hash.to_owned()),
+            false => precompile(&self.source),
+        }
+    }
+
+    // NOTE: The loaded export as Articy writes it, a lazy load gets its packages back from `file` and `pending`
+    fn source_json(&self) -> Result<Value, Error> {
+        let mut json = export_json(&self.source)?;
+        if !self.stripped {
+            return Ok(json);
+        }
+
+        let mut packages = vec![];
+        for package in &self.file()?.packages {
+            packages.push(serde_json::to_value(package).map_err(|error| {
+                Error::InvalidResource(format!("can't serialize export: {error}"))
+            })?);
+        }
+
+        for (_, package) in &self.pending {
+            packages.push(serde_json::from_str::<Value>(package.get()).map_err(invalid_export)?);
+        }
+
+        json["Packages"] = Value::Array(packages);
+        Ok(json)
+    }
+
+    pub fn file(&self) -> Result<&ArticyFile, Error> {
+        self.file.as_deref().ok_or(Error::DatabaseNotSetup)
+    }
+
+    // NOTE: Sessions keep their own handle on the file, so they outlive a reload of the Library
+    pub fn shared(&self) -> Result<SharedLibrary, Error> {
+        Ok(SharedLibrary {
+            file: self.file.clone().ok_or(Error::DatabaseNotSetup)?,
+            index: self.index.clone(),
+            localization: self.localization.clone(),
+            overrides: self.overrides.clone(),
+            export: self.export.clone(),
+        })
+    }
+
+    pub fn global_variables(&self) -> Result<&[VariableNamespace], Error> {
+        self.file()?;
+
+        Ok(&self.export.global_variables)
+    }
+
+    pub fn template_names(&self) -> Result<Vec<String>, Error> {
+        self.file()?;
+
+        Ok(self.export.template_names())
+    }
+
+    pub fn template(&self, name: &str) -> Result<&TemplateDefinition, Error> {
+        self.file()?;
+
+        self.export
+            .template(name)
+            .ok_or_else(|| Error::TemplateNotFound(name.to_owned()))
+    }
+
+    pub fn translation_entries(&self) -> Result<Vec<TranslationEntry>, Error> {
+        let mut entries = vec![];
+
+        for model in self.all_models()? {
+            let json = model_json(model);
+            let id = model.id().to_inner();
+
+            for property in LOCALIZABLE_PROPERTIES {
+                if let Some(text) = json["Properties"][property]
+                    .as_str()
+                    .filter(|text| !text.is_empty())
+                {
+                    entries.push(TranslationEntry {
+                        key: format!("{id}.{property}"),
+                        text: text.to_owned(),
+                    });
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    // NOTE: Tables are keyed by the text of a localized export, or by `{id}.{property}` as `export_translations` writes
+    pub fn missing_translations(&self, entry: &TranslationEntry) -> Vec<String> {
+        let localization = self
+            .localization
+            .read()
+            .expect("localization lock to not be poisoned");
+        let by_key = localization.missing(&entry.key);
+
+        localization
+            .missing(&entry.text)
+            .into_iter()
+            .filter(|locale| by_key.contains(locale))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    pub fn load_localization(&mut self, locale: &str, buffer: &[u8]) -> Result<(), Error> {
+        self.localization
+            .write()
+            .expect("localization lock to not be poisoned")
+            .load(locale, buffer)
+    }
+
+    pub fn set_locale(&mut self, locale: &str) -> Result<(), Error> {
+        self.localization
+            .write()
+            .expect("localization lock to not be poisoned")
+            .set_locale(locale)
+    }
+
+    // NOTE: Overrides outlive reloads of the export, they belong to the playthrough rather than the file
+    pub fn set_entity_override(
+        &mut self,
+        id: &str,
+        property: &str,
+        value: Value,
+    ) -> Result<(), Error> {
+        self.find_model(id)?;
+
+        self.overrides
+            .write()
+            .expect("overrides lock to not be poisoned")
+            .set(id, property, value);
+
+        Ok(())
+    }
+
+    pub fn entity_overrides(&self) -> EntityOverrides {
+        self.overrides
+            .read()
+            .expect("overrides lock to not be poisoned")
+            .clone()
+    }
+
+    pub fn locale(&self) -> Option<String> {
+        self.localization
+            .read()
+            .expect("localization lock to not be poisoned")
+            .locale()
+            .map(str::to_owned)
+    }
+
+    pub fn find_model(&self, id: &str) -> Result<&Model, Error> {
+        model_at(self.file()?, self.index.get(Lookup::Id, id), None)
+            .ok_or_else(|| Error::ModelNotFound(id.to_owned()))
+    }
+
+    pub fn find_summary(&self, id: &str) -> Result<&Value, Error> {
+        self.file()?;
+        self.index
+            .summary(id)
+            .ok_or_else(|| Error::ModelNotFound(id.to_owned()))
+    }
+
+    // NOTE: An empty name means the active package, "*" means every package
+    fn package_scope(&self, package: Option<&str>) -> Result<Option<usize>, Error> {
+        match package {
+            None | Some("") => Ok(Some(self.active_package)),
+            Some("*") => Ok(None),
+            Some(name) => self
+                .file()?
+                .packages
+                .iter()
+                .position(|package| package.name == name)
+                .map(Some)
+                .ok_or_else(|| Error::PackageNotFound(name.to_owned())),
+        }
+    }
+
+    pub fn lookup(
+        &self,
+        lookup: Lookup,
+        key: &str,
+        package: Option<&str>,
+    ) -> Result<Option<&Model>, Error> {
+        let file = self.file()?;
+        let package = self.package_scope(package)?;
+
+        Ok(model_at(file, self.index.get(lookup, key), package))
+    }
+
+    pub fn property(&self, id: &str, path: &str) -> Result<Value, Error> {
+        let mut json = model_json(self.find_model(id)?);
+        if let Some(template) = json.get_mut("Template") {
+            self.export.resolve_enums(template);
+        }
+
+        model::property_at(json, path)
+            .ok_or_else(|| Error::PropertyNotFound(format!("{id}.{path}")))
+    }
+
+    // NOTE: Whether `path` leads to one of Articy's colors, a model's own "Color" or a template property defined as one
+    pub fn is_color(&self, path: &str) -> bool {
+        let mut segments = path.rsplit('.');
+
+        match (segments.next(), segments.next()) {
+            (Some("Color"), _) => true,
+            (Some(property), Some(feature)) => self.export.is_color(feature, property),
+            _ => false,
+        }
+    }
+
+    // NOTE: Follows a slot (one id) or reference strip (a list of them) to the models it points at, in the order of
+    //       the strip. Empty slots and ids of models that aren't in the export are left out
+    pub fn resolve_references(&self, id: &str, path: &str) -> Result<Vec<&Model>, Error> {
+        let value = self.property(id, path)?;
+
+        let mut ids = vec![];
+        collect_ids(&value, &mut ids);
+
+        Ok(ids
+            .into_iter()
+            .filter_map(|target| self.find_model(target).ok())
+            .collect())
+    }
+
+    pub fn models_by_display_name(
+        &self,
+        display_name: &str,
+        package: Option<&str>,
+    ) -> Result<Vec<&Model>, Error> {
+        let file = self.file()?;
+        let package = self.package_scope(package)?;
+
+        Ok(self
+            .index
+            .by_display_name(display_name)
+            .iter()
+            .filter_map(|position| model_at(file, Some(position), package))
+            .collect())
+    }
+
+    // NOTE: Every DialogueFragment the entity speaks, across all dialogues and packages
+    pub fn lines_by_speaker(&self, speaker: &str) -> Result<Vec<&Model>, Error> {
+        let file = self.file()?;
+
+        Ok(self
+            .index
+            .by_speaker(speaker)
+            .iter()
+            .filter_map(|position| model_at(file, Some(position), None))
+            .collect())
+    }
+
+    pub fn models_of_type(&self, kind: &str) -> Result<Vec<&Model>, Error> {
+        Ok(self.file()?.get_models_of_type(kind))
+    }
+
+    // NOTE: In the order they're in the export, whichever of `kinds` they are. Paged through the index, so only the
+    //       models on the page are looked at
+    pub fn models_of_types(
+        &self,
+        kinds: &[String],
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<&Model>, Error> {
+        let file = self.file()?;
+
+        let mut kinds = kinds.to_vec();
+        kinds.sort();
+        kinds.dedup();
+
+        let mut positions = kinds
+            .iter()
+            .flat_map(|kind| self.index.by_type(kind))
+            .copied()
+            .collect::<Vec<Position>>();
+        positions.sort_unstable();
+
+        Ok(positions
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .filter_map(|position| model_at(file, Some(position), None))
+            .collect())
+    }
+
+    pub fn all_models(&self) -> Result<Vec<&Model>, Error> {
+        Ok(self.file()?.get_models())
+    }
+
+    pub fn packages(&self) -> Result<Vec<PackageInfo>, Error> {
+        let file = self.file()?;
+
+        Ok(file
+            .packages
+            .iter()
+            .enumerate()
+            .map(|(position, package)| PackageInfo {
+                name: package.name.to_owned(),
+                model_count: package.models.len(),
+                is_default: std::ptr::eq(package, file.get_default_package()),
+                is_active: position == self.active_package,
+                is_loaded: true,
+            })
+            .chain(self.pending.iter().map(|(name, _)| PackageInfo {
+                name: name.to_owned(),
+                model_count: 0,
+                is_default: false,
+                is_active: false,
+                is_loaded: false,
+            }))
+            .collect())
+    }
+
+    pub fn set_active_package(&mut self, name: &str) -> Result<(), Error> {
+        self.load_package(name)?;
+
+        match self.package_scope(Some(name))? {
+            Some(package) => {
+                self.active_package = package;
+                Ok(())
+            }
+            None => Err(Error::PackageNotFound(name.to_owned())),
+        }
+    }
+
+    // NOTE: Recursing swaps nested folders for the entities inside of them
+    pub fn entity_ids_from_folder(
+        &self,
+        folder_id: &str,
+        recursive: bool,
+    ) -> Result<Vec<String>, Error> {
+        let file = self.file()?;
+        let model = self.find_model(folder_id)?;
+
+        if !matches!(model, Model::UserFolder { .. }) {
+            return Err(Error::NotAUserFolder(folder_id.to_owned()));
+        }
+
+        let hierarchy_path = file
+            .get_hierarchy_path_from_model(model)
+            .map_err(|_| Error::HierarchyNotFound(folder_id.to_owned()))?;
+
+        let hierarchy = file
+            .get_hierarchy(hierarchy_path)
+            .map_err(|_| Error::HierarchyNotFound(folder_id.to_owned()))?;
+
+        let ids = hierarchy
+            .children
+            .as_ref()
+            .map(|children| {
+                children
+                    .iter()
+                    .map(|hierarchy| hierarchy.id.clone().to_inner())
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
+
+        if !recursive {
+            return Ok(ids);
+        }
+
+        let mut entities = vec![];
+
+        for id in ids {
+            match self.find_model(&id) {
+                Ok(Model::UserFolder { .. }) => {
+                    entities.extend(self.entity_ids_from_folder(&id, true)?)
+                }
+                _ => entities.push(id),
+            }
+        }
+
+        Ok(entities)
+    }
+
+    pub fn children(&self, id: &str) -> Result<&[String], Error> {
+        self.find_model(id)?;
+
+        Ok(self.index.children(id))
+    }
+
+    // NOTE: Models whose parent isn't a model themselves, e.g. the folders at the top of every package
+    pub fn root_ids(&self) -> Result<Vec<String>, Error> {
+        let file = self.file()?;
+
+        Ok(self
+            .all_models()?
+            .into_iter()
+            .map(|model| model.id().to_inner())
+            .filter(|id| {
+                self.index
+                    .parent(id)
+                    .map_or(true, |parent| self.index.find(file, parent).is_none())
+            })
+            .collect())
+    }
+
+    // NOTE: Without a root, the top level models are the children of a node with an empty id
+    pub fn hierarchy(&self, root: Option<&str>) -> Result<HierarchyNode, Error> {
+        let children = match root {
+            Some(root) => self.children(root)?.to_vec(),
+            None => self.root_ids()?,
+        };
+
+        let json = match root {
+            Some(root) => model_json(self.find_model(root)?),
+            None => Value::Null,
+        };
+        let properties = &json["Properties"];
+
+        Ok(HierarchyNode {
+            id: root.unwrap_or_default().to_owned(),
+            kind: json["Type"].as_str().unwrap_or_default().to_owned(),
+            display_name: properties["DisplayName"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned(),
+            technical_name: properties["TechnicalName"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned(),
+            children: children
+                .iter()
+                .map(|child| self.hierarchy(Some(child)))
+                .collect::<Result<Vec<HierarchyNode>, Error>>()?,
+        })
+    }
+
+    pub fn parent(&self, id: &str) -> Result<Option<&str>, Error> {
+        self.find_model(id)?;
+
+        Ok(self.index.parent(id))
+    }
+
+    pub fn outgoing_connections(&self, id: &str) -> Result<&[Connection], Error> {
+        self.find_model(id)?;
+
+        Ok(self.index.outgoing(id))
+    }
+
+    // NOTE: Outgoing connections leaving the node, without the ones from its input pins into its own children
+    pub fn exits(&self, id: &str) -> Result<Vec<&Connection>, Error> {
+        Ok(self
+            .outgoing_connections(id)?
+            .iter()
+            .filter(|connection| self.index.parent(&connection.target) != Some(id))
+            .collect())
+    }
+
+    pub fn incoming_connections(&self, id: &str) -> Result<&[Connection], Error> {
+        self.find_model(id)?;
+
+        Ok(self.index.incoming(id))
+    }
+
+    // NOTE: Mapped by asset id, the exported path or just its filename, in that order
+    pub fn map_asset(&mut self, key: &str, path: &str) {
+        self.asset_mapping.insert(key.to_owned(), path.to_owned());
+    }
+
+    pub fn asset_path(&self, id: &str, root: &str) -> Result<String, Error> {
+        let json = model_json(self.find_model(id)?);
+
+        if json["Type"] != "Asset" {
+            return Err(Error::NotAnAsset(id.to_owned()));
+        }
+
+        // NOTE: Articy writes paths the Windows way
+        let reference = json["Properties"]["AssetRef"]
+            .as_str()
+            .unwrap_or_default()
+            .replace('\\', "/");
+        let filename = reference.rsplit('/').next().unwrap_or_default();
+
+        if let Some(path) = [id, reference.as_str(), filename]
+            .iter()
+            .find_map(|key| self.asset_mapping.get(*key))
+        {
+            return Ok(path.to_owned());
+        }
+
+        Ok(if root.ends_with('/') {
+            format!("{root}{reference}")
+        } else {
+            format!("{root}/{reference}")
+        })
+    }
+
+    pub fn search_text(&self, query: &str) -> Result<Vec<SearchMatch>, Error> {
+        let file = self.file()?;
+
+        Ok(self
+            .index
+            .search(query)
+            .filter_map(|(position, property, text)| {
+                let model = model_at(file, Some(position), None)?;
+                let id = model.id().to_inner();
+
+                Some(SearchMatch {
+                    kind: model_json(model)["Type"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_owned(),
+                    dialogue: self.enclosing_dialogue(&id),
+                    id,
+                    property: property.to_owned(),
+                    text: text.to_owned(),
+                })
+            })
+            .collect())
+    }
+
+    // NOTE: Scripts can refer to a model by technical name too, e.g. `getObj("Chr_Manfred")`
+    pub fn find_references(&self, id: &str) -> Result<Vec<Reference>, Error> {
+        let file = self.file()?;
+        let target = model_json(self.find_model(id)?);
+        let technical_name = target["Properties"]["TechnicalName"]
+            .as_str()
+            .filter(|name| !name.is_empty())
+            .map(|name| format!("\"{name}\""));
+
+        let mut references = self
+            .index
+            .incoming(id)
+            .iter()
+            .map(|connection| Reference {
+                id: connection.source.to_owned(),
+                kind: "connection".to_owned(),
+                path: connection.source_pin.to_owned(),
+                expression: None,
+            })
+            .collect::<Vec<Reference>>();
+
+        for model in file.get_models() {
+            let source = model.id().to_inner();
+            let json = model_json(model);
+            let mut found = vec![];
+
+            if let Some(properties) = json["Properties"].as_object() {
+                for (key, value) in properties {
+                    // NOTE: Pins are covered by connections and scripts, parents aren't a use of the model
+                    if !["Id", "Parent", "InputPins", "OutputPins"].contains(&key.as_str()) {
+                        find_value(value, format!("Properties.{key}"), id, &mut found);
+                    }
+                }
+            }
+
+            references.extend(found.drain(..).map(|path| Reference {
+                id: source.to_owned(),
+                kind: "property".to_owned(),
+                path,
+                expression: None,
+            }));
+
+            find_value(&json["Template"], "Template".to_owned(), id, &mut found);
+            references.extend(found.drain(..).map(|path| Reference {
+                id: source.to_owned(),
+                kind: "slot".to_owned(),
+                path,
+                expression: None,
+            }));
+
+            let properties = &json["Properties"];
+            let scripts = ["InputPins", "OutputPins"]
+                .into_iter()
+                .flat_map(|pins| {
+                    properties[pins]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .map(move |pin| {
+                            (
+                                format!("{pins}.{}", pin["Id"].as_str().unwrap_or_default()),
+                                &pin["Text"],
+                            )
+                        })
+                })
+                .chain([(
+                    "Properties.Expression".to_owned(),
+                    &properties["Expression"],
+                )]);
+
+            for (path, script) in scripts {
+                let script = match script.as_str() {
+                    Some(script) => script,
+                    None => continue,
+                };
+
+                if script.contains(id)
+                    || technical_name
+                        .as_ref()
+                        .map_or(false, |name| script.contains(name.as_str()))
+                {
+                    references.push(Reference {
+                        id: source.to_owned(),
+                        kind: "script".to_owned(),
+                        path,
+                        expression: Some(script.to_owned()),
+                    });
+                }
+            }
+        }
+
+        Ok(references)
+    }
+
+    // NOTE: Walks up the parents until it hits a Dialogue, yielding its id and display name
+    pub fn enclosing_dialogue(&self, id: &str) -> Option<(String, String)> {
+        let file = self.file.as_deref()?;
+        let mut current = self.index.parent(id)?;
+
+        loop {
+            let model = self.index.find(file, current)?;
+            let json = model_json(model);
+
+            if json["Type"] == "Dialogue" {
+                return Some((
+                    current.to_owned(),
+                    json["Properties"]["DisplayName"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_owned(),
+                ));
+            }
+
+            current = self.index.parent(current)?;
+        }
+    }
+
+    // NOTE: With a folder, only the dialogues somewhere inside of it
+    pub fn dialogues(&self, folder: Option<&str>) -> Result<Vec<&Model>, Error> {
+        let folder = match folder {
+            Some(folder) => folder,
+            None => return self.models_of_type("Dialogue"),
+        };
+
+        let file = self.file()?;
+        let mut dialogues = vec![];
+        let mut pending = self.children(folder)?.to_vec();
+        pending.reverse();
+
+        while let Some(id) = pending.pop() {
+            pending.extend(self.index.children(&id).iter().rev().cloned());
+
+            if let Some(model) = self.index.find(file, &id) {
+                if model_json(model)["Type"] == "Dialogue" {
+                    dialogues.push(model);
+                }
+            }
+        }
+
+        Ok(dialogues)
+    }
+
+    pub fn first_dialogue_fragment_of_dialogue(&self, id: &str) -> Result<&Model, Error> {
+        let dialogue = self.find_model(id)?;
+
+        let fragment_id = self
+            .file()?
+            .get_first_dialogue_fragment_of_dialogue(dialogue)
+            .map_err(Error::ArticyError)?;
+
+        self.find_model(&fragment_id.to_inner())
+    }
+
+    // NOTE: Counted in the current locale, `text` being the localization key when there is one
+    pub fn word_count(&self, text: &str) -> usize {
+        self.localization
+            .read()
+            .expect("localization lock to not be poisoned")
+            .translate(text)
+            .split_whitespace()
+            .count()
+    }
+
+    // NOTE: Words are counted in the current locale, fragments nested in other nodes are included
+    pub fn dialogue_overview(&self, id: &str) -> Result<DialogueOverview, Error> {
+        let file = self.file()?;
+
+        if model_json(self.find_model(id)?)["Type"] != "Dialogue" {
+            return Err(Error::NotADialogue(id.to_owned()));
+        }
+
+        // NOTE: The dialogue's input pins connect to its start nodes, its output pins lead outside of it
+        let start_nodes = self
+            .index
+            .outgoing(id)
+            .iter()
+            .filter(|connection| self.index.parent(&connection.target) == Some(id))
+            .map(|connection| connection.target.to_owned())
+            .collect::<Vec<String>>();
+
+        let mut fragments = vec![];
+        let mut participants = vec![];
+        let mut word_count = 0;
+
+        let mut pending = self.index.children(id).to_vec();
+        pending.reverse();
+        while let Some(node) = pending.pop() {
+            pending.extend(self.index.children(&node).iter().rev().cloned());
+
+            let json = match self.index.find(file, &node) {
+                Some(model) => model_json(model),
+                None => continue,
+            };
+
+            if json["Type"] != "DialogueFragment" {
+                continue;
+            }
+
+            let properties = &json["Properties"];
+            if let Some(speaker) = properties["Speaker"]
+                .as_str()
+                .filter(|speaker| *speaker != "0x0000000000000000")
+            {
+                if !participants
+                    .iter()
+                    .any(|participant| participant == speaker)
+                {
+                    participants.push(speaker.to_owned());
+                }
+            }
+
+            word_count += self.word_count(properties["Text"].as_str().unwrap_or_default());
+
+            fragments.push(node);
+        }
+
+        Ok(DialogueOverview {
+            id: id.to_owned(),
+            start_nodes,
+            fragments,
+            participants,
+            word_count,
+        })
+    }
+}
+
+// NOTE: Strips can hold the ids themselves or objects with an "Id", depending on the export
+fn collect_ids<'a>(value: &'a Value, ids: &mut Vec<&'a str>) {
+    match value {
+        Value::String(id) => ids.push(id),
+        Value::Array(values) => {
+            for value in values {
+                collect_ids(value, ids);
+            }
+        }
+        Value::Object(object) => {
+            if let Some(Value::String(id)) = object.get("Id") {
+                ids.push(id);
+            }
+        }
+        _ => {}
+    }
+}
+
+// NOTE: Collects the dot paths of every string in `value` that's exactly `id`
+fn find_value(value: &Value, path: String, id: &str, found: &mut Vec<String>) {
+    match value {
+        Value::String(string) if string == id => found.push(path),
+        Value::Array(values) => {
+            for (position, value) in values.iter().enumerate() {
+                find_value(value, format!("{path}.{position}"), id, found);
+            }
+        }
+        Value::Object(object) => {
+            for (key, value) in object {
+                find_value(value, format!("{path}.{key}"), id, found);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error::Error;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+pub struct Localization {
+    tables: HashMap<String, HashMap<String, String>>,
+    locale: Option<String>,
+}
+
+impl Localization {
+    // NOTE: Articy exports a table per language keyed by localization id, e.g. `{"DFr_1A2B3C4D.Text": {"": "Hello"}}`
+    pub fn load(&mut self, locale: &str, buffer: &[u8]) -> Result<(), Error> {
+        let json = serde_json::from_slice::<Value>(buffer)
+            .map_err(|error| Error::InvalidResource(format!("localization: {error}")))?;
+
+        let entries = json.as_object().ok_or_else(|| {
+            Error::InvalidResource("localization table isn't a JSON object".to_owned())
+        })?;
+
+        let table = entries
+            .iter()
+            .filter_map(|(key, entry)| {
+                let text = match entry {
+                    Value::String(text) => Some(text.as_str()),
+                    Value::Object(fields) => fields
+                        .get("")
+                        .or_else(|| fields.get("Text"))
+                        .and_then(Value::as_str),
+                    _ => None,
+                };
+
+                text.map(|text| (key.to_owned(), text.to_owned()))
+            })
+            .collect();
+
+        self.tables.insert(locale.to_owned(), table);
+
+        Ok(())
+    }
+
+    pub fn locales(&self) -> Vec<&str> {
+        self.tables.keys().map(String::as_str).collect()
+    }
+
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    // NOTE: An empty locale goes back to the source text of the export
+    pub fn set_locale(&mut self, locale: &str) -> Result<(), Error> {
+        if locale.is_empty() {
+            self.locale = None;
+        } else if self.tables.contains_key(locale) {
+            self.locale = Some(locale.to_owned());
+        } else {
+            return Err(Error::LocaleNotFound(locale.to_owned()));
+        }
+
+        Ok(())
+    }
+
+    // NOTE: Loaded locales without an entry for `key`, sorted so reports come out the same every time
+    pub fn missing(&self, key: &str) -> Vec<&str> {
+        let mut locales = self
+            .tables
+            .iter()
+            .filter(|(_, table)| !table.contains_key(key))
+            .map(|(locale, _)| locale.as_str())
+            .collect::<Vec<&str>>();
+        locales.sort();
+
+        locales
+    }
+
+    pub fn translate<'a>(&'a self, key: &'a str) -> &'a str {
+        self.locale
+            .as_ref()
+            .and_then(|locale| self.tables.get(locale))
+            .and_then(|table| table.get(key))
+            .map_or(key, String::as_str)
+    }
+}
+
+// NOTE: Properties a translator gets to see, keyed as `{id}.{property}`
+pub const LOCALIZABLE_PROPERTIES: [&str; 4] =
+    ["Text", "MenuText", "DisplayName", "StageDirections"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslationEntry {
+    pub key: String,
+    pub text: String,
+}
+
+// NOTE: In the layout Godot imports translation CSVs in, a "keys" column followed by one per locale
+pub fn to_csv(entries: &[TranslationEntry], locale: &str) -> String {
+    let quote = |field: &str| format!("\"{}\"", field.replace('"', "\"\""));
+
+    let mut csv = format!("keys,{locale}\n");
+    for entry in entries {
+        csv.push_str(&format!("{},{}\n", quote(&entry.key), quote(&entry.text)));
+    }
+
+    csv
+}
+
+pub fn to_pot(entries: &[TranslationEntry]) -> String {
+    let quote = |field: &str| {
+        format!(
+            "\"{}\"",
+            field
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        )
+    };
+
+    let mut pot =
+        "msgid \"\"\nmsgstr \"\"\n\"Content-Type: text/plain; charset=UTF-8\\n\"\n".to_owned();
+    for entry in entries {
+        pot.push_str(&format!(
+            "\n#. {}\nmsgctxt {}\nmsgid {}\nmsgstr \"\"\n",
+            entry.key,
+            quote(&entry.key),
+            quote(&entry.text)
+        ));
+    }
+
+    pot
+}
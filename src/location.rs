@@ -0,0 +1,96 @@
+use crate::{error::Error, library::Library, model::model_json};
+use serde_json::Value;
+
+// NOTE: What's drawn on an Articy Location, with positions in Articy's units multiplied by the scale it was read with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub id: String,
+    pub display_name: String,
+    pub zones: Vec<Zone>,
+    pub spots: Vec<Spot>,
+    pub links: Vec<Link>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zone {
+    pub id: String,
+    pub display_name: String,
+    pub points: Vec<[f64; 2]>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spot {
+    pub id: String,
+    pub display_name: String,
+    pub position: [f64; 2],
+}
+
+// NOTE: `source` is the Location or Zone the link is placed in, `target` what it links to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub id: String,
+    pub source: String,
+    pub target: String,
+}
+
+impl Location {
+    // NOTE: Zones, spots and links nested in other zones are included as well
+    pub fn read(library: &Library, id: &str, scale: f64) -> Result<Self, Error> {
+        let json = model_json(library.find_model(id)?);
+        if json["Type"] != "Location" {
+            return Err(Error::NotALocation(id.to_owned()));
+        }
+
+        let mut location = Location {
+            id: id.to_owned(),
+            display_name: string_of(&json["Properties"]["DisplayName"]),
+            zones: vec![],
+            spots: vec![],
+            links: vec![],
+        };
+
+        let mut pending = library.children(id)?.to_vec();
+        pending.reverse();
+
+        while let Some(child) = pending.pop() {
+            let json = model_json(library.find_model(&child)?);
+            let properties = &json["Properties"];
+
+            match json["Type"].as_str() {
+                Some("Zone") => location.zones.push(Zone {
+                    id: child.to_owned(),
+                    display_name: string_of(&properties["DisplayName"]),
+                    points: properties["Vertices"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|vertex| point(vertex, scale))
+                        .collect(),
+                }),
+                Some("Spot") => location.spots.push(Spot {
+                    id: child.to_owned(),
+                    display_name: string_of(&properties["DisplayName"]),
+                    position: point(&properties["Position"], scale).unwrap_or_default(),
+                }),
+                Some("Link") => location.links.push(Link {
+                    id: child.to_owned(),
+                    source: library.parent(&child)?.unwrap_or(id).to_owned(),
+                    target: string_of(&properties["Target"]),
+                }),
+                _ => {}
+            }
+
+            pending.extend(library.children(&child)?.iter().rev().cloned());
+        }
+
+        Ok(location)
+    }
+}
+
+fn point(value: &Value, scale: f64) -> Option<[f64; 2]> {
+    Some([value["x"].as_f64()? * scale, value["y"].as_f64()? * scale])
+}
+
+fn string_of(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_owned()
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+// NOTE: Articy's rich text export is a small HTML document, styled either inline or through a <style> block
+//       keyed by element id. Only the parts BBCode can express are kept, everything else is dropped.
+
+pub fn to_bbcode(text: &str) -> String {
+    if !text.contains('<') {
+        return decode_entities(text);
+    }
+
+    let styles = stylesheet(text);
+    let mut output = String::new();
+    let mut open = Vec::<(String, Vec<&'static str>, Option<String>)>::new();
+    let mut paragraphs = 0;
+    let mut skipping = None::<String>;
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        if skipping.is_none() {
+            output.push_str(&decode_entities(&rest[..start]));
+        }
+
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        };
+
+        let tag = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_start_matches('/').trim_end_matches('/').trim();
+        let name = tag
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if let Some(skipped) = &skipping {
+            if closing && *skipped == name {
+                skipping = None;
+            }
+
+            continue;
+        }
+
+        match (closing, name.as_str()) {
+            (false, "style" | "title" | "head") => skipping = Some(name.clone()),
+            (false, "br") => output.push('\n'),
+            (true, _) => {
+                if let Some(position) = open.iter().rposition(|(open_name, ..)| *open_name == name)
+                {
+                    for (_, closers, color) in open.drain(position..).rev() {
+                        for closer in closers.iter().rev() {
+                            output.push_str(closer);
+                        }
+
+                        if color.is_some() {
+                            output.push_str("[/color]");
+                        }
+                    }
+                }
+            }
+            (false, _) => {
+                if name == "p" {
+                    if paragraphs > 0 {
+                        output.push('\n');
+                    }
+                    paragraphs += 1;
+                }
+
+                let mut declarations = attribute(tag, "id")
+                    .and_then(|id| styles.get(&id))
+                    .cloned()
+                    .unwrap_or_default();
+                declarations.extend(
+                    attribute(tag, "style")
+                        .map(|style| declarations_of(&style))
+                        .unwrap_or_default(),
+                );
+
+                let mut closers = vec![];
+                let mut tags = vec![];
+
+                let mut add = |opener: &'static str, closer: &'static str| {
+                    if !tags.contains(&opener) {
+                        tags.push(opener);
+                        closers.push(closer);
+                    }
+                };
+
+                match name.as_str() {
+                    "b" | "strong" => add("[b]", "[/b]"),
+                    "i" | "em" => add("[i]", "[/i]"),
+                    "u" => add("[u]", "[/u]"),
+                    "s" | "strike" | "del" => add("[s]", "[/s]"),
+                    _ => {}
+                }
+
+                for (property, value) in &declarations {
+                    match (property.as_str(), value.as_str()) {
+                        ("font-weight", "bold" | "bolder" | "600" | "700" | "800" | "900") => {
+                            add("[b]", "[/b]")
+                        }
+                        ("font-style", "italic" | "oblique") => add("[i]", "[/i]"),
+                        ("text-decoration", decoration) if decoration.contains("underline") => {
+                            add("[u]", "[/u]")
+                        }
+                        ("text-decoration", decoration) if decoration.contains("line-through") => {
+                            add("[s]", "[/s]")
+                        }
+                        _ => {}
+                    }
+                }
+
+                let color = declarations
+                    .iter()
+                    .find(|(property, _)| property == "color")
+                    .map(|(_, color)| color.to_owned())
+                    .or_else(|| (name == "font").then(|| attribute(tag, "color")).flatten());
+
+                if let Some(color) = &color {
+                    output.push_str(&format!("[color={color}]"));
+                }
+
+                for opener in &tags {
+                    output.push_str(opener);
+                }
+
+                if !self_closing {
+                    open.push((name.clone(), closers, color));
+                }
+            }
+        }
+    }
+
+    if skipping.is_none() {
+        output.push_str(&decode_entities(rest));
+    }
+
+    for (_, closers, color) in open.into_iter().rev() {
+        for closer in closers.iter().rev() {
+            output.push_str(closer);
+        }
+
+        if color.is_some() {
+            output.push_str("[/color]");
+        }
+    }
+
+    output.trim().to_owned()
+}
+
+fn stylesheet(text: &str) -> HashMap<String, Vec<(String, String)>> {
+    let mut styles = HashMap::new();
+
+    let lower = text.to_ascii_lowercase();
+    let (start, end) = match (lower.find("<style"), lower.find("</style>")) {
+        (Some(start), Some(end)) if start < end => (start, end),
+        _ => return styles,
+    };
+
+    let block = &text[start..end];
+    let block = &block[block.find('>').map_or(0, |end| end + 1)..];
+
+    for rule in block.split('}') {
+        if let Some((selectors, declarations)) = rule.split_once('{') {
+            let declarations = declarations_of(declarations);
+
+            for selector in selectors.split(',') {
+                if let Some(id) = selector.trim().strip_prefix('#') {
+                    styles.insert(id.to_owned(), declarations.clone());
+                }
+            }
+        }
+    }
+
+    styles
+}
+
+fn declarations_of(style: &str) -> Vec<(String, String)> {
+    style
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .map(|(property, value)| {
+            (
+                property.trim().to_lowercase(),
+                value.trim().trim_matches('\'').trim_matches('"').to_owned(),
+            )
+        })
+        .collect()
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search = 0;
+
+    while let Some(found) = lower[search..].find(name) {
+        let position = search + found;
+        search = position + name.len();
+
+        let is_start = position == 0
+            || lower[..position]
+                .chars()
+                .last()
+                .map_or(true, char::is_whitespace);
+        let value = tag[search..].trim_start();
+
+        if let (true, Some(value)) = (is_start, value.strip_prefix('=')) {
+            let value = value.trim_start();
+
+            return Some(match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => value[1..]
+                    .split(quote)
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned(),
+                _ => value
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned(),
+            });
+        }
+    }
+
+    None
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_owned();
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", "\u{a0}")
+        .replace("&amp;", "&")
+}
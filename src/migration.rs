@@ -0,0 +1,96 @@
+use crate::{
+    library::SharedLibrary,
+    model::{self, Lookup},
+    session::SavedState,
+};
+use std::collections::HashMap;
+
+// NOTE: What the ids in a save are known by besides the id itself, as (id, technical name, external id)
+pub fn references(library: &SharedLibrary, saved: &SavedState) -> Vec<(String, String, String)> {
+    let ids = saved
+        .cursor
+        .iter()
+        .chain(saved.visited.iter().map(|(id, _)| id))
+        .chain(saved.overrides.iter().map(|(id, _, _)| id));
+
+    let mut references = ids
+        .filter_map(|id| {
+            let model = library.index.find(&library.file, id)?;
+            let technical_name = model::model_json(model)["Properties"]["TechnicalName"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned();
+
+            Some((
+                id.to_owned(),
+                technical_name,
+                model.external_id().to_inner(),
+            ))
+        })
+        .collect::<Vec<(String, String, String)>>();
+
+    references.sort();
+    references.dedup();
+    references
+}
+
+// NOTE: Ids that no longer exist in the export are swapped for the model with the same technical name, or failing
+//       that the same external id. The ones that can't be found either are dropped and given back along with what
+//       they were saved as, "cursor", "visited" or "override"
+pub fn migrate(library: &SharedLibrary, saved: &mut SavedState) -> Vec<(String, &'static str)> {
+    let references = saved
+        .references
+        .iter()
+        .map(|(id, technical_name, external_id)| {
+            (
+                id.to_owned(),
+                (technical_name.to_owned(), external_id.to_owned()),
+            )
+        })
+        .collect::<HashMap<String, (String, String)>>();
+
+    let remap = |id: &str| {
+        if library.index.find(&library.file, id).is_some() {
+            return Some(id.to_owned());
+        }
+
+        let (technical_name, external_id) = references.get(id)?;
+        let position = Some(technical_name)
+            .filter(|technical_name| !technical_name.is_empty())
+            .and_then(|technical_name| library.index.get(Lookup::TechnicalName, technical_name))
+            .or_else(|| {
+                Some(external_id)
+                    .filter(|external_id| !external_id.is_empty() && *external_id != "0")
+                    .and_then(|external_id| library.index.get(Lookup::ExternalId, external_id))
+            });
+
+        model::model_at(&library.file, position, None).map(|model| model.id().to_inner())
+    };
+
+    let mut unmapped = vec![];
+
+    if let Some(cursor) = saved.cursor.take() {
+        match remap(&cursor) {
+            Some(id) => saved.cursor = Some(id),
+            None => unmapped.push((cursor, "cursor")),
+        }
+    }
+
+    for (id, count) in std::mem::take(&mut saved.visited) {
+        match remap(&id) {
+            Some(id) => saved.visited.push((id, count)),
+            None => unmapped.push((id, "visited")),
+        }
+    }
+
+    for (id, property, value) in std::mem::take(&mut saved.overrides) {
+        match remap(&id) {
+            Some(id) => saved.overrides.push((id, property, value)),
+            None if unmapped.contains(&(id.to_owned(), "override")) => {}
+            None => unmapped.push((id, "override")),
+        }
+    }
+
+    saved.references = references(library, saved);
+    unmapped
+}
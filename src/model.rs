@@ -0,0 +1,443 @@
+use articy::types::{File as ArticyFile, Model};
+use serde_json::Value;
+use std::{collections::HashMap, mem::size_of};
+
+// NOTE: Positions are (package, model) pairs into `ArticyFile::packages`
+pub type Position = (usize, usize);
+
+#[derive(Debug, Clone, Copy)]
+pub enum Lookup {
+    Id,
+    ExternalId,
+    TechnicalName,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModelIndex {
+    by_id: HashMap<String, Position>,
+    by_external_id: HashMap<String, Position>,
+    by_technical_name: HashMap<String, Position>,
+    by_display_name: HashMap<String, Vec<Position>>,
+    by_speaker: HashMap<String, Vec<Position>>,
+    // NOTE: In export order, as packages are added in order and models in them too
+    by_type: HashMap<String, Vec<Position>>,
+    parents: HashMap<String, String>,
+    children: HashMap<String, Vec<String>>,
+    outgoing: HashMap<String, Vec<Connection>>,
+    incoming: HashMap<String, Vec<Connection>>,
+    texts: Vec<(Position, &'static str, String)>,
+    summaries: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+    pub source: String,
+    pub source_pin: String,
+    pub target: String,
+    pub target_pin: String,
+    pub label: String,
+}
+
+// NOTE: Properties holding text a player or writer would recognise a model by
+const SEARCHABLE_PROPERTIES: [&str; 3] = ["Text", "MenuText", "DisplayName"];
+
+// NOTE: Left out of the cached summaries, articy-rs has them as fields on the model already
+const SUMMARY_OMITTED_PROPERTIES: [&str; 2] = ["Text", "MenuText"];
+
+impl ModelIndex {
+    pub fn build(file: &ArticyFile) -> Self {
+        let mut index = ModelIndex::default();
+
+        for package_position in 0..file.packages.len() {
+            index.add_package(file, package_position);
+        }
+
+        index
+    }
+
+    // NOTE: For a package parsed after the rest of the file, e.g. by a lazy load
+    pub fn add_package(&mut self, file: &ArticyFile, package_position: usize) {
+        if let Some(package) = file.packages.get(package_position) {
+            for (model_position, model) in package.models.iter().enumerate() {
+                let position = (package_position, model_position);
+
+                let id = model.id().to_inner();
+                let json = model_json(model);
+                let properties = &json["Properties"];
+
+                self.by_id.insert(id.to_owned(), position);
+                self.by_external_id
+                    .insert(model.external_id().to_inner(), position);
+
+                if let Some(technical_name) = properties["TechnicalName"].as_str() {
+                    self.by_technical_name
+                        .insert(technical_name.to_owned(), position);
+                }
+
+                // NOTE: Display names aren't unique, e.g. every "Guard" in the project
+                if let Some(display_name) = properties["DisplayName"]
+                    .as_str()
+                    .filter(|display_name| !display_name.is_empty())
+                {
+                    self.by_display_name
+                        .entry(display_name.to_owned())
+                        .or_default()
+                        .push(position);
+                }
+
+                if let Some(kind) = json["Type"].as_str() {
+                    self.by_type
+                        .entry(kind.to_owned())
+                        .or_default()
+                        .push(position);
+                }
+
+                if let (true, Some(speaker)) = (
+                    json["Type"] == "DialogueFragment",
+                    properties["Speaker"].as_str(),
+                ) {
+                    self.by_speaker
+                        .entry(speaker.to_owned())
+                        .or_default()
+                        .push(position);
+                }
+
+                if let Some(parent) = properties["Parent"].as_str() {
+                    self.parents.insert(id.to_owned(), parent.to_owned());
+                    self.children
+                        .entry(parent.to_owned())
+                        .or_default()
+                        .push(id.to_owned());
+                }
+
+                for pin in properties["OutputPins"].as_array().into_iter().flatten() {
+                    for connection in pin["Connections"].as_array().into_iter().flatten() {
+                        let connection = Connection {
+                            source: id.to_owned(),
+                            source_pin: pin["Id"].as_str().unwrap_or_default().to_owned(),
+                            target: connection["Target"].as_str().unwrap_or_default().to_owned(),
+                            target_pin: connection["TargetPin"]
+                                .as_str()
+                                .unwrap_or_default()
+                                .to_owned(),
+                            label: connection["Label"].as_str().unwrap_or_default().to_owned(),
+                        };
+
+                        self.incoming
+                            .entry(connection.target.to_owned())
+                            .or_default()
+                            .push(connection.clone());
+                        self.outgoing
+                            .entry(id.to_owned())
+                            .or_default()
+                            .push(connection);
+                    }
+                }
+
+                for property in SEARCHABLE_PROPERTIES {
+                    if let Some(text) = properties[property]
+                        .as_str()
+                        .filter(|text| !text.is_empty())
+                    {
+                        self.texts.push((position, property, text.to_owned()));
+                    }
+                }
+
+                self.summaries.insert(id, summarize(json));
+            }
+        }
+    }
+
+    pub fn get(&self, lookup: Lookup, key: &str) -> Option<&Position> {
+        match lookup {
+            Lookup::Id => self.by_id.get(key),
+            Lookup::ExternalId => self.by_external_id.get(key),
+            Lookup::TechnicalName => self.by_technical_name.get(key),
+        }
+    }
+
+    pub fn by_display_name(&self, display_name: &str) -> &[Position] {
+        self.by_display_name
+            .get(display_name)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn by_speaker(&self, speaker: &str) -> &[Position] {
+        self.by_speaker.get(speaker).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn by_type(&self, kind: &str) -> &[Position] {
+        self.by_type.get(kind).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn parent(&self, id: &str) -> Option<&str> {
+        self.parents.get(id).map(String::as_str)
+    }
+
+    pub fn children(&self, id: &str) -> &[String] {
+        self.children.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn outgoing(&self, id: &str) -> &[Connection] {
+        self.outgoing.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn incoming(&self, id: &str) -> &[Connection] {
+        self.incoming.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    // NOTE: Case-insensitive, yields the position, property name and full text of every match
+    pub fn search<'a>(
+        &'a self,
+        query: &'a str,
+    ) -> impl Iterator<Item = (&'a Position, &'static str, &'a str)> + 'a {
+        let query = query.to_lowercase();
+
+        self.texts
+            .iter()
+            .filter(move |(_, _, text)| text.to_lowercase().contains(&query))
+            .map(|(position, property, text)| (position, *property, text.as_str()))
+    }
+
+    // NOTE: The model's JSON without its lines or pin connections, kept around for the
+    //       property lookups done while a session steps through the flow
+    pub fn summary(&self, id: &str) -> Option<&Value> {
+        self.summaries.get(id)
+    }
+
+    pub fn kind(&self, id: &str) -> &str {
+        self.summary(id)
+            .and_then(|json| json["Type"].as_str())
+            .unwrap_or_default()
+    }
+
+    pub fn find<'a>(&self, file: &'a ArticyFile, id: &str) -> Option<&'a Model> {
+        model_at(file, self.by_id.get(id), None)
+    }
+
+    pub fn sizes(&self) -> Vec<(&'static str, usize)> {
+        let connections =
+            |map: &HashMap<String, Vec<Connection>>| map.values().map(Vec::len).sum::<usize>();
+
+        vec![
+            ("ids", self.by_id.len()),
+            ("external_ids", self.by_external_id.len()),
+            ("technical_names", self.by_technical_name.len()),
+            ("display_names", self.by_display_name.len()),
+            ("speakers", self.by_speaker.len()),
+            ("types", self.by_type.len()),
+            ("parents", self.parents.len()),
+            ("connections", connections(&self.outgoing)),
+            ("texts", self.texts.len()),
+            ("summaries", self.summaries.len()),
+        ]
+    }
+
+    // NOTE: Counts the strings and entries held, not what the maps allocate on top, so it's on the low side
+    pub fn estimated_bytes(&self) -> usize {
+        let key = |key: &String| size_of::<String>() + key.len();
+        let positions = |map: &HashMap<String, Position>| {
+            map.keys()
+                .map(|id| key(id) + size_of::<Position>())
+                .sum::<usize>()
+        };
+        let lists = |map: &HashMap<String, Vec<Position>>| {
+            map.iter()
+                .map(|(id, list)| {
+                    key(id) + size_of::<Vec<Position>>() + list.len() * size_of::<Position>()
+                })
+                .sum::<usize>()
+        };
+        let connections = |map: &HashMap<String, Vec<Connection>>| {
+            map.iter()
+                .map(|(id, list)| {
+                    key(id)
+                        + list
+                            .iter()
+                            .map(|connection| {
+                                size_of::<Connection>()
+                                    + connection.source.len()
+                                    + connection.source_pin.len()
+                                    + connection.target.len()
+                                    + connection.target_pin.len()
+                                    + connection.label.len()
+                            })
+                            .sum::<usize>()
+                })
+                .sum::<usize>()
+        };
+
+        positions(&self.by_id)
+            + positions(&self.by_external_id)
+            + positions(&self.by_technical_name)
+            + lists(&self.by_display_name)
+            + lists(&self.by_speaker)
+            + lists(&self.by_type)
+            + self
+                .parents
+                .iter()
+                .map(|(id, parent)| key(id) + key(parent))
+                .sum::<usize>()
+            + self
+                .children
+                .iter()
+                .map(|(id, children)| key(id) + children.iter().map(key).sum::<usize>())
+                .sum::<usize>()
+            + connections(&self.outgoing)
+            + connections(&self.incoming)
+            + self
+                .texts
+                .iter()
+                .map(|(_, _, text)| size_of::<(Position, &str, String)>() + text.len())
+                .sum::<usize>()
+            + self
+                .summaries
+                .iter()
+                .map(|(id, json)| key(id) + json.to_string().len())
+                .sum::<usize>()
+    }
+}
+
+// NOTE: A `package` of `None` means the model can be in any package
+pub fn model_at<'a>(
+    file: &'a ArticyFile,
+    position: Option<&Position>,
+    package: Option<usize>,
+) -> Option<&'a Model> {
+    let (package_position, model_position) = *position?;
+
+    if package.map_or(true, |package| package == package_position) {
+        file.packages
+            .get(package_position)?
+            .models
+            .get(model_position)
+    } else {
+        None
+    }
+}
+
+fn summarize(mut json: Value) -> Value {
+    if let Some(properties) = json["Properties"].as_object_mut() {
+        for property in SUMMARY_OMITTED_PROPERTIES {
+            properties.remove(property);
+        }
+
+        for pins in ["InputPins", "OutputPins"] {
+            for pin in properties
+                .get_mut(pins)
+                .and_then(Value::as_array_mut)
+                .into_iter()
+                .flatten()
+            {
+                if let Some(pin) = pin.as_object_mut() {
+                    pin.remove("Connections");
+                }
+            }
+        }
+    }
+
+    json
+}
+
+// TODO: Maybe replace Type / Properties with a flat "Properties" dictionary with a "type" key
+pub fn model_value(model: &Model) -> Value {
+    match model {
+        Model::Custom(kind, value) => serde_json::json!({"type": kind, "properties": value}),
+        _ => serde_json::to_value(model).expect("articy-rs to produce proper JSON"),
+    }
+}
+
+// NOTE: Gives every model the shape Articy exports it in: `{"Type": ..., "Properties": {...}}`
+pub fn model_json(model: &Model) -> Value {
+    match model {
+        Model::Custom(kind, value) => serde_json::json!({"Type": kind, "Properties": value}),
+        _ => serde_json::to_value(model).expect("articy-rs to produce proper JSON"),
+    }
+}
+
+pub fn model_property(model: &Model, key: &str) -> Option<Value> {
+    model_json(model)
+        .get_mut("Properties")?
+        .get_mut(key)
+        .map(Value::take)
+}
+
+// NOTE: Paths start at the top of the model (e.g. "Template.QuestFeature.Reward"), or at its properties ("DisplayName")
+pub fn property_at(mut json: Value, path: &str) -> Option<Value> {
+    let first = path.split('.').next()?;
+    if json.get(first).is_none() {
+        json = json.get_mut("Properties")?.take();
+    }
+
+    path.split('.')
+        .try_fold(&mut json, |value, key| match value {
+            Value::Array(values) => values.get_mut(key.parse::<usize>().ok()?),
+            value => value.get_mut(key),
+        })
+        .map(Value::take)
+}
+
+// NOTE: The script on one of the pins in a model's JSON, `pins` being either "InputPins" or "OutputPins"
+pub fn pin_script(json: &Value, pins: &str, pin: &str) -> Option<String> {
+    json["Properties"][pins]
+        .as_array()?
+        .iter()
+        .find(|candidate| candidate["Id"].as_str() == Some(pin))?["Text"]
+        .as_str()
+        .filter(|text| !text.trim().is_empty())
+        .map(str::to_owned)
+}
+
+// NOTE: Looks in the model's own properties first, then in every feature of its template
+pub fn number_property(json: &Value, key: &str) -> Option<f64> {
+    json["Properties"][key].as_f64().or_else(|| {
+        json["Template"]
+            .as_object()?
+            .values()
+            .find_map(|feature| feature[key].as_f64())
+    })
+}
+
+pub fn bool_property(json: &Value, key: &str) -> Option<bool> {
+    json["Properties"][key].as_bool().or_else(|| {
+        json["Template"]
+            .as_object()?
+            .values()
+            .find_map(|feature| feature[key].as_bool())
+    })
+}
+
+// NOTE: Same lookup as `number_property`, skipping empty strings and Articy's null reference
+pub fn string_property(json: &Value, key: &str) -> Option<String> {
+    let is_set = |value: &&str| !value.is_empty() && *value != "0x0000000000000000";
+
+    json["Properties"][key]
+        .as_str()
+        .filter(is_set)
+        .or_else(|| {
+            json["Template"]
+                .as_object()?
+                .values()
+                .find_map(|feature| feature[key].as_str().filter(is_set))
+        })
+        .map(str::to_owned)
+}
+
+pub fn json_color(value: &Value) -> Option<[f32; 4]> {
+    if !value
+        .as_object()?
+        .keys()
+        .all(|key| ["r", "g", "b", "a"].contains(&key.as_str()))
+    {
+        return None;
+    }
+
+    let channel = |name: &str| value.get(name).and_then(Value::as_f64);
+
+    Some([
+        channel("r")? as f32,
+        channel("g")? as f32,
+        channel("b")? as f32,
+        channel("a").unwrap_or(1.0) as f32,
+    ])
+}
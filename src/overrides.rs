@@ -0,0 +1,65 @@
+use crate::{model, session::Speaker};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+// NOTE: Entity properties changed while playing, e.g. a character that got renamed by the player. Only the
+//       `DisplayName` and `Color` of speakers are picked up by sessions, the rest is kept for the game to read back.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntityOverrides {
+    entities: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+impl EntityOverrides {
+    // NOTE: Setting null takes the override away again
+    pub fn set(&mut self, id: &str, property: &str, value: Value) {
+        if value.is_null() {
+            if let Some(properties) = self.entities.get_mut(id) {
+                properties.remove(property);
+
+                if properties.is_empty() {
+                    self.entities.remove(id);
+                }
+            }
+
+            return;
+        }
+
+        self.entities
+            .entry(id.to_owned())
+            .or_default()
+            .insert(property.to_owned(), value);
+    }
+
+    pub fn get(&self, id: &str, property: &str) -> Option<&Value> {
+        self.entities.get(id)?.get(property)
+    }
+
+    pub fn entries(&self) -> Vec<(String, String, Value)> {
+        self.entities
+            .iter()
+            .flat_map(|(id, properties)| {
+                properties
+                    .iter()
+                    .map(|(property, value)| (id.to_owned(), property.to_owned(), value.clone()))
+            })
+            .collect()
+    }
+
+    pub fn replace(&mut self, entries: &[(String, String, Value)]) {
+        self.entities.clear();
+
+        for (id, property, value) in entries {
+            self.set(id, property, value.clone());
+        }
+    }
+
+    pub fn apply(&self, id: &str, speaker: &mut Speaker) {
+        if let Some(name) = self.get(id, "DisplayName").and_then(Value::as_str) {
+            speaker.name = Some(name.to_owned());
+        }
+
+        if let Some(color) = self.get(id, "Color").and_then(model::json_color) {
+            speaker.color = Some(color);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{
+    error::Error,
+    library::Library,
+    model::{self, model_json},
+};
+use articy::types::Model;
+use serde_json::Value;
+
+// NOTE: Every filter that's set has to match, a model passes the ones left out
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    pub kinds: Vec<String>,
+    pub template: Option<String>,
+    pub folder: Option<String>,
+    pub speaker: Option<String>,
+    pub properties: Vec<(String, Condition)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Equals(Value),
+    // NOTE: Both ends are inclusive, a property that isn't a number never falls in range
+    Range(Option<f64>, Option<f64>),
+}
+
+impl Query {
+    // NOTE: `{"type": "Entity" or ["Entity", ...], "template": "Character", "folder": id, "speaker": id,
+    //       "properties": {path: value or {"min": number, "max": number}}}`, paths as in `model::property_at`
+    pub fn from_json(json: &Value) -> Result<Self, Error> {
+        let filters = json.as_object().ok_or_else(|| {
+            Error::InvalidOption("query filter has to be a Dictionary".to_owned())
+        })?;
+
+        let mut query = Query::default();
+        for (key, value) in filters {
+            match key.as_str() {
+                "type" => {
+                    query.kinds = match value {
+                        Value::Array(kinds) => kinds.iter().map(string_of).collect(),
+                        kind => vec![string_of(kind)],
+                    }
+                }
+                "template" => query.template = Some(string_of(value)),
+                "folder" => query.folder = Some(string_of(value)),
+                "speaker" => query.speaker = Some(string_of(value)),
+                "properties" => {
+                    for (path, condition) in value.as_object().into_iter().flatten() {
+                        query
+                            .properties
+                            .push((path.to_owned(), Condition::from_json(condition)));
+                    }
+                }
+                other => return Err(Error::InvalidOption(format!("query filter {other}"))),
+            }
+        }
+
+        Ok(query)
+    }
+
+    // NOTE: Models come back in the order they're in the export
+    pub fn run<'a>(&self, library: &'a Library) -> Result<Vec<&'a Model>, Error> {
+        let models = match self.kinds.is_empty() {
+            true => library.all_models()?,
+            false => library.models_of_types(&self.kinds, 0, usize::MAX)?,
+        };
+
+        Ok(models
+            .into_iter()
+            .filter(|model| self.matches(library, model))
+            .collect())
+    }
+
+    fn matches(&self, library: &Library, model: &Model) -> bool {
+        // NOTE: Anywhere inside the folder, not just directly in it
+        if let Some(folder) = &self.folder {
+            let mut parent = library.parent(&model.id().to_inner()).ok().flatten();
+            while let Some(current) = parent.filter(|current| current != folder) {
+                parent = library.parent(current).ok().flatten();
+            }
+
+            if parent.is_none() {
+                return false;
+            }
+        }
+
+        let json = model_json(model);
+
+        if let Some(template) = &self.template {
+            let has_template = json["Type"] == *template
+                || json["Template"]
+                    .as_object()
+                    .map_or(false, |features| features.contains_key(template));
+
+            if !has_template {
+                return false;
+            }
+        }
+
+        if let Some(speaker) = &self.speaker {
+            if json["Properties"]["Speaker"] != *speaker {
+                return false;
+            }
+        }
+
+        self.properties.iter().all(|(path, condition)| {
+            model::property_at(json.clone(), path).map_or(false, |value| condition.holds(&value))
+        })
+    }
+}
+
+impl Condition {
+    fn from_json(json: &Value) -> Self {
+        match json.as_object() {
+            Some(range)
+                if !range.is_empty() && range.keys().all(|key| key == "min" || key == "max") =>
+            {
+                Condition::Range(
+                    range.get("min").and_then(Value::as_f64),
+                    range.get("max").and_then(Value::as_f64),
+                )
+            }
+            _ => Condition::Equals(json.to_owned()),
+        }
+    }
+
+    fn holds(&self, value: &Value) -> bool {
+        match self {
+            Condition::Equals(expected) => value == expected,
+            Condition::Range(min, max) => value.as_f64().map_or(false, |number| {
+                min.map_or(true, |min| number >= min) && max.map_or(true, |max| number <= max)
+            }),
+        }
+    }
+}
+
+fn string_of(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.to_owned(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{error::Error, library::Library, model::model_json};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuestState {
+    #[default]
+    Inactive,
+    Active,
+    Completed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quest {
+    pub id: String,
+    pub technical_name: String,
+    pub display_name: String,
+    pub state: QuestState,
+    pub stage: Option<String>,
+}
+
+// NOTE: Quests are FlowFragments using a given template, their stages the nodes inside of them
+#[derive(Debug, Default)]
+pub struct QuestLog {
+    quests: Vec<Quest>,
+    owners: HashMap<String, usize>,
+    endings: HashSet<String>,
+}
+
+impl QuestState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuestState::Inactive => "inactive",
+            QuestState::Active => "active",
+            QuestState::Completed => "completed",
+        }
+    }
+}
+
+impl QuestLog {
+    // NOTE: `template` matches either the exported type of the fragment or one of its template's features
+    pub fn scan(library: &Library, template: &str) -> Result<Self, Error> {
+        let mut log = QuestLog::default();
+
+        for model in library.all_models()? {
+            let json = model_json(model);
+            let is_quest = json["Type"] == template
+                || json["Template"]
+                    .as_object()
+                    .map_or(false, |features| features.contains_key(template));
+
+            if !is_quest {
+                continue;
+            }
+
+            let id = model.id().to_inner();
+            let position = log.quests.len();
+
+            // NOTE: A stage ends the quest when nothing follows it but the fragment's own output
+            let mut inner = library.children(&id)?.to_vec();
+            while let Some(child) = inner.pop() {
+                let outgoing = library.outgoing_connections(&child)?;
+                if outgoing.iter().all(|connection| connection.target == id) {
+                    log.endings.insert(child.to_owned());
+                }
+
+                inner.extend(library.children(&child)?.iter().cloned());
+                log.owners.insert(child, position);
+            }
+
+            let properties = &json["Properties"];
+            log.quests.push(Quest {
+                id,
+                technical_name: properties["TechnicalName"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned(),
+                display_name: properties["DisplayName"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned(),
+                state: QuestState::Inactive,
+                stage: None,
+            });
+        }
+
+        Ok(log)
+    }
+
+    pub fn quests(&self) -> &[Quest] {
+        &self.quests
+    }
+
+    pub fn quest(&self, id: &str) -> Option<&Quest> {
+        self.quests.iter().find(|quest| quest.id == id)
+    }
+
+    // NOTE: Gives back the quest when passing `id` moved it along
+    pub fn pass(&mut self, id: &str) -> Option<&Quest> {
+        let quest = &mut self.quests[*self.owners.get(id)?];
+
+        if quest.state == QuestState::Completed || quest.stage.as_deref() == Some(id) {
+            return None;
+        }
+
+        quest.stage = Some(id.to_owned());
+        quest.state = if self.endings.contains(id) {
+            QuestState::Completed
+        } else {
+            QuestState::Active
+        };
+
+        Some(quest)
+    }
+}
@@ -0,0 +1,600 @@
+use crate::error::Error;
+use serde_json::{Number, Value};
+
+// NOTE: A small evaluator for Articy's expresso scripts (conditions and instructions), used for the
+// parts of a script articy-rs doesn't know how to run itself, like calls into the game
+
+pub trait Environment {
+    fn get(&mut self, name: &str) -> Result<Value, Error>;
+    fn set(&mut self, name: &str, value: Value) -> Result<(), Error>;
+    fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Value, Error>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Not,
+    Negate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Literal(Value),
+    Variable(String),
+    Call(String, Vec<Expression>),
+    Unary(Operator, Box<Expression>),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Assign(String, Option<Operator>, Expression),
+    Expression(Expression),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Number),
+    String(String),
+    Identifier(String),
+    Symbol(&'static str),
+}
+
+// NOTE: Ordered so that longer symbols match before their prefixes
+const SYMBOLS: [&str; 23] = [
+    "==", "!=", "<=", ">=", "&&", "||", "+=", "-=", "*=", "/=", "+", "-", "*", "/", "%", "<", ">",
+    "=", "!", "(", ")", ",", ";",
+];
+
+pub fn parse(script: &str) -> Result<Vec<Statement>, Error> {
+    let mut parser = Parser::new(script)?;
+    let mut statements = vec![];
+
+    while !parser.is_done() {
+        if parser.eat(";") {
+            continue;
+        }
+
+        statements.push(parser.statement()?);
+
+        if !parser.is_done() {
+            parser.expect(";")?;
+        }
+    }
+
+    Ok(statements)
+}
+
+pub fn parse_expression(expression: &str) -> Result<Expression, Error> {
+    let mut parser = Parser::new(expression)?;
+    let expression = parser.expression()?;
+
+    if !parser.is_done() {
+        return Err(parser.unexpected());
+    }
+
+    Ok(expression)
+}
+
+pub fn execute(script: &str, environment: &mut impl Environment) -> Result<(), Error> {
+    for statement in parse(script)? {
+        statement.execute(environment)?;
+    }
+
+    Ok(())
+}
+
+pub fn evaluate(expression: &str, environment: &mut impl Environment) -> Result<Value, Error> {
+    // NOTE: Articy treats an empty condition as always passing
+    if expression.trim().is_empty() {
+        return Ok(Value::Bool(true));
+    }
+
+    parse_expression(expression)?.evaluate(environment)
+}
+
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(bool) => *bool,
+        Value::Number(number) => number.as_f64().map_or(false, |number| number != 0.0),
+        Value::String(string) => !string.is_empty(),
+        Value::Array(array) => !array.is_empty(),
+        Value::Object(object) => !object.is_empty(),
+    }
+}
+
+impl Statement {
+    pub fn execute(&self, environment: &mut impl Environment) -> Result<(), Error> {
+        match self {
+            Statement::Assign(name, operator, expression) => {
+                let value = expression.evaluate(environment)?;
+                let value = match operator {
+                    Some(operator) => binary(*operator, environment.get(name)?, value)?,
+                    None => value,
+                };
+
+                environment.set(name, value)
+            }
+            Statement::Expression(expression) => expression.evaluate(environment).map(|_| ()),
+        }
+    }
+
+    pub fn functions(&self) -> Vec<&str> {
+        let mut functions = vec![];
+        match self {
+            Statement::Assign(_, _, expression) | Statement::Expression(expression) => {
+                expression.collect_functions(&mut functions)
+            }
+        }
+
+        functions
+    }
+
+    // NOTE: Assigned variables included
+    pub fn variables(&self) -> Vec<&str> {
+        let mut variables = vec![];
+        match self {
+            Statement::Assign(name, _, expression) => {
+                variables.push(name.as_str());
+                expression.collect_variables(&mut variables)
+            }
+            Statement::Expression(expression) => expression.collect_variables(&mut variables),
+        }
+
+        variables
+    }
+}
+
+impl Expression {
+    pub fn evaluate(&self, environment: &mut impl Environment) -> Result<Value, Error> {
+        match self {
+            Expression::Literal(value) => Ok(value.clone()),
+            Expression::Variable(name) => environment.get(name),
+            Expression::Call(name, arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(environment))
+                    .collect::<Result<Vec<Value>, Error>>()?;
+
+                environment.call(name, arguments)
+            }
+            Expression::Unary(Operator::Not, operand) => {
+                Ok(Value::Bool(!is_truthy(&operand.evaluate(environment)?)))
+            }
+            Expression::Unary(_, operand) => binary(
+                Operator::Subtract,
+                Value::from(0),
+                operand.evaluate(environment)?,
+            ),
+            // NOTE: Short-circuit so calls on the right hand side only run when needed
+            Expression::Binary(left, Operator::And, right) => Ok(Value::Bool(
+                is_truthy(&left.evaluate(environment)?) && is_truthy(&right.evaluate(environment)?),
+            )),
+            Expression::Binary(left, Operator::Or, right) => Ok(Value::Bool(
+                is_truthy(&left.evaluate(environment)?) || is_truthy(&right.evaluate(environment)?),
+            )),
+            Expression::Binary(left, operator, right) => {
+                let left = left.evaluate(environment)?;
+                let right = right.evaluate(environment)?;
+
+                binary(*operator, left, right)
+            }
+        }
+    }
+
+    pub fn functions(&self) -> Vec<&str> {
+        let mut functions = vec![];
+        self.collect_functions(&mut functions);
+
+        functions
+    }
+
+    fn collect_functions<'a>(&'a self, functions: &mut Vec<&'a str>) {
+        match self {
+            Expression::Literal(..) | Expression::Variable(..) => {}
+            Expression::Call(name, arguments) => {
+                functions.push(name);
+                for argument in arguments {
+                    argument.collect_functions(functions);
+                }
+            }
+            Expression::Unary(_, operand) => operand.collect_functions(functions),
+            Expression::Binary(left, _, right) => {
+                left.collect_functions(functions);
+                right.collect_functions(functions);
+            }
+        }
+    }
+
+    pub fn variables(&self) -> Vec<&str> {
+        let mut variables = vec![];
+        self.collect_variables(&mut variables);
+
+        variables
+    }
+
+    fn collect_variables<'a>(&'a self, variables: &mut Vec<&'a str>) {
+        match self {
+            Expression::Literal(..) => {}
+            Expression::Variable(name) => variables.push(name),
+            Expression::Call(_, arguments) => {
+                for argument in arguments {
+                    argument.collect_variables(variables);
+                }
+            }
+            Expression::Unary(_, operand) => operand.collect_variables(variables),
+            Expression::Binary(left, _, right) => {
+                left.collect_variables(variables);
+                right.collect_variables(variables);
+            }
+        }
+    }
+}
+
+fn binary(operator: Operator, left: Value, right: Value) -> Result<Value, Error> {
+    use Operator::*;
+
+    Ok(match (operator, &left, &right) {
+        (Equal, ..) => Value::Bool(equals(&left, &right)),
+        (NotEqual, ..) => Value::Bool(!equals(&left, &right)),
+        (Add, Value::String(left), right) => Value::String(format!("{left}{}", plain(right))),
+        (Add, left, Value::String(right)) => Value::String(format!("{}{right}", plain(left))),
+        (
+            Less | LessOrEqual | Greater | GreaterOrEqual,
+            Value::String(left),
+            Value::String(right),
+        ) => Value::Bool(compare(operator, left.cmp(right))),
+        (_, Value::Number(left), Value::Number(right)) => arithmetic(operator, left, right)?,
+        _ => {
+            return Err(script_error(format!(
+                "can't apply {operator:?} to {left} and {right}"
+            )))
+        }
+    })
+}
+
+fn arithmetic(operator: Operator, left: &Number, right: &Number) -> Result<Value, Error> {
+    use Operator::*;
+
+    if let (Some(left), Some(right)) = (left.as_i64(), right.as_i64()) {
+        return Ok(match operator {
+            Add => Value::from(left.wrapping_add(right)),
+            Subtract => Value::from(left.wrapping_sub(right)),
+            Multiply => Value::from(left.wrapping_mul(right)),
+            Divide | Remainder if right == 0 => {
+                return Err(script_error("division by zero".to_owned()))
+            }
+            Divide => Value::from(left / right),
+            Remainder => Value::from(left % right),
+            _ => Value::Bool(compare(operator, left.cmp(&right))),
+        });
+    }
+
+    let (left, right) = (
+        left.as_f64().unwrap_or_default(),
+        right.as_f64().unwrap_or_default(),
+    );
+
+    let result = match operator {
+        Add => left + right,
+        Subtract => left - right,
+        Multiply => left * right,
+        Divide => left / right,
+        Remainder => left % right,
+        _ => {
+            return Ok(Value::Bool(compare(
+                operator,
+                left.partial_cmp(&right)
+                    .ok_or_else(|| script_error(format!("can't compare {left} and {right}")))?,
+            )))
+        }
+    };
+
+    Number::from_f64(result)
+        .map(Value::Number)
+        .ok_or_else(|| script_error(format!("{operator:?} resulted in {result}")))
+}
+
+fn compare(operator: Operator, ordering: std::cmp::Ordering) -> bool {
+    match operator {
+        Operator::Less => ordering.is_lt(),
+        Operator::LessOrEqual => ordering.is_le(),
+        Operator::Greater => ordering.is_gt(),
+        Operator::GreaterOrEqual => ordering.is_ge(),
+        _ => false,
+    }
+}
+
+fn equals(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64() == right.as_f64(),
+        (left, right) => left == right,
+    }
+}
+
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.to_owned(),
+        value => value.to_string(),
+    }
+}
+
+fn script_error(message: String) -> Error {
+    Error::ScriptError(message)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Result<Self, Error> {
+        Ok(Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        })
+    }
+
+    fn is_done(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+
+        token
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        let matches = matches!(self.peek(), Some(Token::Symbol(found)) if *found == symbol);
+        if matches {
+            self.position += 1;
+        }
+
+        matches
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), Error> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn unexpected(&self) -> Error {
+        match self.peek() {
+            Some(token) => script_error(format!("unexpected {token:?}")),
+            None => script_error("unexpected end of script".to_owned()),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, Error> {
+        if let (Some(Token::Identifier(name)), Some(Token::Symbol(symbol))) = (
+            self.tokens.get(self.position),
+            self.tokens.get(self.position + 1),
+        ) {
+            let operator = match *symbol {
+                "=" => Some(None),
+                "+=" => Some(Some(Operator::Add)),
+                "-=" => Some(Some(Operator::Subtract)),
+                "*=" => Some(Some(Operator::Multiply)),
+                "/=" => Some(Some(Operator::Divide)),
+                _ => None,
+            };
+
+            if let Some(operator) = operator {
+                let name = name.to_owned();
+                self.position += 2;
+
+                return Ok(Statement::Assign(name, operator, self.expression()?));
+            }
+        }
+
+        Ok(Statement::Expression(self.expression()?))
+    }
+
+    fn expression(&mut self) -> Result<Expression, Error> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expression, Error> {
+        const LEVELS: [&[(&str, Operator)]; 5] = [
+            &[("||", Operator::Or)],
+            &[("&&", Operator::And)],
+            &[
+                ("==", Operator::Equal),
+                ("!=", Operator::NotEqual),
+                ("<=", Operator::LessOrEqual),
+                (">=", Operator::GreaterOrEqual),
+                ("<", Operator::Less),
+                (">", Operator::Greater),
+            ],
+            &[("+", Operator::Add), ("-", Operator::Subtract)],
+            &[
+                ("*", Operator::Multiply),
+                ("/", Operator::Divide),
+                ("%", Operator::Remainder),
+            ],
+        ];
+
+        let operators = match LEVELS.get(level) {
+            Some(operators) => operators,
+            None => return self.unary(),
+        };
+
+        let mut left = self.binary(level + 1)?;
+        'operands: loop {
+            for (symbol, operator) in operators.iter() {
+                if self.eat(symbol) {
+                    let right = self.binary(level + 1)?;
+                    left = Expression::Binary(Box::new(left), *operator, Box::new(right));
+
+                    continue 'operands;
+                }
+            }
+
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, Error> {
+        if self.eat("!") {
+            return Ok(Expression::Unary(Operator::Not, Box::new(self.unary()?)));
+        }
+
+        if self.eat("-") {
+            return Ok(Expression::Unary(Operator::Negate, Box::new(self.unary()?)));
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expression, Error> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expression::Literal(Value::Number(number))),
+            Some(Token::String(string)) => Ok(Expression::Literal(Value::String(string))),
+            Some(Token::Symbol("(")) => {
+                let expression = self.expression()?;
+                self.expect(")")?;
+
+                Ok(expression)
+            }
+            Some(Token::Identifier(name)) => match name.as_str() {
+                "true" => Ok(Expression::Literal(Value::Bool(true))),
+                "false" => Ok(Expression::Literal(Value::Bool(false))),
+                "null" => Ok(Expression::Literal(Value::Null)),
+                _ if self.eat("(") => {
+                    let mut arguments = vec![];
+                    if !self.eat(")") {
+                        loop {
+                            arguments.push(self.expression()?);
+
+                            if self.eat(")") {
+                                break;
+                            }
+
+                            self.expect(",")?;
+                        }
+                    }
+
+                    Ok(Expression::Call(name, arguments))
+                }
+                _ => Ok(Expression::Variable(name)),
+            },
+            _ => {
+                self.position -= 1;
+                Err(self.unexpected())
+            }
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = vec![];
+    let mut offset = 0;
+
+    while let Some(character) = source[offset..].chars().next() {
+        let rest = &source[offset..];
+
+        if character.is_whitespace() {
+            offset += character.len_utf8();
+        } else if rest.starts_with("//") {
+            offset += rest.find('\n').unwrap_or(rest.len());
+        } else if rest.starts_with("/*") {
+            offset += rest
+                .find("*/")
+                .map(|end| end + 2)
+                .ok_or_else(|| script_error("unterminated comment".to_owned()))?;
+        } else if character == '"' || character == '\'' {
+            let mut string = String::new();
+            let mut characters = rest.char_indices().skip(1);
+
+            loop {
+                match characters.next() {
+                    None => return Err(script_error("unterminated string".to_owned())),
+                    Some((end, found)) if found == character => {
+                        offset += end + 1;
+                        break;
+                    }
+                    Some((_, '\\')) => match characters.next() {
+                        Some((_, 'n')) => string.push('\n'),
+                        Some((_, 't')) => string.push('\t'),
+                        Some((_, escaped)) => string.push(escaped),
+                        None => return Err(script_error("unterminated string".to_owned())),
+                    },
+                    Some((_, found)) => string.push(found),
+                }
+            }
+
+            tokens.push(Token::String(string));
+        } else if character.is_ascii_digit() {
+            let literal = take_while(rest, |character| {
+                character.is_ascii_digit() || character == '.'
+            });
+            offset += literal.len();
+
+            let number = match literal.parse::<i64>() {
+                Ok(int) => Number::from(int),
+                Err(_) => literal
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .ok_or_else(|| script_error(format!("invalid number {literal}")))?,
+            };
+
+            tokens.push(Token::Number(number));
+        } else if character.is_alphabetic() || character == '_' {
+            let identifier = take_while(rest, |character| {
+                character.is_alphanumeric() || character == '_' || character == '.'
+            });
+            offset += identifier.len();
+
+            // NOTE: Articy also accepts the word forms of the logical operators
+            tokens.push(match identifier {
+                "and" => Token::Symbol("&&"),
+                "or" => Token::Symbol("||"),
+                "not" => Token::Symbol("!"),
+                _ => Token::Identifier(identifier.to_owned()),
+            });
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or_else(|| script_error(format!("unexpected character {character:?}")))?;
+
+            offset += symbol.len();
+            tokens.push(Token::Symbol(*symbol));
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn take_while(source: &str, predicate: impl Fn(char) -> bool) -> &str {
+    let end = source
+        .char_indices()
+        .find(|(_, character)| !predicate(*character))
+        .map_or(source.len(), |(end, _)| end);
+
+    &source[..end]
+}
@@ -0,0 +1,2192 @@
+use crate::{
+    codegen,
+    coverage::Coverage,
+    error::Error,
+    export::ExportInfo,
+    interpolation,
+    library::SharedLibrary,
+    localization::Localization,
+    markup, migration,
+    model::{self, Lookup, ModelIndex},
+    overrides::EntityOverrides,
+    random::Rng,
+    script::{self, Environment, Expression},
+    state,
+};
+use articy::{
+    types::{File as ArticyFile, Id, Model},
+    Interpreter as ArticyInterpreter, Outcome,
+};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Speaker {
+    pub name: Option<String>,
+    pub color: Option<[f32; 4]>,
+    pub external_id: String,
+    pub portrait: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub id: String,
+    pub text: String,
+    pub speaker: String,
+    pub technical_name: String,
+    pub template: Option<Value>,
+    // NOTE: Template feature properties keyed by their snake cased name, only filled in with `flatten_template` on
+    pub fields: Vec<(String, Value)>,
+    // NOTE: "Feature.Property" of the template properties the export defines as colors
+    pub colors: Vec<String>,
+    pub speaker_details: Option<Speaker>,
+    pub stage_directions: String,
+    pub color: Option<[f32; 4]>,
+    pub voice_over: Option<String>,
+    // NOTE: Left for the embedding engine to fill in, the asset mapping lives outside of sessions
+    pub voice_over_path: Option<String>,
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Choice {
+    pub id: String,
+    pub label: String,
+    pub template: Option<Value>,
+    pub fields: Vec<(String, Value)>,
+    pub colors: Vec<String>,
+    pub available: bool,
+    pub condition: String,
+    pub seen: bool,
+    pub fallback: bool,
+    pub time_limit: Option<f64>,
+    // NOTE: The node the choice leads to, one connection on. Leading to the enclosing dialogue means it ends there
+    pub target_id: Option<String>,
+    pub target_type: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Started(String),
+    NodeEntered {
+        id: String,
+        kind: String,
+    },
+    Line(Line),
+    Choices(Vec<Choice>),
+    Model(Value),
+    ConditionEvaluated {
+        id: String,
+        expression: String,
+        result: Option<bool>,
+    },
+    InstructionExecuted {
+        id: String,
+        expression: String,
+    },
+    PinConditionEvaluated {
+        id: String,
+        pin: String,
+        expression: String,
+        result: Option<bool>,
+    },
+    PinInstructionExecuted {
+        id: String,
+        pin: String,
+        expression: String,
+    },
+    StateChanged {
+        key: String,
+        old: Value,
+        new: Value,
+    },
+    HubEntered(String),
+    DialoguePushed(String),
+    DialoguePopped(String),
+    JumpFollowed {
+        from: String,
+        target: String,
+    },
+    Stopped {
+        id: String,
+        reason: StopReason,
+    },
+    WatchTriggered {
+        name: String,
+        value: Value,
+    },
+    SpeakerChanged {
+        previous: String,
+        new: String,
+    },
+}
+
+// NOTE: Why a dialogue stopped, `Error` is for flows articy-rs failed on halfway through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Ended,
+    Stopped,
+    Error,
+}
+
+// NOTE: What to do when a hub has more than one valid way out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HubStrategy {
+    #[default]
+    Ask,
+    First,
+    Priority,
+    Random,
+}
+
+// NOTE: How choices are ordered, `Connection` follows the order of the output pins and their connections in Articy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChoiceSortMode {
+    None,
+    #[default]
+    Connection,
+    Priority,
+    Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub speaker: String,
+    pub text: String,
+    pub timestamp: f64,
+    pub chosen: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SavedState {
+    pub cursor: Option<String>,
+    pub visited: Vec<(String, u64)>,
+    pub state: Vec<(String, Value)>,
+    pub rng: Option<u64>,
+    pub overrides: Vec<(String, String, Value)>,
+    // NOTE: (id, technical name, external id) of every id above, for finding them again in a newer export
+    pub references: Vec<(String, String, String)>,
+}
+
+// NOTE: Where the outer flow was when a dialogue got pushed on top of it
+#[derive(Debug, Clone)]
+struct Frame {
+    dialogue: String,
+    cursor: String,
+    kind: String,
+    choices: Vec<Choice>,
+}
+
+// NOTE: An expression evaluated again whenever a variable changes, `last` is None until it evaluated successfully
+#[derive(Debug, Clone)]
+struct Watch {
+    name: String,
+    expression: Expression,
+    last: Option<Value>,
+}
+
+// NOTE: Steps taken without reaching a line, choice or the end before the flow is considered stuck
+pub const DEFAULT_MAX_ITERATIONS: usize = 1000;
+
+// NOTE: Boolean property on a choice, or one of its template's features, hiding it once it has been taken
+pub const ONCE_ONLY_PROPERTY: &str = "OnceOnly";
+
+// NOTE: Properties on a choice, or one of its template's features, for timed choices. The fallback gets picked when
+//       time runs out
+pub const DEFAULT_FALLBACK_PROPERTY: &str = "IsFallback";
+pub const DEFAULT_TIME_LIMIT_PROPERTY: &str = "TimeLimit";
+
+// NOTE: Property holding a fragment's voice over asset, on the fragment itself or one of its template's features
+pub const DEFAULT_VOICE_OVER_PROPERTY: &str = "VoiceOver";
+
+pub type Function = Box<dyn FnMut(Vec<Value>) -> Result<Value, Error>>;
+
+pub struct Session {
+    interpreter: ArticyInterpreter,
+    functions: HashMap<String, Function>,
+    history: Vec<HistoryEntry>,
+    hub_strategy: HubStrategy,
+    skip_types: Vec<String>,
+    max_iterations: usize,
+    stack: Vec<Frame>,
+    rng: Rng,
+    last_state: HashMap<String, Value>,
+    watches: Vec<Watch>,
+    interpolate: bool,
+    tokens: HashMap<String, String>,
+    group_lines: bool,
+    last_speaker: Option<String>,
+    context: Context,
+}
+
+// NOTE: Kept apart from the interpreter so it can be updated while an `Outcome` borrows the interpreter
+struct Context {
+    file: Arc<ArticyFile>,
+    index: Arc<ModelIndex>,
+    localization: Arc<RwLock<Localization>>,
+    overrides: Arc<RwLock<EntityOverrides>>,
+    export: Arc<ExportInfo>,
+    visited: HashMap<String, u64>,
+    coverage: Option<Coverage>,
+    choices: Vec<Choice>,
+    entered: Vec<Event>,
+    dialogue: String,
+    current_id: String,
+    current_kind: String,
+    emit_bbcode: bool,
+    flatten_template: bool,
+    voice_over_property: String,
+    fallback_property: String,
+    time_limit_property: String,
+    include_unavailable: bool,
+    hide_seen_choices: bool,
+    choice_sort_mode: ChoiceSortMode,
+    running: bool,
+}
+
+struct ScriptEnvironment<'a> {
+    interpreter: &'a mut ArticyInterpreter,
+    functions: &'a mut HashMap<String, Function>,
+}
+
+impl Session {
+    pub fn new(shared: SharedLibrary) -> Self {
+        let SharedLibrary {
+            file,
+            index,
+            localization,
+            overrides,
+            export,
+        } = shared;
+
+        let mut session = Session {
+            interpreter: ArticyInterpreter::new(file.clone()),
+            functions: HashMap::new(),
+            history: vec![],
+            hub_strategy: HubStrategy::default(),
+            skip_types: vec![],
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            stack: vec![],
+            rng: Rng::default(),
+            last_state: HashMap::new(),
+            watches: vec![],
+            interpolate: false,
+            tokens: HashMap::new(),
+            group_lines: false,
+            last_speaker: None,
+            context: Context {
+                file,
+                index,
+                localization,
+                overrides,
+                export,
+                visited: HashMap::new(),
+                coverage: None,
+                choices: vec![],
+                entered: vec![],
+                dialogue: String::new(),
+                current_id: String::new(),
+                current_kind: String::new(),
+                emit_bbcode: false,
+                flatten_template: false,
+                voice_over_property: DEFAULT_VOICE_OVER_PROPERTY.to_owned(),
+                fallback_property: DEFAULT_FALLBACK_PROPERTY.to_owned(),
+                time_limit_property: DEFAULT_TIME_LIMIT_PROPERTY.to_owned(),
+                include_unavailable: false,
+                hide_seen_choices: false,
+                choice_sort_mode: ChoiceSortMode::default(),
+                running: false,
+            },
+        };
+
+        session.state_changes();
+        session
+    }
+
+    pub fn library(&self) -> SharedLibrary {
+        SharedLibrary {
+            file: self.context.file.clone(),
+            index: self.context.index.clone(),
+            localization: self.context.localization.clone(),
+            overrides: self.context.overrides.clone(),
+            export: self.context.export.clone(),
+        }
+    }
+
+    pub fn interpreter(&self) -> &ArticyInterpreter {
+        &self.interpreter
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.context.running
+    }
+
+    // NOTE: The node the flow is resting on, nothing once the dialogue stopped
+    pub fn current_model(&self) -> Option<&Model> {
+        if self.context.running {
+            self.interpreter.get_current_model().ok()
+        } else {
+            None
+        }
+    }
+
+    pub fn current_id(&self) -> Option<&str> {
+        self.context
+            .running
+            .then_some(self.context.current_id.as_str())
+    }
+
+    pub fn current_kind(&self) -> Option<&str> {
+        self.context
+            .running
+            .then_some(self.context.current_kind.as_str())
+    }
+
+    pub fn choices(&self) -> &[Choice] {
+        &self.context.choices
+    }
+
+    pub fn visit_count(&self, id: &str) -> u64 {
+        self.context.visited.get(id).copied().unwrap_or_default()
+    }
+
+    pub fn has_seen(&self, id: &str) -> bool {
+        self.visit_count(id) > 0
+    }
+
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    // NOTE: For a line translated outside of the session, e.g. by the engine. The translation gets the markup and
+    //       interpolation the original text got, and replaces it in the history too.
+    pub fn retranslate(&mut self, line: &mut Line, translated: &str) {
+        let mut text = if self.context.emit_bbcode {
+            markup::to_bbcode(translated)
+        } else {
+            translated.to_owned()
+        };
+
+        if self.interpolate {
+            text = self.interpolate(&text);
+        }
+
+        if let Some(entry) = self
+            .history
+            .iter_mut()
+            .rev()
+            .find(|entry| entry.id == line.id && !entry.chosen)
+        {
+            entry.text = text.to_owned();
+        }
+
+        line.text = text;
+    }
+
+    pub fn set_emit_bbcode(&mut self, emit_bbcode: bool) {
+        self.context.emit_bbcode = emit_bbcode;
+    }
+
+    pub fn set_flatten_template(&mut self, flatten_template: bool) {
+        self.context.flatten_template = flatten_template;
+    }
+
+    // NOTE: Fills in `{placeholders}` in lines and choices, see `interpolate`
+    pub fn set_interpolate(&mut self, interpolate: bool) {
+        self.interpolate = interpolate;
+    }
+
+    pub fn set_token(&mut self, name: &str, value: &str) {
+        self.tokens.insert(name.to_owned(), value.to_owned());
+    }
+
+    pub fn remove_token(&mut self, name: &str) -> bool {
+        self.tokens.remove(name).is_some()
+    }
+
+    // NOTE: Runs on through lines following each other without a choice in between, as long as the speaker stays the same
+    pub fn set_group_lines(&mut self, group_lines: bool) {
+        self.group_lines = group_lines;
+    }
+
+    pub fn set_voice_over_property(&mut self, property: &str) {
+        self.context.voice_over_property = property.to_owned();
+    }
+
+    pub fn set_fallback_property(&mut self, property: &str) {
+        self.context.fallback_property = property.to_owned();
+    }
+
+    pub fn set_time_limit_property(&mut self, property: &str) {
+        self.context.time_limit_property = property.to_owned();
+    }
+
+    pub fn set_include_unavailable(&mut self, include_unavailable: bool) {
+        self.context.include_unavailable = include_unavailable;
+    }
+
+    pub fn set_hub_strategy(&mut self, strategy: HubStrategy) {
+        self.hub_strategy = strategy;
+    }
+
+    // NOTE: Hides every choice taken before, instead of only the ones flagged with `ONCE_ONLY_PROPERTY`
+    pub fn set_hide_seen_choices(&mut self, hide_seen_choices: bool) {
+        self.context.hide_seen_choices = hide_seen_choices;
+    }
+
+    pub fn set_choice_sort_mode(&mut self, mode: ChoiceSortMode) {
+        self.context.choice_sort_mode = mode;
+    }
+
+    // NOTE: Turning coverage off throws away what was recorded
+    pub fn set_coverage(&mut self, enabled: bool) {
+        match (enabled, &self.context.coverage) {
+            (true, None) => self.context.coverage = Some(Coverage::default()),
+            (false, _) => self.context.coverage = None,
+            _ => {}
+        }
+    }
+
+    pub fn coverage_report(&self) -> Option<Value> {
+        self.context
+            .coverage
+            .as_ref()
+            .map(|coverage| coverage.report(&self.context.file, &self.context.index))
+    }
+
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations;
+    }
+
+    // NOTE: Models of these types are advanced through without surfacing their events
+    pub fn set_skip_types(&mut self, skip_types: Vec<String>) {
+        self.skip_types = skip_types;
+    }
+
+    pub fn register_function(&mut self, name: &str, function: Function) {
+        self.functions.insert(name.to_owned(), function);
+    }
+
+    // NOTE: The line a fragment would surface, without the conversation moving there
+    pub fn line(&self, id: &str) -> Option<Line> {
+        let model = self.context.index.find(&self.context.file, id)?;
+
+        match self.context.model_event(model) {
+            Event::Line(line) => Some(line),
+            _ => None,
+        }
+    }
+
+    // NOTE: Runs an expresso condition against the current variables and registered functions
+    pub fn evaluate(&mut self, expression: &str) -> Result<Value, Error> {
+        script::evaluate(expression, &mut self.environment())
+    }
+
+    // NOTE: Adding a watch under a name that's taken replaces it, the current result doesn't count as a change
+    pub fn add_watch(&mut self, name: &str, expression: &str) -> Result<(), Error> {
+        let expression = script::parse_expression(expression)?;
+        let last = expression.evaluate(&mut self.environment()).ok();
+
+        self.remove_watch(name);
+        self.watches.push(Watch {
+            name: name.to_owned(),
+            expression,
+            last,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_watch(&mut self, name: &str) -> bool {
+        let count = self.watches.len();
+        self.watches.retain(|watch| watch.name != name);
+
+        self.watches.len() != count
+    }
+
+    pub fn execute(&mut self, script: &str) -> Result<Vec<Event>, Error> {
+        script::execute(script, &mut self.environment())?;
+
+        Ok(self.state_changes())
+    }
+
+    pub fn set_state(&mut self, key: &str, value: &Value) -> Result<Vec<Event>, Error> {
+        self.interpreter
+            .set_state(key, state::from_json(value)?)
+            .map_err(|_| Error::FailedToSetState(key.to_owned()))?;
+
+        Ok(self.state_changes())
+    }
+
+    pub fn get_state(&mut self, key: &str) -> Result<Value, Error> {
+        state::to_json(
+            self.interpreter
+                .get_state(key)
+                .map_err(|_| Error::FailedToGetState(key.to_owned()))?,
+        )
+    }
+
+    pub fn states(&self) -> Result<Vec<(String, Value)>, Error> {
+        self.interpreter
+            .state
+            .iter()
+            .map(|(key, value)| Ok((key.to_owned(), state::to_json(value)?)))
+            .collect()
+    }
+
+    // NOTE: Every value is converted up front, so a bad value doesn't leave the state half applied
+    pub fn set_states(&mut self, states: &[(String, Value)]) -> Result<Vec<Event>, Error> {
+        let states = states
+            .iter()
+            .map(|(key, value)| Ok((key, state::from_json(value)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        for (key, value) in states {
+            self.interpreter
+                .set_state(key, value)
+                .map_err(|_| Error::FailedToSetState(key.to_owned()))?;
+        }
+
+        Ok(self.state_changes())
+    }
+
+    // NOTE: Variables are named "Namespace.variable", the names given back and taken here leave out the namespace
+    pub fn namespace(&self, name: &str) -> Result<Vec<(String, Value)>, Error> {
+        let prefix = format!("{name}.");
+
+        Ok(self
+            .states()?
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.strip_prefix(&prefix)?.to_owned(), value)))
+            .collect())
+    }
+
+    pub fn set_namespace(
+        &mut self,
+        name: &str,
+        states: &[(String, Value)],
+    ) -> Result<Vec<Event>, Error> {
+        let states = states
+            .iter()
+            .map(|(key, value)| (format!("{name}.{key}"), value.to_owned()))
+            .collect::<Vec<(String, Value)>>();
+
+        self.set_states(&states)
+    }
+
+    pub fn namespaces(&self) -> Vec<String> {
+        let mut namespaces = self
+            .interpreter
+            .state
+            .iter()
+            .filter_map(|(key, _)| Some(key.split_once('.')?.0.to_owned()))
+            .collect::<Vec<String>>();
+
+        namespaces.sort();
+        namespaces.dedup();
+        namespaces
+    }
+
+    // NOTE: For carrying variables over to a newer export, ones that no longer exist are dropped and new ones keep their default
+    pub fn migrate_states(&mut self, states: &[(String, Value)]) -> Result<Vec<Event>, Error> {
+        let known = self
+            .states()?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect::<HashSet<String>>();
+
+        let states = states
+            .iter()
+            .filter(|(key, _)| known.contains(key))
+            .cloned()
+            .collect::<Vec<(String, Value)>>();
+
+        self.set_states(&states)
+    }
+
+    // NOTE: Diffs against the state seen last time, which catches changes made by articy-rs' own instructions too
+    fn state_changes(&mut self) -> Vec<Event> {
+        let current = self
+            .interpreter
+            .state
+            .iter()
+            .filter_map(|(key, value)| Some((key.to_owned(), state::to_json(value).ok()?)))
+            .collect::<HashMap<String, Value>>();
+
+        let mut changed = current
+            .iter()
+            .filter(|(key, value)| self.last_state.get(*key) != Some(*value))
+            .map(|(key, value)| Event::StateChanged {
+                key: key.to_owned(),
+                old: self.last_state.get(key).cloned().unwrap_or_default(),
+                new: value.to_owned(),
+            })
+            .collect::<Vec<Event>>();
+
+        changed.sort_by(|left, right| match (left, right) {
+            (Event::StateChanged { key: left, .. }, Event::StateChanged { key: right, .. }) => {
+                left.cmp(right)
+            }
+            _ => std::cmp::Ordering::Equal,
+        });
+
+        self.last_state = current;
+
+        if !changed.is_empty() {
+            changed.extend(self.triggered_watches());
+        }
+
+        changed
+    }
+
+    // NOTE: Failing to evaluate, e.g. through a function that isn't registered yet, doesn't count as a change
+    fn triggered_watches(&mut self) -> Vec<Event> {
+        let mut watches = std::mem::take(&mut self.watches);
+        let mut triggered = vec![];
+
+        for watch in &mut watches {
+            let value = match watch.expression.evaluate(&mut self.environment()) {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+
+            if watch.last.as_ref() != Some(&value) {
+                watch.last = Some(value.to_owned());
+                triggered.push(Event::WatchTriggered {
+                    name: watch.name.to_owned(),
+                    value,
+                });
+            }
+        }
+
+        self.watches = watches;
+        triggered
+    }
+
+    pub fn start(&mut self, id: &str) -> Result<Vec<Event>, Error> {
+        self.interpreter
+            .start(Id(id.to_owned()))
+            .map_err(Error::ArticyError)?;
+
+        let model = self
+            .interpreter
+            .get_current_model()
+            .map_err(Error::ArticyError)?;
+
+        // NOTE: Entering from the dialogue itself, so nothing is taken for a pin of wherever the last flow ended
+        let from = self.context.entered.len();
+        self.context.current_id = id.to_owned();
+        self.context.enter(model);
+        self.context.dialogue = id.to_owned();
+        self.context.running = true;
+        self.last_speaker = None;
+
+        let events = vec![
+            Event::Started(id.to_owned()),
+            self.context.model_event(model),
+        ];
+        let events = self.run_step(from, events)?;
+        let events = self.skip_through(events)?;
+        let events = self.gather_lines(events)?;
+
+        self.finish(events)
+    }
+
+    // NOTE: Puts the flow on `id` as if it just got there, e.g. to load a save in the middle of a conversation. The
+    //       node isn't entered again, so its instructions and the pins leading up to it don't run a second time
+    pub fn resume_at(&mut self, id: &str) -> Result<Vec<Event>, Error> {
+        if self.context.index.find(&self.context.file, id).is_none() {
+            return Err(Error::ModelNotFound(id.to_owned()));
+        }
+
+        self.place(id)?;
+
+        let model = self
+            .interpreter
+            .get_current_model()
+            .map_err(Error::ArticyError)?;
+
+        // NOTE: Counts as seen, without counting another visit on top of the ones a loaded save brought along
+        self.context.visited.entry(id.to_owned()).or_insert(1);
+
+        let mut events = vec![
+            Event::Started(self.context.dialogue.to_owned()),
+            Event::NodeEntered {
+                id: id.to_owned(),
+                kind: self.context.current_kind.to_owned(),
+            },
+            self.context.model_event(model),
+        ];
+
+        if let Some(Event::ConditionEvaluated {
+            expression, result, ..
+        }) = events.last_mut()
+        {
+            *result = self.condition(expression);
+        }
+        let events = self.skip_through(events)?;
+        let events = self.gather_lines(events)?;
+
+        self.finish(events)
+    }
+
+    // NOTE: Moves the cursor onto `id` and takes over its node and enclosing dialogue, leaving whatever the flow
+    //       was doing before behind
+    fn place(&mut self, id: &str) -> Result<(), Error> {
+        self.interpreter
+            .start(Id(id.to_owned()))
+            .map_err(Error::ArticyError)?;
+
+        let kind = self.context.index.kind(id).to_owned();
+
+        self.stack.clear();
+        self.context.entered.clear();
+        self.context.choices.clear();
+        self.context.current_id = id.to_owned();
+        self.context.current_kind = kind;
+        self.context.dialogue = self
+            .context
+            .enclosing_dialogue(id)
+            .unwrap_or_else(|| id.to_owned());
+        self.context.running = true;
+        self.last_speaker = None;
+
+        Ok(())
+    }
+
+    pub fn advance(&mut self) -> Result<Vec<Event>, Error> {
+        let events = self.advance_once()?;
+        let events = self.skip_through(events)?;
+        let events = self.gather_lines(events)?;
+
+        self.finish(events)
+    }
+
+    fn advance_once(&mut self) -> Result<Vec<Event>, Error> {
+        let from = self.context.entered.len();
+        let outcome = self.interpreter.advance().map_err(Error::ArticyError)?;
+
+        if let Some(id) = self
+            .context
+            .pick_hub_exit(&outcome, self.hub_strategy, &mut self.rng)
+        {
+            return self.choose_once(&id);
+        }
+
+        let events = self.context.handle_outcome(outcome);
+        self.run_step(from, events)
+    }
+
+    // NOTE: Goes through what the step just taken passed, the pins and nodes it entered from `from` on followed by the
+    //       events of its outcome, in the order the flow passed them
+    fn run_step(&mut self, from: usize, mut events: Vec<Event>) -> Result<Vec<Event>, Error> {
+        let mut entered = self.context.entered.split_off(from);
+
+        for event in entered.iter_mut().chain(events.iter_mut()) {
+            match event {
+                Event::InstructionExecuted { expression, .. }
+                | Event::PinInstructionExecuted { expression, .. } => {
+                    self.run_functions(expression)?;
+                }
+                Event::ConditionEvaluated {
+                    expression, result, ..
+                }
+                | Event::PinConditionEvaluated {
+                    expression, result, ..
+                } => *result = self.condition(expression),
+                _ => {}
+            }
+        }
+
+        self.context.entered.extend(entered);
+        Ok(events)
+    }
+
+    // NOTE: The event of a skipped model is always the last one, `handle_outcome` only emits one when advancing
+    fn skip_through(&mut self, mut events: Vec<Event>) -> Result<Vec<Event>, Error> {
+        let mut trail = vec![];
+
+        while self.context.running
+            && self.context.choices.is_empty()
+            && self.skip_types.contains(&self.context.current_kind)
+        {
+            self.watchdog(&mut trail)?;
+
+            events.pop();
+            events.extend(self.advance_once()?);
+        }
+
+        Ok(events)
+    }
+
+    // NOTE: Only follows a single connection straight into another line, anything branching ends the group
+    fn gather_lines(&mut self, mut events: Vec<Event>) -> Result<Vec<Event>, Error> {
+        let mut trail = vec![];
+
+        while self.group_lines && self.context.running && self.context.choices.is_empty() {
+            let speaker = match events.last() {
+                Some(Event::Line(line)) => line.speaker.to_owned(),
+                _ => break,
+            };
+
+            let next = match self.context.index.outgoing(&self.context.current_id) {
+                [connection] => self
+                    .context
+                    .index
+                    .find(&self.context.file, &connection.target),
+                _ => None,
+            };
+
+            match next {
+                Some(Model::DialogueFragment {
+                    speaker: following, ..
+                }) if following.to_inner() == speaker => {}
+                _ => break,
+            }
+
+            self.watchdog(&mut trail)?;
+
+            let advanced = self.advance_once()?;
+            events.extend(self.skip_through(advanced)?);
+        }
+
+        Ok(events)
+    }
+
+    // NOTE: Leaves the cursor on the last visited node, so the flow can still be stopped, loaded or advanced
+    fn watchdog(&mut self, trail: &mut Vec<String>) -> Result<(), Error> {
+        trail.push(self.context.current_id.to_owned());
+
+        if trail.len() <= self.max_iterations {
+            return Ok(());
+        }
+
+        self.context.entered.clear();
+
+        let mut looping = vec![];
+        for (position, id) in trail.iter().enumerate() {
+            if trail[position + 1..].contains(id) && !looping.contains(id) {
+                looping.push(id.to_owned());
+            }
+        }
+
+        Err(Error::FlowLoop(looping))
+    }
+
+    pub fn push_dialogue(&mut self, id: &str) -> Result<Vec<Event>, Error> {
+        let frame = match self.interpreter.get_current_model() {
+            Ok(model) if self.context.running => Some(Frame {
+                dialogue: self.context.dialogue.to_owned(),
+                cursor: model.id().to_inner(),
+                kind: self.context.current_kind.to_owned(),
+                choices: self.context.choices.clone(),
+            }),
+            _ => None,
+        };
+
+        let mut events = vec![Event::DialoguePushed(id.to_owned())];
+        events.extend(self.start(id)?);
+
+        // NOTE: Only pushed once the dialogue actually started, so a failed push leaves the stack as it was
+        self.stack.extend(frame);
+
+        Ok(events)
+    }
+
+    pub fn pop_dialogue(&mut self) -> Result<Vec<Event>, Error> {
+        let frame = self.stack.pop().ok_or(Error::DialogueStackEmpty)?;
+
+        // NOTE: Like loading a save, variables go back in after starting so the pushed dialogue's changes stick
+        let states = self.states()?;
+        self.interpreter
+            .start(Id(frame.cursor.to_owned()))
+            .map_err(Error::ArticyError)?;
+        self.set_states(&states)?;
+
+        self.context.dialogue = frame.dialogue;
+        self.context.current_id = frame.cursor.to_owned();
+        self.context.current_kind = frame.kind;
+        self.context.choices = frame.choices.clone();
+        self.context.running = true;
+
+        let mut events = vec![Event::DialoguePopped(frame.cursor)];
+        if !frame.choices.is_empty() {
+            events.push(Event::Choices(frame.choices));
+        }
+
+        Ok(events)
+    }
+
+    pub fn choose(&mut self, id: &str) -> Result<Vec<Event>, Error> {
+        if let Some(index) = self
+            .context
+            .choices
+            .iter()
+            .position(|choice| choice.id == id && !choice.available)
+        {
+            return Err(Error::ChoiceNotAvailable(index as i64));
+        }
+
+        let entry = HistoryEntry {
+            id: id.to_owned(),
+            speaker: self
+                .context
+                .index
+                .find(&self.context.file, id)
+                .and_then(|model| match model {
+                    Model::DialogueFragment { speaker, .. } => Some(speaker.to_inner()),
+                    _ => None,
+                })
+                .unwrap_or_default(),
+            text: self
+                .context
+                .choices
+                .iter()
+                .find(|choice| choice.id == id)
+                .map(|choice| choice.label.to_owned())
+                .unwrap_or_default(),
+            timestamp: timestamp(),
+            chosen: true,
+        };
+
+        let position = self.history.len();
+        let events = self.follow(id)?;
+
+        if let Some(coverage) = &mut self.context.coverage {
+            coverage.choose(id);
+        }
+
+        self.history.insert(position, entry);
+
+        Ok(events)
+    }
+
+    fn follow(&mut self, id: &str) -> Result<Vec<Event>, Error> {
+        let events = self.choose_once(id)?;
+        let events = self.skip_through(events)?;
+        let events = self.gather_lines(events)?;
+
+        self.finish(events)
+    }
+
+    // NOTE: A hub picking its own way out chooses again right away, cyclic hubs are caught by the watchdog
+    fn choose_once(&mut self, id: &str) -> Result<Vec<Event>, Error> {
+        let from = self.context.entered.len();
+        let mut trail = vec![];
+        let mut id = id.to_owned();
+
+        loop {
+            let outcome = self
+                .interpreter
+                .choose(Id(id.to_owned()))
+                .map_err(Error::ArticyError)?;
+
+            // NOTE: Only count the choice here when the outcome doesn't land on it already
+            if !matches!(&outcome, Outcome::Advanced(model) if model.id().to_inner() == id) {
+                self.context.visit(id.to_owned());
+            }
+
+            match self
+                .context
+                .pick_hub_exit(&outcome, self.hub_strategy, &mut self.rng)
+            {
+                Some(exit) => {
+                    self.watchdog(&mut trail)?;
+                    id = exit;
+                }
+                None => {
+                    let events = self.context.handle_outcome(outcome);
+                    return self.run_step(from, events);
+                }
+            }
+        }
+    }
+
+    pub fn choose_by_index(&mut self, index: i64) -> Result<Vec<Event>, Error> {
+        let id = usize::try_from(index)
+            .ok()
+            .and_then(|index| self.context.choices.get(index))
+            .map(|choice| choice.id.to_owned())
+            .ok_or(Error::ChoiceNotAvailable(index))?;
+
+        self.choose(&id)
+    }
+
+    pub fn choose_fallback(&mut self) -> Result<Vec<Event>, Error> {
+        let id = self
+            .context
+            .choices
+            .iter()
+            .find(|choice| choice.fallback && choice.available)
+            .map(|choice| choice.id.to_owned())
+            .ok_or(Error::NoFallbackChoice)?;
+
+        self.choose(&id)
+    }
+
+    // NOTE: Done here instead of through articy-rs, so a cyclic flow can't hang the game
+    pub fn exhaust_maximally(&mut self) -> Result<Vec<Event>, Error> {
+        let mut trail = vec![];
+        let mut events = if self.context.choices.is_empty() {
+            vec![]
+        } else {
+            vec![Event::Choices(self.context.choices.clone())]
+        };
+
+        while self.context.running && self.context.choices.is_empty() {
+            self.watchdog(&mut trail)?;
+
+            events = self.advance_once()?;
+        }
+
+        self.finish(events)
+    }
+
+    fn environment(&mut self) -> ScriptEnvironment<'_> {
+        ScriptEnvironment {
+            interpreter: &mut self.interpreter,
+            functions: &mut self.functions,
+        }
+    }
+
+    // NOTE: articy-rs doesn't report what a condition evaluated to, so it's evaluated against the same variables.
+    //       Registered functions are left out, looking at a condition shouldn't set anything off in the game
+    fn condition(&mut self, expression: &str) -> Option<bool> {
+        let mut environment = ScriptEnvironment {
+            interpreter: &mut self.interpreter,
+            functions: &mut HashMap::new(),
+        };
+
+        script::evaluate(expression, &mut environment)
+            .ok()
+            .map(|value| script::is_truthy(&value))
+    }
+
+    fn finish(&mut self, events: Vec<Event>) -> Result<Vec<Event>, Error> {
+        // NOTE: Entering is reported up front, skipped nodes included, so trackers are up to date before any line shows
+        let mut events = std::mem::take(&mut self.context.entered)
+            .into_iter()
+            .chain(events)
+            .collect::<Vec<Event>>();
+
+        events.extend(self.state_changes());
+
+        let mut changes = vec![];
+        for (position, event) in events.iter_mut().enumerate() {
+            if self.interpolate {
+                match event {
+                    Event::Line(line) => {
+                        line.text = self.interpolate(&line.text);
+                        line.stage_directions = self.interpolate(&line.stage_directions);
+                    }
+                    Event::Choices(choices) => {
+                        for choice in choices.iter_mut() {
+                            choice.label = self.interpolate(&choice.label);
+                        }
+
+                        // NOTE: So the history gets what the player saw when one of them is chosen
+                        self.context.choices = choices.clone();
+                    }
+                    _ => {}
+                }
+            }
+
+            if let Event::Line(line) = event {
+                if let Some(previous) = self.last_speaker.replace(line.speaker.to_owned()) {
+                    if previous != line.speaker {
+                        changes.push((position, previous, line.speaker.to_owned()));
+                    }
+                }
+
+                self.history.push(HistoryEntry {
+                    id: line.id.to_owned(),
+                    speaker: line.speaker.to_owned(),
+                    text: line.text.to_owned(),
+                    timestamp: timestamp(),
+                    chosen: false,
+                });
+            }
+        }
+
+        // NOTE: Right before the line of the new speaker
+        for (position, previous, new) in changes.into_iter().rev() {
+            events.insert(position, Event::SpeakerChanged { previous, new });
+        }
+
+        Ok(events)
+    }
+
+    // NOTE: A placeholder is looked up as a custom token first, then as a variable, e.g. `{Inventory.Gold}`, and at last
+    //       as the name of an entity by technical name, e.g. `{Player.Name}` or `{Player.DisplayName}`
+    fn interpolate(&mut self, text: &str) -> String {
+        if !text.contains(['{', '}']) {
+            return text.to_owned();
+        }
+
+        let tokens = &self.tokens;
+        let context = &self.context;
+        let mut environment = ScriptEnvironment {
+            interpreter: &mut self.interpreter,
+            functions: &mut self.functions,
+        };
+
+        interpolation::interpolate(text, |name| {
+            tokens
+                .get(name)
+                .cloned()
+                .or_else(|| {
+                    environment
+                        .get(name)
+                        .ok()
+                        .map(|value| interpolation::display_value(&value))
+                })
+                .or_else(|| context.entity_name(name))
+        })
+    }
+
+    // NOTE: articy-rs runs the variable side of instructions, only statements calling registered functions are run here
+    fn run_functions(&mut self, source: &str) -> Result<(), Error> {
+        let mut environment = self.environment();
+        if !environment
+            .functions
+            .keys()
+            .any(|name| source.contains(name.as_str()))
+        {
+            return Ok(());
+        }
+
+        for statement in script::parse(source)? {
+            if statement
+                .functions()
+                .iter()
+                .any(|name| environment.functions.contains_key(*name))
+            {
+                statement.execute(&mut environment)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> Vec<Event> {
+        self.halt(StopReason::Stopped)
+    }
+
+    // NOTE: For when a step failed in a way that leaves the flow stuck, so whoever is waiting on it gets to move on
+    pub fn abort(&mut self) -> Vec<Event> {
+        self.halt(StopReason::Error)
+    }
+
+    fn halt(&mut self, reason: StopReason) -> Vec<Event> {
+        self.reset();
+
+        vec![Event::Stopped {
+            id: self.context.dialogue.to_owned(),
+            reason,
+        }]
+    }
+
+    fn reset(&mut self) {
+        // NOTE: A fresh interpreter has no cursor, carry the variables over so only the flow is reset
+        let mut interpreter = ArticyInterpreter::new(self.context.file.clone());
+        interpreter.state = std::mem::take(&mut self.interpreter.state);
+
+        self.interpreter = interpreter;
+        self.stack.clear();
+        self.context.entered.clear();
+        self.context.choices.clear();
+        self.context.running = false;
+    }
+
+    // NOTE: Runs a throwaway interpreter on a copy of the variables, stopping early at the next choice or the end
+    pub fn preview(&self, id: &str, depth: usize) -> Result<Vec<&Model>, Error> {
+        let mut interpreter = ArticyInterpreter::new(self.context.file.clone());
+        interpreter.state = self.interpreter.state.clone();
+
+        interpreter
+            .start(Id(id.to_owned()))
+            .map_err(Error::ArticyError)?;
+
+        let mut ids = vec![id.to_owned()];
+        while ids.len() < depth {
+            match interpreter.advance().map_err(Error::ArticyError)? {
+                Outcome::Advanced(model) => ids.push(model.id().to_inner()),
+                Outcome::WaitingForChoice(..) | Outcome::Stopped | Outcome::EndOfDialogue => break,
+            }
+        }
+
+        Ok(ids
+            .iter()
+            .filter_map(|id| self.context.index.find(&self.context.file, id))
+            .collect())
+    }
+
+    pub fn connections(&self, id: Option<&str>) -> Result<Vec<&Model>, Error> {
+        match id {
+            None => self.interpreter.get_available_connections_at_cursor(),
+            Some(id) => self
+                .interpreter
+                .get_available_connections(&Id(id.to_owned())),
+        }
+        .map_err(Error::ArticyError)
+    }
+
+    pub fn save(&self) -> Result<SavedState, Error> {
+        let mut saved = SavedState {
+            cursor: self
+                .interpreter
+                .get_current_model()
+                .ok()
+                .map(|model| model.id().to_inner()),
+            visited: self
+                .context
+                .visited
+                .iter()
+                .map(|(id, count)| (id.to_owned(), *count))
+                .collect(),
+            state: self.states()?,
+            rng: Some(self.rng.state()),
+            overrides: self
+                .context
+                .overrides
+                .read()
+                .expect("overrides lock to not be poisoned")
+                .entries(),
+            references: vec![],
+        };
+        saved.references = migration::references(&self.library(), &saved);
+
+        Ok(saved)
+    }
+
+    // NOTE: Gives back the ids that no longer exist in the export and couldn't be mapped to a current one, with
+    //       what they were saved as. Those are left out, the rest of the save is still restored
+    pub fn load(&mut self, saved: &SavedState) -> Result<Vec<(String, &'static str)>, Error> {
+        let mut saved = saved.to_owned();
+        let unmapped = migration::migrate(&self.library(), &mut saved);
+
+        // NOTE: Starting resets the cursor, so it has to happen before variables are restored. A save made while
+        //       nothing was running stops whatever is running now.
+        match &saved.cursor {
+            Some(cursor) => self.place(cursor)?,
+            None => self.reset(),
+        }
+
+        // NOTE: Restoring a save isn't a change made by the story, so it's not reported
+        self.set_states(&saved.state)?;
+
+        self.context.visited = saved.visited.iter().cloned().collect();
+
+        if let Some(rng) = saved.rng {
+            self.rng = Rng::new(rng);
+        }
+
+        // NOTE: Overrides are shared, so this renames characters for every session on the same Database
+        self.context
+            .overrides
+            .write()
+            .expect("overrides lock to not be poisoned")
+            .replace(&saved.overrides);
+
+        Ok(unmapped)
+    }
+}
+
+impl Context {
+    fn visit(&mut self, id: String) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.visit(&id);
+        }
+
+        *self.visited.entry(id).or_default() += 1;
+    }
+
+    fn speaker(&self, id: &str) -> Option<Speaker> {
+        let mut speaker = speaker_details(self.index.find(&self.file, id)?);
+        self.overrides
+            .read()
+            .expect("overrides lock to not be poisoned")
+            .apply(id, &mut speaker);
+
+        Some(speaker)
+    }
+
+    fn enclosing_dialogue(&self, id: &str) -> Option<String> {
+        let mut current = self.index.parent(id)?;
+
+        loop {
+            if self.index.kind(current) == "Dialogue" {
+                return Some(current.to_owned());
+            }
+
+            current = self.index.parent(current)?;
+        }
+    }
+
+    fn entity_name(&self, placeholder: &str) -> Option<String> {
+        let (technical_name, property) = placeholder.rsplit_once('.')?;
+        if property != "Name" && property != "DisplayName" {
+            return None;
+        }
+
+        let entity = model::model_at(
+            &self.file,
+            self.index.get(Lookup::TechnicalName, technical_name),
+            None,
+        )?;
+
+        self.speaker(&entity.id().to_inner())?.name
+    }
+
+    fn display_text(&self, text: &str) -> String {
+        let localization = self
+            .localization
+            .read()
+            .expect("localization lock to not be poisoned");
+        let text = localization.translate(text);
+
+        if self.emit_bbcode {
+            markup::to_bbcode(text)
+        } else {
+            text.to_owned()
+        }
+    }
+
+    fn enter(&mut self, model: &Model) {
+        let id = model.id().to_inner();
+        self.pass_pins(&id, true);
+
+        self.current_kind = self.index.kind(&id).to_owned();
+        self.current_id = id;
+        self.visit(self.current_id.to_owned());
+        self.choices.clear();
+
+        self.entered.push(Event::NodeEntered {
+            id: self.current_id.to_owned(),
+            kind: self.current_kind.to_owned(),
+        });
+    }
+
+    // NOTE: Only the pins of the connection the flow took count, articy-rs doesn't say which one so it's looked up.
+    //       Jumps and returns from a pushed dialogue don't follow a connection and pass no pins.
+    fn pass_pins(&mut self, target: &str, entered: bool) {
+        let connection = match self
+            .index
+            .outgoing(&self.current_id)
+            .iter()
+            .find(|connection| connection.target == target)
+        {
+            Some(connection) => connection.clone(),
+            None => return,
+        };
+
+        if let Some(expression) = self
+            .index
+            .summary(&self.current_id)
+            .and_then(|json| model::pin_script(json, "OutputPins", &connection.source_pin))
+        {
+            self.entered.push(Event::PinInstructionExecuted {
+                id: self.current_id.to_owned(),
+                pin: connection.source_pin.to_owned(),
+                expression,
+            });
+        }
+
+        if let Some(expression) = self
+            .index
+            .summary(target)
+            .filter(|_| entered)
+            .and_then(|json| model::pin_script(json, "InputPins", &connection.target_pin))
+        {
+            self.entered.push(Event::PinConditionEvaluated {
+                id: target.to_owned(),
+                pin: connection.target_pin,
+                expression,
+                result: None,
+            });
+        }
+    }
+
+    fn pick_hub_exit(
+        &self,
+        outcome: &Outcome,
+        strategy: HubStrategy,
+        rng: &mut Rng,
+    ) -> Option<String> {
+        let options = match outcome {
+            Outcome::WaitingForChoice(options) if self.current_kind == "Hub" => options,
+            _ => return None,
+        };
+
+        let picked = match strategy {
+            HubStrategy::Ask => None,
+            HubStrategy::First => options.first(),
+            HubStrategy::Priority => options.iter().max_by(|left, right| {
+                let priority = |model: &Model| {
+                    self.index
+                        .summary(&model.id().to_inner())
+                        .and_then(|json| model::number_property(json, "Priority"))
+                        .unwrap_or(f64::NEG_INFINITY)
+                };
+
+                priority(left).total_cmp(&priority(right))
+            }),
+            HubStrategy::Random => options.get(rng.below(options.len())),
+        };
+
+        picked.map(|model| model.id().to_inner())
+    }
+
+    fn handle_outcome(&mut self, outcome: Outcome) -> Vec<Event> {
+        match outcome {
+            Outcome::Advanced(model) => {
+                self.enter(model);
+
+                vec![self.model_event(model)]
+            }
+            Outcome::WaitingForChoice(choices) => {
+                let mut events = vec![];
+                let mut options = vec![];
+
+                for choice in choices {
+                    match self.choice(choice, None) {
+                        Some(option) => options.push(option),
+                        None => events.push(Event::Model(self.model_value(choice))),
+                    }
+                }
+
+                if self.include_unavailable {
+                    options = self.with_unavailable(options);
+                }
+
+                self.sort_choices(&mut options);
+
+                // NOTE: Once every option is used up the fallback stays on offer, without one the flow ends here
+                let offered = !options.is_empty();
+                let fallback = options
+                    .iter()
+                    .find(|choice| choice.fallback && choice.available)
+                    .cloned();
+
+                options.retain(|choice| !self.is_used_up(choice));
+
+                if offered && options.is_empty() {
+                    match fallback {
+                        Some(fallback) => options.push(fallback),
+                        None => {
+                            self.choices.clear();
+                            self.running = false;
+
+                            events.push(Event::Stopped {
+                                id: self.dialogue.to_owned(),
+                                reason: StopReason::Ended,
+                            });
+                            return events;
+                        }
+                    }
+                }
+
+                self.choices = options.clone();
+
+                events.push(Event::Choices(options));
+                events
+            }
+            Outcome::Stopped | Outcome::EndOfDialogue => {
+                // NOTE: The last node leaves through an output pin into its parent's
+                if let Some(parent) = self.index.parent(&self.current_id).map(str::to_owned) {
+                    self.pass_pins(&parent, false);
+                }
+
+                self.choices.clear();
+                self.running = false;
+
+                vec![Event::Stopped {
+                    id: self.dialogue.to_owned(),
+                    reason: StopReason::Ended,
+                }]
+            }
+        }
+    }
+
+    // NOTE: A choice is only unavailable when it comes with the input pin condition that failed
+    fn choice(&self, model: &Model, condition: Option<String>) -> Option<Choice> {
+        let target = self
+            .index
+            .outgoing(&model.id().to_inner())
+            .first()
+            .map(|connection| connection.target.to_owned());
+        let target_type = target
+            .as_ref()
+            .map(|target| self.index.kind(target))
+            .filter(|kind| !kind.is_empty())
+            .map(str::to_owned);
+        let json = self
+            .index
+            .summary(&model.id().to_inner())
+            .unwrap_or(&Value::Null);
+
+        match model {
+            Model::DialogueFragment {
+                menu_text,
+                id,
+                text,
+                template,
+                ..
+            } => Some(Choice {
+                id: id.to_inner(),
+                label: self.display_text(if menu_text.is_empty() {
+                    text
+                } else {
+                    menu_text
+                }),
+                fields: self.template_fields(template.as_ref()),
+                colors: self.template_colors(template.as_ref()),
+                template: template
+                    .as_ref()
+                    .map(|template| self.template_value(template)),
+                available: condition.is_none(),
+                condition: condition.unwrap_or_default(),
+                seen: self.visited.contains_key(&id.to_inner()),
+                fallback: model::bool_property(json, &self.fallback_property).unwrap_or_default(),
+                time_limit: model::number_property(json, &self.time_limit_property),
+                target_id: target,
+                target_type,
+            }),
+            _ => None,
+        }
+    }
+
+    // NOTE: articy-rs leaves out branches whose condition failed, they're recovered from the outgoing connections
+    //       and placed in connection order. Options reached through other nodes stay at the end.
+    fn with_unavailable(&self, mut available: Vec<Choice>) -> Vec<Choice> {
+        let mut options = vec![];
+
+        for connection in self.index.outgoing(&self.current_id) {
+            if let Some(position) = available
+                .iter()
+                .position(|choice| choice.id == connection.target)
+            {
+                options.push(available.remove(position));
+                continue;
+            }
+
+            if options.iter().any(|choice| choice.id == connection.target) {
+                continue;
+            }
+
+            let target = match self.index.find(&self.file, &connection.target) {
+                Some(target) => target,
+                None => continue,
+            };
+
+            let condition = self
+                .index
+                .summary(&connection.target)
+                .and_then(|json| model::pin_script(json, "InputPins", &connection.target_pin))
+                .unwrap_or_default();
+
+            if let Some(choice) = self.choice(target, Some(condition)) {
+                options.push(choice);
+            }
+        }
+
+        options.extend(available);
+        options
+    }
+
+    // NOTE: Taken means visited, so saves keep track of it through the visit counts
+    fn is_used_up(&self, choice: &Choice) -> bool {
+        if !choice.seen {
+            return false;
+        }
+
+        self.hide_seen_choices
+            || self
+                .index
+                .summary(&choice.id)
+                .and_then(|json| model::bool_property(json, ONCE_ONLY_PROPERTY))
+                .unwrap_or_default()
+    }
+
+    // NOTE: Sorts are stable, so ties and choices the mode knows nothing about keep the order they came in
+    fn sort_choices(&self, options: &mut [Choice]) {
+        let summary = |choice: &Choice| self.index.summary(&choice.id).unwrap_or(&Value::Null);
+
+        match self.choice_sort_mode {
+            ChoiceSortMode::None => {}
+            ChoiceSortMode::Connection => {
+                let outgoing = self.index.outgoing(&self.current_id);
+                options.sort_by_key(|choice| {
+                    outgoing
+                        .iter()
+                        .position(|connection| connection.target == choice.id)
+                        .unwrap_or(usize::MAX)
+                });
+            }
+            ChoiceSortMode::Priority => options.sort_by(|left, right| {
+                let priority = |choice: &Choice| {
+                    model::number_property(summary(choice), "Priority").unwrap_or(f64::NEG_INFINITY)
+                };
+
+                priority(right).total_cmp(&priority(left))
+            }),
+            // NOTE: Top to bottom, then left to right, as laid out in the flow
+            ChoiceSortMode::Position => options.sort_by(|left, right| {
+                let position = |choice: &Choice| {
+                    let position = &summary(choice)["Properties"]["Position"];
+
+                    (
+                        position["y"].as_f64().unwrap_or(f64::INFINITY),
+                        position["x"].as_f64().unwrap_or(f64::INFINITY),
+                    )
+                };
+
+                let (left, right) = (position(left), position(right));
+                left.0.total_cmp(&right.0).then(left.1.total_cmp(&right.1))
+            }),
+        }
+    }
+
+    fn template_value(&self, template: &impl serde::Serialize) -> Value {
+        let mut value = serde_json::to_value(template).expect("articy-rs to produce proper JSON");
+        self.export.resolve_enums(&mut value);
+
+        value
+    }
+
+    fn template_colors(&self, template: Option<&impl serde::Serialize>) -> Vec<String> {
+        let features = match template {
+            Some(template) => serde_json::to_value(template).unwrap_or_default(),
+            None => return vec![],
+        };
+
+        features
+            .as_object()
+            .into_iter()
+            .flatten()
+            .flat_map(|(feature, properties)| {
+                properties
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .filter(|(property, _)| self.export.is_color(feature, property))
+                    .map(move |(property, _)| format!("{feature}.{property}"))
+            })
+            .collect()
+    }
+
+    // NOTE: Features are left out of the keys, when two of them share a property name the first one wins
+    fn template_fields(&self, template: Option<&impl serde::Serialize>) -> Vec<(String, Value)> {
+        let features = match template {
+            Some(template) if self.flatten_template => self.template_value(template),
+            _ => return vec![],
+        };
+
+        let mut fields: Vec<(String, Value)> = vec![];
+        for properties in features
+            .as_object()
+            .into_iter()
+            .flat_map(|features| features.values())
+        {
+            for (name, value) in properties.as_object().into_iter().flatten() {
+                let key = codegen::words(name).join("_");
+                if !fields.iter().any(|(other, _)| *other == key) {
+                    fields.push((key, value.to_owned()));
+                }
+            }
+        }
+
+        fields
+    }
+
+    fn model_value(&self, model: &Model) -> Value {
+        let mut value = model::model_value(model);
+        if let Some(template) = value.get_mut("Template") {
+            self.export.resolve_enums(template);
+        }
+
+        value
+    }
+
+    fn model_event(&self, model: &Model) -> Event {
+        match model {
+            Model::DialogueFragment {
+                id,
+                text,
+                speaker,
+                technical_name,
+                template,
+                ..
+            } => {
+                let json = self.index.summary(&id.to_inner()).unwrap_or(&Value::Null);
+                let properties = &json["Properties"];
+
+                Event::Line(Line {
+                    id: id.to_inner(),
+                    text: self.display_text(text),
+                    speaker: speaker.to_inner(),
+                    technical_name: technical_name.to_owned(),
+                    fields: self.template_fields(template.as_ref()),
+                    colors: self.template_colors(template.as_ref()),
+                    template: template
+                        .as_ref()
+                        .map(|template| self.template_value(template)),
+                    speaker_details: self.speaker(&speaker.to_inner()),
+                    stage_directions: properties["StageDirections"]
+                        .as_str()
+                        .map(|stage_directions| self.display_text(stage_directions))
+                        .unwrap_or_default(),
+                    color: model::json_color(&properties["Color"]),
+                    voice_over: model::string_property(json, &self.voice_over_property),
+                    voice_over_path: None,
+                    key: None,
+                })
+            }
+            model => {
+                let id = model.id().to_inner();
+                let json = self.index.summary(&id).unwrap_or(&Value::Null);
+                let expression = json["Properties"]["Expression"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned();
+
+                match json["Type"].as_str() {
+                    Some("Hub") => Event::HubEntered(id),
+                    Some("Jump") => Event::JumpFollowed {
+                        from: id,
+                        target: json["Properties"]["Target"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_owned(),
+                    },
+                    Some("Condition") => Event::ConditionEvaluated {
+                        id,
+                        expression,
+                        result: None,
+                    },
+                    Some("Instruction") => Event::InstructionExecuted { id, expression },
+                    _ => Event::Model(self.model_value(model)),
+                }
+            }
+        }
+    }
+}
+
+impl Environment for ScriptEnvironment<'_> {
+    fn get(&mut self, name: &str) -> Result<Value, Error> {
+        state::to_json(
+            self.interpreter
+                .get_state(name)
+                .map_err(|_| Error::FailedToGetState(name.to_owned()))?,
+        )
+    }
+
+    fn set(&mut self, name: &str, value: Value) -> Result<(), Error> {
+        self.interpreter
+            .set_state(name, state::from_json(&value)?)
+            .map_err(|_| Error::FailedToSetState(name.to_owned()))
+    }
+
+    fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Value, Error> {
+        let function = self
+            .functions
+            .get_mut(name)
+            .ok_or_else(|| Error::FunctionNotRegistered(name.to_owned()))?;
+
+        function(arguments)
+    }
+}
+
+impl StopReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StopReason::Ended => "ended",
+            StopReason::Stopped => "stopped",
+            StopReason::Error => "error",
+        }
+    }
+}
+
+impl FromStr for ChoiceSortMode {
+    type Err = Error;
+
+    fn from_str(mode: &str) -> Result<Self, Error> {
+        match mode {
+            "none" => Ok(ChoiceSortMode::None),
+            "" | "connection" => Ok(ChoiceSortMode::Connection),
+            "priority" => Ok(ChoiceSortMode::Priority),
+            "position" => Ok(ChoiceSortMode::Position),
+            _ => Err(Error::InvalidOption(format!("choice sort mode {mode}"))),
+        }
+    }
+}
+
+impl FromStr for HubStrategy {
+    type Err = Error;
+
+    fn from_str(strategy: &str) -> Result<Self, Error> {
+        match strategy {
+            "" | "ask" => Ok(HubStrategy::Ask),
+            "first" => Ok(HubStrategy::First),
+            "priority" => Ok(HubStrategy::Priority),
+            "random" => Ok(HubStrategy::Random),
+            _ => Err(Error::InvalidOption(format!("hub strategy {strategy}"))),
+        }
+    }
+}
+
+fn timestamp() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64())
+        .unwrap_or_default()
+}
+
+pub fn speaker_details(entity: &Model) -> Speaker {
+    let json = model::model_json(entity);
+    let properties = &json["Properties"];
+
+    Speaker {
+        name: properties["DisplayName"].as_str().map(str::to_owned),
+        color: model::json_color(&properties["Color"]),
+        external_id: entity.external_id().to_inner(),
+        // NOTE: Articy uses the null id for an unset preview image
+        portrait: properties["PreviewImage"]["Asset"]
+            .as_str()
+            .filter(|asset| *asset != "0x0000000000000000")
+            .map(str::to_owned),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::Library;
+    use serde_json::json;
+    use std::thread;
+
+    const PROJECT: &str = "0x0100000000000001";
+    const DIALOGUE: &str = "0x0100000000000010";
+    const GREETING: &str = "0x0100000000000011";
+    const ACCEPT: &str = "0x0100000000000012";
+    const REFUSE: &str = "0x0100000000000013";
+    const HERO: &str = "0x0100000000000020";
+
+    // NOTE: A dialogue laid out the way Articy exports it, a greeting followed by a choice between two replies
+    fn export() -> Vec<u8> {
+        let pin = |id: &str, owner: &str, targets: &[(&str, &str)]| {
+            json!({
+                "Text": "",
+                "Id": id,
+                "Owner": owner,
+                "Connections": targets
+                    .iter()
+                    .map(|(target, pin)| json!({"Label": "", "TargetPin": pin, "Target": target}))
+                    .collect::<Vec<Value>>(),
+            })
+        };
+        let fragment = |id: &str, name: &str, text: &str, menu_text: &str, pins: [Value; 2]| {
+            let [input, output] = pins;
+
+            json!({
+                "Type": "DialogueFragment",
+                "Properties": {
+                    "TechnicalName": name,
+                    "Id": id,
+                    "Parent": DIALOGUE,
+                    "DisplayName": "",
+                    "Attachments": [],
+                    "MenuText": menu_text,
+                    "StageDirections": "",
+                    "Speaker": HERO,
+                    "Text": text,
+                    "ExternalId": "",
+                    "Color": {"r": 0.5, "g": 0.5, "b": 0.5},
+                    "Position": {"x": 0.0, "y": 0.0},
+                    "ZIndex": 0.0,
+                    "Size": {"w": 150.0, "h": 100.0},
+                    "ShortId": 0,
+                    "InputPins": [input],
+                    "OutputPins": [output],
+                },
+            })
+        };
+
+        let models = vec![
+            json!({
+                "Type": "Dialogue",
+                "Properties": {
+                    "TechnicalName": "Dlg_Meeting",
+                    "Id": DIALOGUE,
+                    "Parent": PROJECT,
+                    "DisplayName": "Meeting",
+                    "Attachments": [],
+                    "Text": "",
+                    "ExternalId": "",
+                    "Color": {"r": 0.5, "g": 0.5, "b": 0.5},
+                    "Position": {"x": 0.0, "y": 0.0},
+                    "ZIndex": 0.0,
+                    "Size": {"w": 400.0, "h": 300.0},
+                    "ShortId": 0,
+                    "InputPins": [pin("0x0100000000000030", DIALOGUE, &[(GREETING, "0x0100000000000032")])],
+                    "OutputPins": [pin("0x0100000000000031", DIALOGUE, &[])],
+                },
+            }),
+            fragment(
+                GREETING,
+                "DFr_Greeting",
+                "Well met, traveller.",
+                "",
+                [
+                    pin("0x0100000000000032", GREETING, &[]),
+                    pin(
+                        "0x0100000000000033",
+                        GREETING,
+                        &[
+                            (ACCEPT, "0x0100000000000034"),
+                            (REFUSE, "0x0100000000000036"),
+                        ],
+                    ),
+                ],
+            ),
+            fragment(
+                ACCEPT,
+                "DFr_Accept",
+                "Gladly.",
+                "Accept",
+                [
+                    pin("0x0100000000000034", ACCEPT, &[]),
+                    pin(
+                        "0x0100000000000035",
+                        ACCEPT,
+                        &[(DIALOGUE, "0x0100000000000031")],
+                    ),
+                ],
+            ),
+            fragment(
+                REFUSE,
+                "DFr_Refuse",
+                "Not today.",
+                "Refuse",
+                [
+                    pin("0x0100000000000036", REFUSE, &[]),
+                    pin(
+                        "0x0100000000000037",
+                        REFUSE,
+                        &[(DIALOGUE, "0x0100000000000031")],
+                    ),
+                ],
+            ),
+            json!({
+                "Type": "Entity",
+                "Properties": {
+                    "TechnicalName": "Chr_Hero",
+                    "Id": HERO,
+                    "Parent": PROJECT,
+                    "DisplayName": "Hero",
+                    "Attachments": [],
+                    "Text": "",
+                    "ExternalId": "",
+                    "Color": {"r": 1.0, "g": 0.0, "b": 0.0},
+                    "ShortId": 0,
+                    "PreviewImage": {
+                        "ViewBox": {"x": 0.0, "y": 0.0, "w": 0.0, "h": 0.0},
+                        "Mode": "FromAsset",
+                        "Asset": "0x0000000000000000",
+                    },
+                },
+            }),
+        ];
+
+        let node = |id: &str, name: &str, kind: &str, children: Vec<Value>| json!({"Id": id, "TechnicalName": name, "Type": kind, "Children": children});
+
+        serde_json::to_vec(&json!({
+            "Settings": {
+                "set_Localization": "False",
+                "set_TextFormatter": "Unformatted",
+                "set_UseScriptSupport": "True",
+                "ExportVersion": "1.0",
+            },
+            "Project": {
+                "Name": "Test",
+                "DetailName": "",
+                "Guid": "00000000-0000-0000-0000-000000000001",
+                "TechnicalName": "Test",
+            },
+            "GlobalVariables": [{
+                "Namespace": "Game",
+                "Description": "",
+                "Variables": [{"Variable": "Gold", "Type": "Integer", "Value": "0", "Description": ""}],
+            }],
+            "ObjectDefinitions": [],
+            "Packages": [{
+                "Name": "Default",
+                "Description": "",
+                "IsDefaultPackage": true,
+                "Models": models,
+            }],
+            "ScriptMethods": [],
+            "Hierarchy": node(PROJECT, "Test", "Project", vec![
+                node(DIALOGUE, "Dlg_Meeting", "Dialogue", vec![
+                    node(GREETING, "DFr_Greeting", "DialogueFragment", vec![]),
+                    node(ACCEPT, "DFr_Accept", "DialogueFragment", vec![]),
+                    node(REFUSE, "DFr_Refuse", "DialogueFragment", vec![]),
+                ]),
+                node(HERO, "Chr_Hero", "Entity", vec![]),
+            ]),
+        }))
+        .unwrap()
+    }
+
+    fn library() -> Library {
+        let mut library = Library::default();
+        library.load(&export()).expect("fixture to load");
+
+        library
+    }
+
+    fn lines(events: &[Event]) -> Vec<(&str, &str)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Line(line) => Some((line.id.as_str(), line.text.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn choices(events: &[Event]) -> Vec<(&str, &str)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Choices(choices) => Some(choices),
+                _ => None,
+            })
+            .flatten()
+            .map(|choice| (choice.id.as_str(), choice.label.as_str()))
+            .collect()
+    }
+
+    fn ended() -> Event {
+        Event::Stopped {
+            id: DIALOGUE.to_owned(),
+            reason: StopReason::Ended,
+        }
+    }
+
+    #[test]
+    fn plays_a_dialogue_to_the_end() {
+        let mut session = Session::new(library().shared().unwrap());
+
+        let events = session.start(DIALOGUE).unwrap();
+        assert_eq!(events.first(), Some(&Event::Started(DIALOGUE.to_owned())));
+        assert!(session.is_running());
+
+        let events = session.advance().unwrap();
+        assert_eq!(lines(&events), [(GREETING, "Well met, traveller.")]);
+
+        let events = session.advance().unwrap();
+        assert_eq!(choices(&events), [(ACCEPT, "Accept"), (REFUSE, "Refuse")]);
+
+        let events = session.choose(REFUSE).unwrap();
+        assert_eq!(lines(&events), [(REFUSE, "Not today.")]);
+
+        let events = session.advance().unwrap();
+        assert!(events.contains(&ended()));
+        assert!(!session.is_running());
+    }
+
+    #[test]
+    fn reports_changed_variables() {
+        let mut session = Session::new(library().shared().unwrap());
+
+        let events = session.set_state("Game.Gold", &json!(5)).unwrap();
+        assert_eq!(
+            events,
+            [Event::StateChanged {
+                key: "Game.Gold".to_owned(),
+                old: json!(0),
+                new: json!(5),
+            }]
+        );
+        assert_eq!(session.get_state("Game.Gold").unwrap(), json!(5));
+    }
+
+    // NOTE: Each session plays on its own flow and variables, localization and overrides are the Library's for all
+    #[test]
+    fn sessions_on_other_threads_share_the_library() {
+        let mut library = library();
+        library
+            .load_localization(
+                "de",
+                r#"{"Well met, traveller.": "Sei gegrüßt, Reisender."}"#.as_bytes(),
+            )
+            .unwrap();
+        library.set_locale("de").unwrap();
+        library
+            .set_entity_override(HERO, "DisplayName", json!("Stranger"))
+            .unwrap();
+
+        let threads = (0..8)
+            .map(|position: i64| {
+                let shared = library.shared().unwrap();
+
+                thread::spawn(move || {
+                    let mut session = Session::new(shared);
+                    let reply = if position % 2 == 0 { ACCEPT } else { REFUSE };
+
+                    session.start(DIALOGUE).unwrap();
+                    session.set_state("Game.Gold", &json!(position)).unwrap();
+                    let greeting = session.advance().unwrap();
+                    session.advance().unwrap();
+                    let chosen = session.choose(reply).unwrap();
+                    let end = session.advance().unwrap();
+                    let gold = session.get_state("Game.Gold").unwrap();
+
+                    (position, reply, greeting, chosen, end, gold)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            let (position, reply, greeting, chosen, end, gold) =
+                thread.join().expect("session not to panic");
+
+            let line = greeting
+                .iter()
+                .find_map(|event| match event {
+                    Event::Line(line) => Some(line),
+                    _ => None,
+                })
+                .expect("greeting to be a line");
+            assert_eq!(line.text, "Sei gegrüßt, Reisender.");
+            assert_eq!(
+                line.speaker_details
+                    .as_ref()
+                    .and_then(|speaker| speaker.name.as_deref()),
+                Some("Stranger")
+            );
+
+            assert_eq!(lines(&chosen).first().map(|(id, _)| *id), Some(reply));
+            assert!(end.contains(&ended()));
+            assert_eq!(gold, json!(position));
+        }
+
+        let mut session = Session::new(library.shared().unwrap());
+        assert_eq!(session.get_state("Game.Gold").unwrap(), json!(0));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{
+    library::SharedLibrary,
+    random::Rng,
+    script,
+    session::{Event, Session},
+};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+// NOTE: A run that hasn't ended after this many steps is reported as stuck
+const MAX_STEPS: usize = 10_000;
+
+// NOTE: Plays a dialogue over and over with random choices on fresh variables, reporting what went wrong on the way
+//       and which endings never got reached. Registered functions aren't available to the runs.
+pub fn soak_test(library: SharedLibrary, id: &str, iterations: usize, seed: u64) -> Value {
+    let mut rng = Rng::new(seed);
+    let mut errors = vec![];
+    let mut script_failures = BTreeSet::new();
+    let mut endings = BTreeMap::<String, u64>::new();
+
+    for iteration in 0..iterations {
+        let mut session = Session::new(library.clone());
+        session.set_rng_seed(rng.next_u64());
+
+        let mut current = id.to_owned();
+        let mut result = session.start(id);
+        let mut steps = 0;
+
+        loop {
+            let events = match result {
+                Ok(events) => events,
+                Err(error) => {
+                    errors.push(json!({
+                        "iteration": iteration,
+                        "id": current,
+                        "message": error.to_string(),
+                    }));
+                    break;
+                }
+            };
+
+            for event in &events {
+                match event {
+                    Event::NodeEntered { id, .. } => current = id.to_owned(),
+                    Event::ConditionEvaluated {
+                        id,
+                        expression,
+                        result: None,
+                    }
+                    | Event::PinConditionEvaluated {
+                        id,
+                        expression,
+                        result: None,
+                        ..
+                    } => {
+                        script_failures.insert((id.to_owned(), expression.to_owned()));
+                    }
+                    Event::InstructionExecuted { id, expression }
+                    | Event::PinInstructionExecuted { id, expression, .. } => {
+                        if script::parse(expression).is_err() {
+                            script_failures.insert((id.to_owned(), expression.to_owned()));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if !session.is_running() {
+                *endings.entry(current.to_owned()).or_default() += 1;
+                break;
+            }
+
+            steps += 1;
+            if steps > MAX_STEPS {
+                errors.push(json!({
+                    "iteration": iteration,
+                    "id": current,
+                    "message": format!("still running after {MAX_STEPS} steps"),
+                }));
+                break;
+            }
+
+            let available = session
+                .choices()
+                .iter()
+                .filter(|choice| choice.available)
+                .map(|choice| choice.id.to_owned())
+                .collect::<Vec<String>>();
+
+            result = if available.is_empty() {
+                session.advance()
+            } else {
+                session.choose(&available[rng.below(available.len())])
+            };
+        }
+    }
+
+    let unreachable = dialogue_endings(&library, id)
+        .into_iter()
+        .filter(|ending| !endings.contains_key(ending))
+        .collect::<Vec<String>>();
+
+    json!({
+        "iterations": iterations,
+        "errors": errors,
+        "script_failures": script_failures
+            .into_iter()
+            .map(|(id, expression)| json!({"id": id, "expression": expression}))
+            .collect::<Vec<Value>>(),
+        "endings": endings,
+        "unreachable_endings": unreachable,
+    })
+}
+
+// NOTE: Nodes inside the dialogue that lead nowhere, or only out of the dialogue itself
+fn dialogue_endings(library: &SharedLibrary, id: &str) -> Vec<String> {
+    let mut endings = vec![];
+    let mut pending = library.index.children(id).to_vec();
+
+    while let Some(node) = pending.pop() {
+        let children = library.index.children(&node);
+
+        if children.is_empty()
+            && library
+                .index
+                .outgoing(&node)
+                .iter()
+                .all(|connection| connection.target == id)
+        {
+            endings.push(node.to_owned());
+        }
+
+        pending.extend(children.iter().cloned());
+    }
+
+    endings.sort();
+    endings
+}
@@ -0,0 +1,51 @@
+use crate::error::Error;
+use articy::StateValue;
+use serde_json::{Number, Value};
+
+pub fn to_json(value: &StateValue) -> Result<Value, Error> {
+    Ok(match value {
+        StateValue::String(string) => Value::String(string.to_owned()),
+        StateValue::Float(float) => Number::from_f64(*float)
+            .map(Value::Number)
+            .ok_or_else(|| Error::UnsupportedType(format!("Float ({float})")))?,
+        StateValue::Int(int) => Value::from(*int),
+        StateValue::Boolean(bool) => Value::Bool(*bool),
+        StateValue::Empty => Value::Null,
+        StateValue::Tuple(values) => Value::Array(
+            values
+                .iter()
+                .map(to_json)
+                .collect::<Result<Vec<Value>, Error>>()?,
+        ),
+    })
+}
+
+pub fn from_json(value: &Value) -> Result<StateValue, Error> {
+    Ok(match value {
+        Value::Null => StateValue::Empty,
+        Value::Bool(bool) => StateValue::Boolean(*bool),
+        Value::Number(number) => match number.as_i64() {
+            Some(int) => StateValue::Int(int),
+            None => StateValue::Float(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(string) => StateValue::String(string.to_owned()),
+        Value::Array(values) => StateValue::Tuple(
+            values
+                .iter()
+                .map(from_json)
+                .collect::<Result<Vec<StateValue>, Error>>()?,
+        ),
+        // NOTE: Stored as a tuple of `(key, value)` tuples, so it reads back as an array of `[key, value]` pairs
+        Value::Object(object) => StateValue::Tuple(
+            object
+                .iter()
+                .map(|(key, value)| {
+                    Ok(StateValue::Tuple(vec![
+                        StateValue::String(key.to_owned()),
+                        from_json(value)?,
+                    ]))
+                })
+                .collect::<Result<Vec<StateValue>, Error>>()?,
+        ),
+    })
+}
@@ -0,0 +1,108 @@
+use crate::{error::Error, library::Library, model::model_json};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default)]
+struct Tally {
+    name: Value,
+    lines: u64,
+    words: u64,
+}
+
+// NOTE: Counts everything under `scope`, the scope itself included, or the whole export without one. Words are
+//       counted in the current locale, so budgets can be made per language. `per_dialogue` also lists dialogues
+//       the scope sits inside of.
+pub fn statistics(library: &Library, scope: Option<&str>) -> Result<Value, Error> {
+    let models = match scope {
+        Some(scope) => {
+            let mut models = vec![library.find_model(scope)?];
+            let mut pending = library.children(scope)?.to_vec();
+
+            while let Some(id) = pending.pop() {
+                pending.extend(library.children(&id)?.iter().cloned());
+                models.push(library.find_model(&id)?);
+            }
+
+            models
+        }
+        None => library.all_models()?,
+    };
+
+    let mut dialogues = BTreeMap::<String, Tally>::new();
+    let mut speakers = BTreeMap::<String, Tally>::new();
+    let mut dialogue_count = 0;
+    let mut fragments = 0;
+    let mut words = 0;
+    let mut branches = 0;
+    let mut options = 0;
+
+    for model in models {
+        let json = model_json(model);
+        let id = model.id().to_inner();
+
+        if json["Type"] == "Dialogue" {
+            dialogue_count += 1;
+            dialogues.entry(id.to_owned()).or_default().name =
+                json["Properties"]["DisplayName"].to_owned();
+        }
+
+        if json["Type"] == "DialogueFragment" {
+            let properties = &json["Properties"];
+            let count = library.word_count(properties["Text"].as_str().unwrap_or_default()) as u64;
+
+            fragments += 1;
+            words += count;
+
+            let speaker = properties["Speaker"].as_str().unwrap_or_default();
+            let tally = speakers.entry(speaker.to_owned()).or_insert_with(|| Tally {
+                name: display_name(library, speaker),
+                ..Tally::default()
+            });
+            tally.lines += 1;
+            tally.words += count;
+
+            if let Some((dialogue, name)) = library.enclosing_dialogue(&id) {
+                let tally = dialogues.entry(dialogue).or_default();
+                tally.name = Value::String(name);
+                tally.lines += 1;
+                tally.words += count;
+            }
+        }
+
+        let exits = library.exits(&id)?.len();
+
+        if exits > 1 {
+            branches += 1;
+            options += exits;
+        }
+    }
+
+    Ok(json!({
+        "dialogues": dialogue_count,
+        "fragments": fragments,
+        "words": words,
+        "branches": branches,
+        "average_choice_width": if branches == 0 { 0.0 } else { options as f64 / branches as f64 },
+        "speakers": tallies(speakers),
+        "per_dialogue": tallies(dialogues),
+    }))
+}
+
+fn display_name(library: &Library, id: &str) -> Value {
+    library
+        .find_model(id)
+        .map(|model| model_json(model)["Properties"]["DisplayName"].to_owned())
+        .unwrap_or_default()
+}
+
+fn tallies(tallies: BTreeMap<String, Tally>) -> Value {
+    tallies
+        .into_iter()
+        .map(|(id, tally)| {
+            (
+                id,
+                json!({"name": tally.name, "lines": tally.lines, "words": tally.words}),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,209 @@
+use crate::{error::Error, library::Library, model::model_json, script};
+use serde_json::Value;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub severity: Severity,
+    pub id: String,
+    pub message: String,
+}
+
+// NOTE: Names expresso gives a meaning of its own, they're never global variables
+const SCRIPT_KEYWORDS: [&str; 3] = ["seen", "unseen", "fallback"];
+
+// NOTE: Articy uses the null id for references that were left empty
+const NULL_ID: &str = "0x0000000000000000";
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+}
+
+// NOTE: Lints the whole export, errors being things that break at runtime and warnings things a writer likely missed
+pub fn validate(library: &Library) -> Result<Vec<Issue>, Error> {
+    let mut issues = vec![];
+    let variables = library
+        .global_variables()?
+        .iter()
+        .flat_map(|namespace| {
+            namespace
+                .variables
+                .iter()
+                .map(move |variable| format!("{}.{}", namespace.name, variable.name))
+        })
+        .collect::<HashSet<String>>();
+
+    for model in library.all_models()? {
+        let json = model_json(model);
+        let id = model.id().to_inner();
+        let properties = &json["Properties"];
+        let mut issue = |severity, message: String| {
+            issues.push(Issue {
+                severity,
+                id: id.to_owned(),
+                message,
+            })
+        };
+
+        if json["Type"] == "DialogueFragment" {
+            if let Some(speaker) = properties["Speaker"]
+                .as_str()
+                .filter(|speaker| *speaker != NULL_ID)
+            {
+                if library.find_model(speaker).is_err() {
+                    issue(
+                        Severity::Error,
+                        format!("speaker {speaker:?} doesn't exist in the export"),
+                    );
+                }
+            }
+
+            if is_choice(library, &id)? && is_empty(&properties["MenuText"]) {
+                if is_empty(&properties["Text"]) {
+                    issue(
+                        Severity::Warning,
+                        "choice has neither a menu text nor a text".to_owned(),
+                    );
+                } else {
+                    issue(
+                        Severity::Info,
+                        "choice has no menu text, its whole text is shown instead".to_owned(),
+                    );
+                }
+            }
+        }
+
+        for (source, is_condition) in scripts(&json) {
+            let used = if is_condition {
+                script::parse_expression(&source).map(|expression| owned(expression.variables()))
+            } else {
+                script::parse(&source).map(|statements| {
+                    statements
+                        .iter()
+                        .flat_map(|statement| owned(statement.variables()))
+                        .collect()
+                })
+            };
+
+            match used {
+                Ok(used) => {
+                    for variable in used {
+                        if !variables.contains(&variable)
+                            && !SCRIPT_KEYWORDS.contains(&variable.as_str())
+                        {
+                            issue(
+                                Severity::Error,
+                                format!("{source:?} uses undefined variable {variable:?}"),
+                            );
+                        }
+                    }
+                }
+                Err(error) => issue(
+                    Severity::Error,
+                    format!("{source:?} doesn't parse: {error}"),
+                ),
+            }
+        }
+
+        // NOTE: Only nodes inside a flow can be unreachable, top level ones get started from the game
+        let in_flow = library
+            .parent(&id)?
+            .and_then(|parent| library.find_model(parent).ok())
+            .map_or(false, |parent| {
+                matches!(
+                    model_json(parent)["Type"].as_str(),
+                    Some("Dialogue" | "FlowFragment")
+                )
+            });
+
+        if in_flow
+            && properties.get("InputPins").is_some()
+            && library.incoming_connections(&id)?.is_empty()
+        {
+            issue(
+                Severity::Warning,
+                "nothing connects to this node, it can never be reached".to_owned(),
+            );
+        }
+    }
+
+    for entry in library.translation_entries()? {
+        let missing = library.missing_translations(&entry);
+        if !missing.is_empty() {
+            issues.push(Issue {
+                severity: Severity::Warning,
+                id: entry.key.split('.').next().unwrap_or_default().to_owned(),
+                message: format!(
+                    "{} has no translation for {}",
+                    entry.key,
+                    missing.join(", ")
+                ),
+            });
+        }
+    }
+
+    Ok(issues)
+}
+
+// NOTE: A fragment is offered as a choice when the node before it branches out to several nodes
+fn is_choice(library: &Library, id: &str) -> Result<bool, Error> {
+    for connection in library.incoming_connections(id)? {
+        if library.exits(&connection.source)?.len() > 1 {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+// NOTE: Conditions are expressions, instructions are statements
+fn scripts(json: &Value) -> Vec<(String, bool)> {
+    let properties = &json["Properties"];
+    let mut scripts = vec![];
+
+    match json["Type"].as_str() {
+        Some("Condition") => {
+            scripts.extend(text(&properties["Expression"]).map(|text| (text, true)))
+        }
+        Some("Instruction") => {
+            scripts.extend(text(&properties["Expression"]).map(|text| (text, false)))
+        }
+        _ => {}
+    }
+
+    for (pins, is_condition) in [("InputPins", true), ("OutputPins", false)] {
+        for pin in properties[pins].as_array().into_iter().flatten() {
+            scripts.extend(text(&pin["Text"]).map(|text| (text, is_condition)));
+        }
+    }
+
+    scripts
+}
+
+fn text(value: &Value) -> Option<String> {
+    value
+        .as_str()
+        .filter(|text| !text.trim().is_empty())
+        .map(str::to_owned)
+}
+
+fn is_empty(value: &Value) -> bool {
+    text(value).is_none()
+}
+
+fn owned(names: Vec<&str>) -> Vec<String> {
+    names.into_iter().map(str::to_owned).collect()
+}